@@ -0,0 +1,101 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dxclparser::{Engine, Parser, Spot, SpotRef};
+
+const DXSPIDER: &str = include_str!("data/dxspider.txt");
+const RBN: &str = include_str!("data/rbn.txt");
+
+fn engines() -> [(&'static str, Parser); 2] {
+    [
+        ("tokenizer", Parser::new()),
+        (
+            "regex",
+            Parser::builder().engine(Engine::Regex).build().unwrap(),
+        ),
+    ]
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for (corpus, lines) in [("dxspider", DXSPIDER), ("rbn", RBN)] {
+        group.throughput(Throughput::Elements(lines.lines().count() as u64));
+
+        for (engine, parser) in engines() {
+            group.bench_with_input(BenchmarkId::new(engine, corpus), lines, |b, lines| {
+                b.iter(|| {
+                    for line in lines.lines() {
+                        let _ = parser.parse(line);
+                    }
+                })
+            });
+            group.bench_with_input(
+                BenchmarkId::new(format!("{}-ref", engine), corpus),
+                lines,
+                |b, lines| {
+                    b.iter(|| {
+                        for line in lines.lines() {
+                            let _ = parser.parse_ref(line);
+                        }
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_parse_rbn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_rbn");
+
+    // The comments of the RBN spots, as they would be handed to the parser after parsing the DX spot
+    let comments: Vec<String> = RBN
+        .lines()
+        .filter_map(|line| match dxclparser::parse(line) {
+            Ok(Spot::DX(dx)) => dx.comment,
+            _ => None,
+        })
+        .collect();
+    group.throughput(Throughput::Elements(comments.len() as u64));
+
+    for (engine, parser) in engines() {
+        group.bench_with_input(BenchmarkId::new(engine, "rbn"), &comments, |b, comments| {
+            b.iter(|| {
+                for comment in comments {
+                    let _ = parser.parse_rbn(comment);
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_rbn_spot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rbn_spot");
+    group.throughput(Throughput::Elements(RBN.lines().count() as u64));
+
+    // Complete ingest of a RBN spot: the DX spot followed by its comment
+    for (engine, parser) in engines() {
+        group.bench_with_input(BenchmarkId::new(engine, "rbn"), RBN, |b, lines| {
+            b.iter(|| {
+                for line in lines.lines() {
+                    if let Ok(SpotRef::DX(dx)) = parser.parse_ref(line) {
+                        if let Some(comment) = dx.comment {
+                            let _ = parser.parse_rbn_ref(&comment);
+                        }
+                    }
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_parse_rbn, bench_rbn_spot);
+criterion_main!(benches);
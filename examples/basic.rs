@@ -13,7 +13,7 @@ fn main() {
         eprintln!("Usage: {} <spot>", args[0]);
         retval = 1;
     } else {
-        match dxclparser::parse(args[1].trim().trim_end_matches('\u{0007}')) {
+        match dxclparser::parse(&dxclparser::sanitize(&args[1]).line) {
             Ok(spot) => {
                 println!("{}", spot.to_json());
                 retval = 0;
@@ -0,0 +1,36 @@
+use std::env;
+use std::process;
+
+/// Connect to a cluster server given as commandline arguments and log in with the given callsign.
+/// Output the received spots in their json format.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 3 {
+        eprintln!("Invalid number of arguments.");
+        eprintln!("Usage: {} <host:port> <call> [command]...", args[0]);
+        process::exit(1);
+    }
+
+    let mut builder = dxclparser::ClusterClient::builder(&args[2]);
+    for command in args[3..].iter() {
+        builder = builder.command(command);
+    }
+
+    let mut client = match builder.connect(&args[1]) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Failed to connect ({})", e);
+            process::exit(1);
+        }
+    };
+
+    for spot in client.by_ref().flatten() {
+        println!("{}", spot.to_json());
+    }
+
+    if let Some(e) = client.take_error() {
+        eprintln!("Connection failed ({})", e);
+        process::exit(1);
+    }
+}
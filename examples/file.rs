@@ -14,12 +14,14 @@ fn main() {
     } else {
         let filepath = Path::new(&args[1]);
         let file = File::open(filepath).expect("Failed to open file");
-        let reader = io::BufReader::new(file).lines();
+        let reader = io::BufReader::new(file).split(b'\n');
 
         for line in reader {
-            match dxclparser::parse(line.unwrap().trim().trim_end_matches('\u{0007}')) {
-                Ok(spot) => {
-                    println!("{}", spot.to_json());
+            let line = line.unwrap();
+
+            match dxclparser::parse_bytes(&dxclparser::sanitize_bytes(&line).line) {
+                Ok(res) => {
+                    println!("{}", res.spot.to_json());
                 }
                 Err(e) => {
                     eprintln!("Failed to parse spot ({})", e);
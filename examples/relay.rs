@@ -0,0 +1,38 @@
+use std::env;
+use std::process;
+
+/// Connect to a cluster server given as commandline arguments and log in with the given callsign.
+/// Re-broadcast the received spots to the loggers connecting to the given local address.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() != 4 {
+        eprintln!("Invalid number of arguments.");
+        eprintln!("Usage: {} <host:port> <call> <listen addr>", args[0]);
+        process::exit(1);
+    }
+
+    let relay = match dxclparser::Relay::builder().node(&args[2]).bind(&args[3]) {
+        Ok(relay) => relay,
+        Err(e) => {
+            eprintln!("Failed to listen on {} ({})", args[3], e);
+            process::exit(1);
+        }
+    };
+
+    let mut client = match dxclparser::ClusterClient::connect(&args[1], &args[2]) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Failed to connect ({})", e);
+            process::exit(1);
+        }
+    };
+
+    let count = relay.forward(client.by_ref());
+    eprintln!("Forwarded {} spots", count);
+
+    if let Some(e) = client.take_error() {
+        eprintln!("Connection failed ({})", e);
+        process::exit(1);
+    }
+}
@@ -13,7 +13,7 @@ fn main() {
         eprintln!("Usage: {} <spot>", args[0]);
         retval = 1;
     } else {
-        match dxclparser::parse(args[1].trim().trim_end_matches('\u{0007}')) {
+        match dxclparser::parse(&dxclparser::sanitize(&args[1]).line) {
             Ok(spot) => {
                 match spot {
                     dxclparser::Spot::DX(dx) => println!("Found a DX spot from {}", dx.call_de),
@@ -26,6 +26,9 @@ fn main() {
                     dxclparser::Spot::ToLocal(tolocal) => {
                         println!("Found a ToLocal spot from {}", tolocal.call_de)
                     }
+                    dxclparser::Spot::Custom(custom) => {
+                        println!("Found a custom spot of kind {}", custom.kind)
+                    }
                 }
                 retval = 0;
             }
@@ -0,0 +1,507 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::client::ClientBuilder;
+use crate::error::ClientError;
+use crate::types::Spot;
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// Default time to wait before connecting again to a source whose connection ended
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Creates the client configuration for each connection to a source
+type Connector = Box<dyn Fn() -> ClientBuilder + Send + Sync>;
+
+/// Spot received from one of the sources of an [`Aggregator`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourcedSpot {
+    /// Name of the source the spot was received from
+    pub source: String,
+
+    /// Time the spot was received
+    pub received: SystemTime,
+
+    /// Received spot
+    pub spot: Spot,
+}
+
+/// State of the connection to a source
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SourceState {
+    /// Connecting and logging in
+    Connecting,
+
+    /// Logged in and receiving spots
+    Connected,
+
+    /// Connection ended, waiting to connect again
+    Waiting,
+
+    /// Connection ended, no further attempts are made
+    Stopped,
+}
+
+/// Health of a source of an [`Aggregator`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceHealth {
+    /// Name of the source
+    pub name: String,
+
+    /// Address of the cluster
+    pub addr: String,
+
+    /// Current state of the connection
+    pub state: SourceState,
+
+    /// Number of successful logins
+    pub connects: u32,
+
+    /// Number of received spots
+    pub spots: u64,
+
+    /// Number of received lines that could not be parsed
+    pub unparsed: u64,
+
+    /// Time the last spot was received
+    pub last_spot: Option<SystemTime>,
+
+    /// Error that ended the last connection or connection attempt
+    pub last_error: Option<String>,
+}
+
+/// Builder for an [`Aggregator`]
+pub struct AggregatorBuilder {
+    sources: Vec<(String, String, Connector)>,
+    reconnect_delay: Option<Duration>,
+}
+
+impl AggregatorBuilder {
+    /// Create a builder of an aggregator without sources.
+    pub fn new() -> Self {
+        AggregatorBuilder {
+            sources: Vec::new(),
+            reconnect_delay: Some(DEFAULT_RECONNECT_DELAY),
+        }
+    }
+
+    /// Add a cluster to receive spots from.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name`: Name the spots of the source are tagged with, e.g. the callsign of the cluster
+    /// * `addr`: Address of the cluster
+    /// * `client`: Creates the configuration of the client for each connection, e.g. with the parser of the flavour of the cluster
+    pub fn source<F>(mut self, name: &str, addr: &str, client: F) -> Self
+    where
+        F: Fn() -> ClientBuilder + Send + Sync + 'static,
+    {
+        self.sources
+            .push((String::from(name), String::from(addr), Box::new(client)));
+        self
+    }
+
+    /// Set the time to wait before connecting again to a source, by default [`DEFAULT_RECONNECT_DELAY`].
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = Some(delay);
+        self
+    }
+
+    /// Connect to each source only once, the merged stream ends as soon as all connections ended.
+    pub fn no_reconnect(mut self) -> Self {
+        self.reconnect_delay = None;
+        self
+    }
+
+    /// Start receiving from all sources, each connected by its own thread.
+    pub fn start(self) -> Aggregator {
+        let (sender, spots) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            stopped: Mutex::new(false),
+            wake: Condvar::new(),
+            order: Mutex::new(()),
+            health: self
+                .sources
+                .iter()
+                .map(|(name, addr, _)| {
+                    Mutex::new(SourceHealth {
+                        name: name.clone(),
+                        addr: addr.clone(),
+                        state: SourceState::Connecting,
+                        connects: 0,
+                        spots: 0,
+                        unparsed: 0,
+                        last_spot: None,
+                        last_error: None,
+                    })
+                })
+                .collect(),
+            sockets: Mutex::new(self.sources.iter().map(|_| None).collect()),
+        });
+
+        let workers = self
+            .sources
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, addr, connector))| {
+                let source = Source {
+                    index,
+                    name,
+                    addr,
+                    connector,
+                    reconnect_delay: self.reconnect_delay,
+                };
+                let shared = shared.clone();
+                let sender = sender.clone();
+                thread::spawn(move || source.run(&shared, &sender))
+            })
+            .collect();
+
+        Aggregator {
+            spots,
+            shared,
+            workers,
+        }
+    }
+}
+
+impl Default for AggregatorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Receiver of the spots of several clusters, merged into a single stream.
+///
+/// Each source is connected by its own thread, the spots of all sources are tagged with the name of their source
+/// and merged in the order they were received. Lines that are no spots, e.g. announcements, are counted but not passed on.
+/// A source whose connection ends is connected again after the reconnect delay.
+/// The health of each source is available at any time, see [`Aggregator::health`].
+/// The aggregator disconnects from all sources as soon as it is dropped.
+///
+/// ```no_run
+/// use dxclparser::{Aggregator, ClusterClient, ClusterFlavor, Parser};
+///
+/// let aggregator = Aggregator::builder()
+///     .source("DB0SDX", "db0sdx.de:8000", || ClusterClient::builder("DL1ABC"))
+///     .source("RBN", "telnet.reversebeacon.net:7000", || {
+///         let rbn = Parser::builder().flavor(ClusterFlavor::RBN).build().unwrap();
+///         ClusterClient::builder("DL1ABC").parser(rbn)
+///     })
+///     .start();
+/// for spot in aggregator {
+///     println!("{}: {}", spot.source, spot.spot.to_json());
+/// }
+/// ```
+pub struct Aggregator {
+    spots: Receiver<SourcedSpot>,
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Aggregator {
+    /// Create a builder for an aggregator.
+    pub fn builder() -> AggregatorBuilder {
+        AggregatorBuilder::new()
+    }
+
+    /// Get the health of all sources, in the order they were added.
+    pub fn health(&self) -> Vec<SourceHealth> {
+        let health = self.shared.health.iter();
+        health.map(|h| h.lock().unwrap().clone()).collect()
+    }
+
+    /// Wait for the next spot of any source.
+    ///
+    /// ## Arguments
+    ///
+    /// * `timeout`: Time to wait for a spot
+    ///
+    /// ## Result
+    ///
+    /// In case a spot was received in time, the spot shall be returned.
+    /// In case the time elapsed or all sources stopped, `None` shall be returned.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<SourcedSpot> {
+        self.spots.recv_timeout(timeout).ok()
+    }
+}
+
+impl Iterator for Aggregator {
+    type Item = SourcedSpot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.spots.recv().ok()
+    }
+}
+
+impl Drop for Aggregator {
+    fn drop(&mut self) {
+        *self.shared.stopped.lock().unwrap() = true;
+        self.shared.wake.notify_all();
+
+        for socket in self.shared.sockets.lock().unwrap().iter_mut() {
+            if let Some(socket) = socket.take() {
+                let _ = socket.shutdown(Shutdown::Both);
+            }
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// State shared by the aggregator and the threads of the sources
+struct Shared {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+    /// Held while a spot is timestamped and sent, so the merged stream is ordered by the time of reception
+    order: Mutex<()>,
+    health: Vec<Mutex<SourceHealth>>,
+    /// Socket of the current connection of each source, to disconnect it on drop
+    sockets: Mutex<Vec<Option<TcpStream>>>,
+}
+
+impl Shared {
+    fn update(&self, index: usize, f: impl FnOnce(&mut SourceHealth)) {
+        f(&mut self.health[index].lock().unwrap());
+    }
+
+    /// Wait for the given time, `true` is returned in case the aggregator was stopped meanwhile.
+    fn wait(&self, delay: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        let (stopped, _) = self
+            .wake
+            .wait_timeout_while(stopped, delay, |stopped| !*stopped)
+            .unwrap();
+        *stopped
+    }
+}
+
+/// Cluster connected by a thread of the aggregator
+struct Source {
+    index: usize,
+    name: String,
+    addr: String,
+    connector: Connector,
+    reconnect_delay: Option<Duration>,
+}
+
+impl Source {
+    fn run(&self, shared: &Shared, sender: &Sender<SourcedSpot>) {
+        loop {
+            shared.update(self.index, |h| h.state = SourceState::Connecting);
+            let error = match self.receive(shared, sender) {
+                Ok(()) => return,
+                Err(e) => e,
+            };
+            shared.update(self.index, |h| h.last_error = Some(error.to_string()));
+
+            let stopped = match self.reconnect_delay {
+                Some(delay) => {
+                    shared.update(self.index, |h| h.state = SourceState::Waiting);
+                    shared.wait(delay)
+                }
+                None => true,
+            };
+            if stopped {
+                shared.update(self.index, |h| h.state = SourceState::Stopped);
+                return;
+            }
+        }
+    }
+
+    /// Connect and pass on the spots until the connection ends.
+    /// In case the aggregator was stopped, `Ok` is returned.
+    fn receive(&self, shared: &Shared, sender: &Sender<SourcedSpot>) -> Result<(), ClientError> {
+        let mut client = (self.connector)().connect(self.addr.as_str())?;
+        {
+            let mut sockets = shared.sockets.lock().unwrap();
+            if *shared.stopped.lock().unwrap() {
+                shared.update(self.index, |h| h.state = SourceState::Stopped);
+                return Ok(());
+            }
+            sockets[self.index] = Some(client.socket()?);
+        }
+        shared.update(self.index, |h| {
+            h.state = SourceState::Connected;
+            h.connects += 1;
+        });
+
+        for spot in client.by_ref() {
+            let Ok(spot) = spot else {
+                shared.update(self.index, |h| h.unparsed += 1);
+                continue;
+            };
+
+            let _order = shared.order.lock().unwrap();
+            let received = SystemTime::now();
+            shared.update(self.index, |h| {
+                h.spots += 1;
+                h.last_spot = Some(received);
+            });
+            let _ = sender.send(SourcedSpot {
+                source: self.name.clone(),
+                received,
+                spot,
+            });
+        }
+        shared.sockets.lock().unwrap()[self.index] = None;
+
+        if *shared.stopped.lock().unwrap() {
+            shared.update(self.index, |h| h.state = SourceState::Stopped);
+            return Ok(());
+        }
+        Err(client.take_error().unwrap_or(ClientError::Closed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::client::ClusterClient;
+    use crate::fixtures::{SPOT, SPOT2};
+    use crate::mock::{MockCluster, Recording};
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    const SPOT3: &str =
+        "DX de W3LPL:     18100.0  3B9FR        CQ                             1201Z";
+    const WWV: &str = "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms";
+
+    fn mock(recording: Recording) -> MockCluster {
+        MockCluster::builder()
+            .replay(recording)
+            .bind("127.0.0.1:0")
+            .unwrap()
+    }
+
+    fn client() -> ClientBuilder {
+        ClusterClient::builder("DL1ABC").read_timeout(Duration::from_millis(800))
+    }
+
+    /// Wait until the health of all sources satisfies the condition.
+    fn wait_for(aggregator: &Aggregator, f: impl Fn(&SourceHealth) -> bool) -> Vec<SourceHealth> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let health = aggregator.health();
+            if health.iter().all(&f) || Instant::now() > deadline {
+                return health;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn aggregator_merge() {
+        let ms = Duration::from_millis;
+        let a = mock(
+            Recording::new()
+                .line(ms(0), SPOT)
+                .line(ms(300), SPOT3)
+                .line(ms(300), "Hello there"),
+        );
+        let b = mock(Recording::new().line(ms(150), SPOT2).line(ms(450), WWV));
+
+        let mut aggregator = Aggregator::builder()
+            .source("A", &a.addr().to_string(), client)
+            .source("B", &b.addr().to_string(), client)
+            .no_reconnect()
+            .start();
+        let spots: Vec<_> = aggregator.by_ref().collect();
+        let tags: Vec<_> = spots
+            .iter()
+            .map(|s| (s.source.as_str(), s.spot.clone()))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("A", crate::parse(SPOT).unwrap()),
+                ("B", crate::parse(SPOT2).unwrap()),
+                ("A", crate::parse(SPOT3).unwrap()),
+                ("B", crate::parse(WWV).unwrap()),
+            ]
+        );
+        assert!(spots.windows(2).all(|w| w[0].received <= w[1].received));
+
+        let health = aggregator.health();
+        assert_eq!(health[0].name, "A");
+        assert_eq!(health[0].state, SourceState::Stopped);
+        assert_eq!(health[0].connects, 1);
+        assert_eq!(health[0].spots, 2);
+        assert_eq!(health[0].unparsed, 1);
+        assert_eq!(health[0].last_spot, Some(spots[2].received));
+        assert_eq!(health[1].spots, 2);
+        assert_eq!(health[1].unparsed, 0);
+    }
+
+    #[test]
+    fn aggregator_health() {
+        let unreachable = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+        let cluster = mock(Recording::new().line(Duration::ZERO, SPOT));
+
+        let aggregator = Aggregator::builder()
+            .source("DOWN", &unreachable, client)
+            .source("UP", &cluster.addr().to_string(), || {
+                ClusterClient::builder("DL1ABC")
+            })
+            .reconnect_delay(Duration::from_secs(60))
+            .start();
+        let spot = aggregator.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(spot.source, "UP");
+
+        let health = wait_for(&aggregator, |h| h.state != SourceState::Connecting);
+        assert_eq!(health[0].state, SourceState::Waiting);
+        assert_eq!(health[0].connects, 0);
+        assert!(health[0]
+            .last_error
+            .as_deref()
+            .unwrap()
+            .starts_with("I/O error"));
+        assert_eq!(health[1].state, SourceState::Connected);
+        assert_eq!(health[1].connects, 1);
+        assert_eq!(health[1].last_error, None);
+
+        // Dropping stops the waiting and the connected source
+        let start = Instant::now();
+        drop(aggregator);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn aggregator_reconnect() {
+        let cluster = mock(Recording::new().line(Duration::ZERO, SPOT));
+
+        let aggregator = Aggregator::builder()
+            .source("A", &cluster.addr().to_string(), client)
+            .reconnect_delay(Duration::from_millis(100))
+            .start();
+        for _ in 0..2 {
+            let spot = aggregator.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(spot.spot, crate::parse(SPOT).unwrap());
+        }
+        let health = wait_for(&aggregator, |h| h.connects >= 2);
+        assert!(health[0].connects >= 2);
+        assert!(health[0]
+            .last_error
+            .as_deref()
+            .unwrap()
+            .starts_with("I/O error"));
+
+        // The socket of a closed connection is not kept while waiting to reconnect
+        let health = wait_for(&aggregator, |h| h.state == SourceState::Waiting);
+        assert_eq!(health[0].state, SourceState::Waiting);
+        let sockets = aggregator.shared.sockets.lock().unwrap();
+        assert_eq!(sockets.len(), 1);
+        if aggregator.health()[0].state == SourceState::Waiting {
+            assert!(sockets[0].is_none());
+        }
+    }
+}
@@ -0,0 +1,713 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::client::{Login, Step, DEFAULT_LOGIN_TIMEOUT, PROMPT_IDLE};
+use crate::error::ClientError;
+use crate::framer::*;
+use crate::parser::Parser;
+use crate::types::Spot;
+use futures_core::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until, timeout, timeout_at, Instant};
+
+/// Number of items buffered by a [`ClusterStream`] before the connection is no longer read
+const CHANNEL_SIZE: usize = 64;
+
+/// Default time to wait for the connection to be established and for a command to be sent
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Item yielded by a [`ClusterStream`]
+pub type StreamItem = Result<Spot, ClientError>;
+
+/// Delay between attempts to reconnect to a cluster.
+///
+/// The delay starts with `initial` and is multiplied by `factor` after each failed attempt, up to `max`.
+/// It is reset as soon as a login succeeded.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Backoff {
+    /// Delay before the first attempt to reconnect
+    pub initial: Duration,
+
+    /// Maximum delay between two attempts
+    pub max: Duration,
+
+    /// Factor the delay is multiplied by after each failed attempt
+    pub factor: f64,
+
+    /// Maximum number of consecutive failed attempts, unlimited in case of `None`
+    pub retries: Option<u32>,
+}
+
+impl Backoff {
+    /// Get the delay before the given attempt, starting with zero.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .factor
+            .max(1.0)
+            .powi(attempt.min(i32::MAX as u32) as i32);
+        Duration::try_from_secs_f64(self.initial.as_secs_f64() * factor)
+            .map_or(self.max, |d| d.min(self.max))
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            factor: 2.0,
+            retries: None,
+        }
+    }
+}
+
+/// Builder for a [`ClusterStream`]
+pub struct AsyncClientBuilder {
+    callsign: String,
+    password: Option<String>,
+    commands: Vec<String>,
+    parser: Arc<Parser>,
+    connect_timeout: Duration,
+    login_timeout: Duration,
+    idle_timeout: Option<Duration>,
+    keepalive: Option<(Duration, String)>,
+    backoff: Option<Backoff>,
+}
+
+impl AsyncClientBuilder {
+    /// Create a builder logging in with the given callsign.
+    pub fn new(callsign: &str) -> Self {
+        AsyncClientBuilder {
+            callsign: String::from(callsign),
+            password: None,
+            commands: Vec::new(),
+            parser: Arc::new(Parser::new()),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            login_timeout: DEFAULT_LOGIN_TIMEOUT,
+            idle_timeout: None,
+            keepalive: None,
+            backoff: Some(Backoff::default()),
+        }
+    }
+
+    /// Set the password sent in case the cluster asks for one.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(String::from(password));
+        self
+    }
+
+    /// Add a command sent right after each login, e.g. a filter or `set/dxgrid`.
+    /// Commands are sent in the order they were added.
+    pub fn command(mut self, command: &str) -> Self {
+        self.commands.push(String::from(command));
+        self
+    }
+
+    /// Set the parser used for the received lines, by default [`Parser::new`].
+    /// The parser may be shared with other streams.
+    pub fn parser(mut self, parser: Arc<Parser>) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Set the time to wait for the connection to be established and for each command to be sent, by default [`DEFAULT_CONNECT_TIMEOUT`].
+    /// Exceeding it fails the connection with an error of the kind [`std::io::ErrorKind::TimedOut`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the time to wait for each step of the login, by default [`DEFAULT_LOGIN_TIMEOUT`].
+    pub fn login_timeout(mut self, timeout: Duration) -> Self {
+        self.login_timeout = timeout;
+        self
+    }
+
+    /// Set the time after which a connection without any received line is considered dead and reconnected.
+    /// By default a connection is never considered dead.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Send a command to the cluster whenever no line was received for the given interval.
+    /// The answer of the cluster keeps the connection from running into the idle timeout, see [`AsyncClientBuilder::idle_timeout`].
+    pub fn keepalive(mut self, interval: Duration, command: &str) -> Self {
+        self.keepalive = Some((interval, String::from(command)));
+        self
+    }
+
+    /// Set the delay between attempts to reconnect, by default [`Backoff::default`].
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+
+    /// Disable reconnecting, the stream ends as soon as the connection is lost.
+    pub fn no_reconnect(mut self) -> Self {
+        self.backoff = None;
+        self
+    }
+
+    /// Start a task connecting to a cluster and stream the received spots.
+    ///
+    /// The task logs in like [`crate::ClientBuilder::connect`] and sends the initial commands after each login.
+    /// Each line that is no prompt is parsed and yielded by the stream, lines that are no spots are yielded as [`ClientError::Parse`].
+    /// In case the connection fails or is lost, the error is yielded and the task reconnects according to the backoff.
+    /// The stream ends after [`ClusterStream::shutdown`], once the retries are exhausted or on [`ClientError::PasswordRequired`].
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address of the cluster
+    ///
+    /// ## Result
+    ///
+    /// The stream of received spots shall be returned.
+    pub fn connect(self, addr: &str) -> ClusterStream {
+        let addr = String::from(addr);
+        self.spawn(move || {
+            let addr = addr.clone();
+            async move { TcpStream::connect(addr).await }
+        })
+    }
+
+    /// Start a task connecting to a cluster via TLS and stream the received spots, see [`AsyncClientBuilder::connect`].
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address of the cluster given as `host:port`, the host is verified against the certificate of the cluster
+    /// * `tls`: Configuration of the TLS connection
+    ///
+    /// ## Result
+    ///
+    /// In case the configuration is valid, the stream of received spots shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(
+        self,
+        addr: &str,
+        tls: &crate::tls::TlsConfig,
+    ) -> Result<ClusterStream, ClientError> {
+        let name = tls.server_name_of(addr)?;
+        let connector = tokio_rustls::TlsConnector::from(tls.client_config()?);
+        let addr = String::from(addr);
+        Ok(self.spawn(move || {
+            let (addr, name, connector) = (addr.clone(), name.clone(), connector.clone());
+            async move {
+                let stream = TcpStream::connect(addr).await?;
+                connector.connect(name, stream).await
+            }
+        }))
+    }
+
+    /// Start the task with the given function opening a connection to the cluster.
+    pub(crate) fn spawn<C, F, S>(self, connect: C) -> ClusterStream
+    where
+        C: Fn() -> F + Send + Sync + 'static,
+        F: std::future::Future<Output = std::io::Result<S>> + Send + 'static,
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        let (stop, stopped) = watch::channel(false);
+        let task = tokio::spawn(run(self, connect, tx, stopped));
+
+        ClusterStream { rx, stop, task }
+    }
+}
+
+/// Stream of the spots received from a DX Cluster.
+///
+/// The connection is handled by a background task, see [`AsyncClientBuilder::connect`].
+/// Dropping the stream stops the task without logging out.
+///
+/// ```no_run
+/// # async fn run() {
+/// let mut stream = dxclparser::ClusterStream::builder("DL1ABC")
+///     .command("set/dxgrid")
+///     .idle_timeout(std::time::Duration::from_secs(300))
+///     .connect("dxc.example.org:7300");
+/// while let Some(item) = stream.next().await {
+///     if let Ok(spot) = item {
+///         println!("{}", spot.to_json());
+///     }
+/// }
+/// # }
+/// ```
+pub struct ClusterStream {
+    rx: mpsc::Receiver<StreamItem>,
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl ClusterStream {
+    /// Create a builder for a stream logging in with the given callsign.
+    pub fn builder(callsign: &str) -> AsyncClientBuilder {
+        AsyncClientBuilder::new(callsign)
+    }
+
+    /// Get the next item of the stream, `None` is returned once the stream ended.
+    pub async fn next(&mut self) -> Option<StreamItem> {
+        self.rx.recv().await
+    }
+
+    /// Log out with `bye`, close the connection and wait for the task to finish.
+    /// Items not yet taken from the stream are dropped.
+    pub async fn shutdown(mut self) {
+        let _ = self.stop.send(true);
+
+        // Drain the channel, the task may wait for space before noticing the shutdown
+        while self.rx.recv().await.is_some() {}
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Stream for ClusterStream {
+    type Item = StreamItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for ClusterStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Reason a session ended without an error
+enum Exit {
+    Shutdown,
+    Dropped,
+}
+
+/// Connection to a cluster, reading lines through a framer
+struct Connection<S> {
+    stream: S,
+    framer: Framer,
+    send_timeout: Duration,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    async fn send(&mut self, command: &str) -> Result<(), ClientError> {
+        let line = format!("{}\r\n", command);
+        let send = async {
+            self.stream.write_all(line.as_bytes()).await?;
+            self.stream.flush().await
+        };
+        Ok(timeout(self.send_timeout, send)
+            .await
+            .map_err(|_| timed_out())??)
+    }
+
+    /// Read the next line, cancel safe since no data is buffered across an await point.
+    /// An incomplete line is checked for a prompt once the cluster stays silent.
+    async fn read_line(&mut self) -> Result<FramedLine, ClientError> {
+        let mut buf = [0; 4096];
+        loop {
+            if let Some(line) = self.framer.next() {
+                return Ok(line);
+            }
+            let n = if self.framer.buffered() > 0 {
+                match timeout(PROMPT_IDLE, self.stream.read(&mut buf)).await {
+                    Ok(n) => n?,
+                    Err(_) => {
+                        self.framer.idle();
+                        continue;
+                    }
+                }
+            } else {
+                self.stream.read(&mut buf).await?
+            };
+            if n == 0 {
+                self.framer.flush();
+                return self.framer.next().ok_or(ClientError::Closed);
+            }
+            self.framer.push(&buf[..n]);
+        }
+    }
+}
+
+/// Connect, log in and stream spots until shut down, reconnecting after errors.
+async fn run<C, F, S>(
+    config: AsyncClientBuilder,
+    connect: C,
+    tx: mpsc::Sender<StreamItem>,
+    mut stopped: watch::Receiver<bool>,
+) where
+    C: Fn() -> F,
+    F: std::future::Future<Output = std::io::Result<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut attempt = 0;
+    loop {
+        let res = session(&config, &connect, &tx, stopped.clone(), &mut attempt).await;
+
+        let err = match res {
+            Ok(_) => return,
+            Err(err) => err,
+        };
+        let fatal = matches!(err, ClientError::PasswordRequired);
+        if tx.send(Err(err)).await.is_err() || fatal {
+            return;
+        }
+
+        let backoff = match config.backoff {
+            Some(backoff) if backoff.retries.is_none_or(|r| attempt < r) => backoff,
+            _ => return,
+        };
+        tokio::select! {
+            _ = sleep(backoff.delay(attempt)) => {}
+            _ = wait_stop(&mut stopped) => return,
+        }
+        attempt += 1;
+    }
+}
+
+/// Run a single connection to the cluster.
+async fn session<C, F, S>(
+    config: &AsyncClientBuilder,
+    connect: &C,
+    tx: &mpsc::Sender<StreamItem>,
+    mut stopped: watch::Receiver<bool>,
+    attempt: &mut u32,
+) -> Result<Exit, ClientError>
+where
+    C: Fn() -> F,
+    F: std::future::Future<Output = std::io::Result<S>>,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let stream = tokio::select! {
+        stream = timeout(config.connect_timeout, connect()) => stream.map_err(|_| timed_out())??,
+        _ = wait_stop(&mut stopped) => return Ok(Exit::Shutdown),
+    };
+    let mut conn = Connection {
+        stream,
+        framer: Framer::new(),
+        send_timeout: config.connect_timeout,
+    };
+
+    let setup = async {
+        let pending = login(config, &mut conn).await?;
+        for command in config.commands.iter() {
+            conn.send(command).await?;
+        }
+        Ok::<_, ClientError>(pending)
+    };
+    let pending = tokio::select! {
+        pending = setup => pending?,
+        _ = wait_stop(&mut stopped) => return Ok(Exit::Shutdown),
+    };
+    *attempt = 0;
+
+    for line in pending {
+        if !deliver(config, tx, line).await {
+            return Ok(Exit::Dropped);
+        }
+    }
+
+    let mut received = Instant::now();
+    let mut sent = received;
+    loop {
+        let idle = config.idle_timeout.map(|t| received + t);
+        let keepalive = config
+            .keepalive
+            .as_ref()
+            .map(|(t, _)| received.max(sent) + *t);
+
+        tokio::select! {
+            line = conn.read_line() => {
+                received = Instant::now();
+                if !deliver(config, tx, line?).await {
+                    return Ok(Exit::Dropped);
+                }
+            }
+            _ = sleep_until(keepalive.unwrap_or_else(Instant::now)), if keepalive.is_some() => {
+                if let Some((_, command)) = &config.keepalive {
+                    conn.send(command).await?;
+                }
+                sent = Instant::now();
+            }
+            _ = sleep_until(idle.unwrap_or_else(Instant::now)), if idle.is_some() => {
+                return Err(ClientError::Idle);
+            }
+            _ = wait_stop(&mut stopped) => {
+                let _ = conn.send("bye").await;
+                let _ = conn.stream.shutdown().await;
+                return Ok(Exit::Shutdown);
+            }
+        }
+    }
+}
+
+/// Log in, the lines received after the callsign was sent are returned.
+async fn login<S: AsyncRead + AsyncWrite + Unpin>(
+    config: &AsyncClientBuilder,
+    conn: &mut Connection<S>,
+) -> Result<Vec<FramedLine>, ClientError> {
+    let mut login = Login::new(&config.callsign, config.password.as_deref());
+    let mut pending = Vec::new();
+    let mut deadline = Instant::now() + config.login_timeout;
+    loop {
+        let line = match timeout_at(deadline, conn.read_line()).await {
+            Ok(line) => line?,
+            Err(_) => {
+                login.timeout()?;
+                return Ok(pending);
+            }
+        };
+        match login.step(&line, &config.parser)? {
+            Step::Skip => {}
+            Step::Send(text) => {
+                conn.send(&text).await?;
+                deadline = Instant::now() + config.login_timeout;
+            }
+            Step::Keep => pending.push(line),
+            Step::Done => return Ok(pending),
+            Step::KeepDone => {
+                pending.push(line);
+                return Ok(pending);
+            }
+        }
+    }
+}
+
+fn timed_out() -> std::io::Error {
+    std::io::Error::from(std::io::ErrorKind::TimedOut)
+}
+
+/// Wait until the stream is shut down or dropped.
+async fn wait_stop(stopped: &mut watch::Receiver<bool>) {
+    let _ = stopped.wait_for(|s| *s).await;
+}
+
+/// Parse a line and pass it to the stream, `false` is returned in case the stream was dropped.
+async fn deliver(
+    config: &AsyncClientBuilder,
+    tx: &mpsc::Sender<StreamItem>,
+    line: FramedLine,
+) -> bool {
+    if line.boundary == Boundary::Prompt {
+        return true;
+    }
+
+    let item = config
+        .parser
+        .parse_bytes(&line.bytes)
+        .map(|res| res.spot)
+        .map_err(ClientError::from);
+    tx.send(item).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::fixtures::SPOT;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    const PROMPT: &str = "DL1ABC de DB0SUE-7 10-Sep-2022 2200Z dxspider >";
+
+    async fn listen() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        (listener, addr)
+    }
+
+    /// Accept a connection and run the login, the stream and a reader for the lines of the client are returned.
+    async fn accept(
+        listener: &TcpListener,
+    ) -> (
+        tokio::net::tcp::OwnedWriteHalf,
+        tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    ) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"login: ").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "DL1ABC");
+        write
+            .write_all(format!("{}\x07\x07", PROMPT).as_bytes())
+            .await
+            .unwrap();
+        (write, lines)
+    }
+
+    #[test]
+    fn backoff_delay() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(0), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(8));
+        assert_eq!(backoff.delay(10), Duration::from_secs(60));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn stream_spots_and_shutdown() {
+        let (listener, addr) = listen().await;
+        let server = tokio::spawn(async move {
+            let (mut write, mut lines) = accept(&listener).await;
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), "set/dxgrid");
+            write
+                .write_all(format!("{}\r\nWX de A1BC: sunny\r\n", SPOT).as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), "bye");
+        });
+
+        let mut stream = ClusterStream::builder("DL1ABC")
+            .command("set/dxgrid")
+            .connect(&addr);
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            crate::parse(SPOT).unwrap()
+        );
+        assert!(matches!(stream.next().await, Some(Ok(Spot::WX(_)))));
+        stream.shutdown().await;
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stream_reconnect() {
+        let (listener, addr) = listen().await;
+        let server = tokio::spawn(async move {
+            let (write, _) = accept(&listener).await;
+            drop(write);
+            let (mut write, _lines) = accept(&listener).await;
+            write
+                .write_all(format!("{}\r\n", SPOT).as_bytes())
+                .await
+                .unwrap();
+            listener
+        });
+
+        let backoff = Backoff {
+            initial: Duration::from_millis(10),
+            ..Backoff::default()
+        };
+        let mut stream = ClusterStream::builder("DL1ABC")
+            .backoff(backoff)
+            .connect(&addr);
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ClientError::Closed))
+        ));
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            crate::parse(SPOT).unwrap()
+        );
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stream_retries_exhausted() {
+        let (listener, addr) = listen().await;
+        drop(listener);
+
+        let backoff = Backoff {
+            initial: Duration::from_millis(10),
+            retries: Some(2),
+            ..Backoff::default()
+        };
+        let mut stream = ClusterStream::builder("DL1ABC")
+            .backoff(backoff)
+            .connect(&addr);
+        for _ in 0..3 {
+            assert!(matches!(stream.next().await, Some(Err(ClientError::Io(_)))));
+        }
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_keepalive_and_idle() {
+        let (listener, addr) = listen().await;
+        let server = tokio::spawn(async move {
+            let (mut write, mut lines) = accept(&listener).await;
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), "sh/time");
+            write.write_all(PROMPT.as_bytes()).await.unwrap();
+            // Stay silent on further commands
+            while lines.next_line().await.unwrap().is_some() {}
+        });
+
+        let mut stream = ClusterStream::builder("DL1ABC")
+            .keepalive(Duration::from_millis(50), "sh/time")
+            .idle_timeout(Duration::from_millis(200))
+            .no_reconnect()
+            .connect(&addr);
+        assert!(matches!(stream.next().await, Some(Err(ClientError::Idle))));
+        assert!(stream.next().await.is_none());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn stream_password_required() {
+        let (listener, addr) = listen().await;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"login: ").await.unwrap();
+            lines.next_line().await.unwrap();
+            write.write_all(b"password: ").await.unwrap();
+            lines.next_line().await.unwrap();
+        });
+
+        let mut stream = ClusterStream::builder("DL1ABC").connect(&addr);
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(ClientError::PasswordRequired))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_shutdown_while_connecting() {
+        let stream = ClusterStream::builder("DL1ABC")
+            .spawn(std::future::pending::<std::io::Result<tokio::io::DuplexStream>>);
+        let res = tokio::time::timeout(Duration::from_secs(1), stream.shutdown()).await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn stream_connect_timeout() {
+        let mut stream = ClusterStream::builder("DL1ABC")
+            .connect_timeout(Duration::from_millis(50))
+            .no_reconnect()
+            .spawn(std::future::pending::<std::io::Result<tokio::io::DuplexStream>>);
+        match stream.next().await {
+            Some(Err(ClientError::Io(e))) => assert_eq!(e.kind(), std::io::ErrorKind::TimedOut),
+            res => panic!("unexpected item {:?}", res),
+        }
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_shutdown_while_logging_in() {
+        let (listener, addr) = listen().await;
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            // The cluster never prompts for the callsign
+            assert!(lines.next_line().await.unwrap().is_none());
+        });
+
+        let stream = ClusterStream::builder("DL1ABC").connect(&addr);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let res = tokio::time::timeout(Duration::from_secs(1), stream.shutdown()).await;
+        assert!(res.is_ok());
+        server.await.unwrap();
+    }
+}
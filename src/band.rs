@@ -0,0 +1,166 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Amateur radio band
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Band {
+    /// 160 metres
+    M160,
+
+    /// 80 metres
+    M80,
+
+    /// 60 metres
+    M60,
+
+    /// 40 metres
+    M40,
+
+    /// 30 metres
+    M30,
+
+    /// 20 metres
+    M20,
+
+    /// 17 metres
+    M17,
+
+    /// 15 metres
+    M15,
+
+    /// 12 metres
+    M12,
+
+    /// 10 metres
+    M10,
+
+    /// 6 metres
+    M6,
+
+    /// 4 metres
+    M4,
+
+    /// 2 metres
+    M2,
+
+    /// 70 centimetres
+    Cm70,
+}
+
+impl Band {
+    /// All bands ordered by frequency
+    pub const ALL: [Band; 14] = [
+        Band::M160,
+        Band::M80,
+        Band::M60,
+        Band::M40,
+        Band::M30,
+        Band::M20,
+        Band::M17,
+        Band::M15,
+        Band::M12,
+        Band::M10,
+        Band::M6,
+        Band::M4,
+        Band::M2,
+        Band::Cm70,
+    ];
+
+    /// Get the band a frequency (in Hz) lies within, `None` is returned for frequencies outside of any band.
+    pub fn of(freq: u64) -> Option<Band> {
+        Band::ALL.into_iter().find(|b| {
+            let (low, high) = b.range();
+            (low..=high).contains(&freq)
+        })
+    }
+
+    /// Get the lower and upper edge of the band (in Hz).
+    /// The edges cover the allocations of all IARU regions.
+    pub fn range(&self) -> (u64, u64) {
+        let (low, high) = match self {
+            Band::M160 => (1_800, 2_000),
+            Band::M80 => (3_500, 4_000),
+            Band::M60 => (5_250, 5_450),
+            Band::M40 => (7_000, 7_300),
+            Band::M30 => (10_100, 10_150),
+            Band::M20 => (14_000, 14_350),
+            Band::M17 => (18_068, 18_168),
+            Band::M15 => (21_000, 21_450),
+            Band::M12 => (24_890, 24_990),
+            Band::M10 => (28_000, 29_700),
+            Band::M6 => (50_000, 54_000),
+            Band::M4 => (70_000, 70_500),
+            Band::M2 => (144_000, 148_000),
+            Band::Cm70 => (430_000, 440_000),
+        };
+        (low * 1000, high * 1000)
+    }
+
+    /// Get the name of the band, e.g. `20m`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Band::M160 => "160m",
+            Band::M80 => "80m",
+            Band::M60 => "60m",
+            Band::M40 => "40m",
+            Band::M30 => "30m",
+            Band::M20 => "20m",
+            Band::M17 => "17m",
+            Band::M15 => "15m",
+            Band::M12 => "12m",
+            Band::M10 => "10m",
+            Band::M6 => "6m",
+            Band::M4 => "4m",
+            Band::M2 => "2m",
+            Band::Cm70 => "70cm",
+        }
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Band {
+    type Err = ();
+
+    /// Parse the name of a band, e.g. `20m`, `20M` or `20`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        Band::ALL
+            .into_iter()
+            .find(|b| b.name() == s || b.name().strip_suffix('m') == Some(&s))
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn band_of() {
+        assert_eq!(Band::of(14025000), Some(Band::M20));
+        assert_eq!(Band::of(1800000), Some(Band::M160));
+        assert_eq!(Band::of(432100000), Some(Band::Cm70));
+        assert_eq!(Band::of(15000000), None);
+    }
+
+    #[test]
+    fn band_names() {
+        assert_eq!("20m".parse(), Ok(Band::M20));
+        assert_eq!("160M".parse(), Ok(Band::M160));
+        assert_eq!("40".parse(), Ok(Band::M40));
+        assert_eq!("70cm".parse(), Ok(Band::Cm70));
+        assert_eq!("11m".parse::<Band>(), Err(()));
+        assert_eq!(Band::M20.to_string(), "20m");
+    }
+}
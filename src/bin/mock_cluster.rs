@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use dxclparser::{MockCluster, Recording};
+use std::env;
+use std::fs;
+use std::process;
+use std::thread;
+
+const USAGE: &str = "Usage: mock_cluster [--bind <addr>] [--node <call>] [--password <password>] [--speed <factor>] [--answer <command>=<file>]... [recording]";
+
+/// Run a mock cluster on a local port until it is killed.
+/// The recorded lines are replayed to each client after its login, the commands are answered by the content of the given files.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut builder = MockCluster::builder();
+    let mut bind = String::from("127.0.0.1:7300");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail("Missing value"));
+        match arg.as_str() {
+            "--bind" => bind = value().clone(),
+            "--node" => builder = builder.node(value()),
+            "--password" => builder = builder.password(value()),
+            "--speed" => match value().parse() {
+                Ok(speed) => builder = builder.speed(speed),
+                Err(_) => fail("Invalid speed"),
+            },
+            "--answer" => match value().split_once('=') {
+                Some((command, file)) => builder = builder.command(command, &read(file)),
+                None => fail("Invalid answer"),
+            },
+            file if !file.starts_with("--") => {
+                builder = builder.replay(Recording::parse(&read(file)));
+            }
+            _ => fail("Invalid argument"),
+        }
+    }
+
+    let cluster = match builder.bind(&bind) {
+        Ok(cluster) => cluster,
+        Err(e) => {
+            eprintln!("Failed to listen on {} ({})", bind, e);
+            process::exit(1);
+        }
+    };
+    println!("Listening on {}", cluster.addr());
+
+    loop {
+        thread::park();
+    }
+}
+
+fn read(file: &str) -> String {
+    fs::read_to_string(file).unwrap_or_else(|e| {
+        eprintln!("Failed to read {} ({})", file, e);
+        process::exit(1);
+    })
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}.", msg);
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
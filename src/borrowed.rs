@@ -0,0 +1,443 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::types::*;
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// Structured representation of a parsed spot borrowing its text fields from the parsed line
+///
+/// The json format is identical to the one of [`Spot`].
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+pub enum SpotRef<'a> {
+    /// Spot of the type DX
+    DX(DXRef<'a>),
+
+    /// Spot of the type WWV
+    WWV(WWVRef<'a>),
+
+    /// Spot of the type WCY
+    WCY(WCYRef<'a>),
+
+    /// Spot of the type WX
+    WX(WXRef<'a>),
+
+    /// Spot of the type ToAll
+    ToAll(ToAllRef<'a>),
+
+    /// Spot of the type ToLocal
+    ToLocal(ToLocalRef<'a>),
+
+    /// Spot of a user-defined type, always owned
+    Custom(Custom),
+}
+
+impl SpotRef<'_> {
+    /// Convert structured spot into its corresponding json format
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap()
+    }
+
+    /// Get the built-in type of the spot, `None` for a custom spot
+    pub fn spot_type(&self) -> Option<SpotType> {
+        match self {
+            SpotRef::DX(_) => Some(SpotType::DX),
+            SpotRef::WWV(_) => Some(SpotType::WWV),
+            SpotRef::WCY(_) => Some(SpotType::WCY),
+            SpotRef::WX(_) => Some(SpotType::WX),
+            SpotRef::ToAll(_) => Some(SpotType::ToAll),
+            SpotRef::ToLocal(_) => Some(SpotType::ToLocal),
+            SpotRef::Custom(_) => None,
+        }
+    }
+
+    /// Copy the borrowed fields into an owned spot.
+    pub fn to_spot(&self) -> Spot {
+        self.clone().into_owned()
+    }
+
+    /// Convert into an owned spot, fields that are already owned are moved.
+    pub fn into_owned(self) -> Spot {
+        match self {
+            SpotRef::DX(dx) => Spot::DX(dx.into_owned()),
+            SpotRef::WWV(wwv) => Spot::WWV(wwv.into_owned()),
+            SpotRef::WCY(wcy) => Spot::WCY(wcy.into_owned()),
+            SpotRef::WX(wx) => Spot::WX(wx.into_owned()),
+            SpotRef::ToAll(ta) => Spot::ToAll(ta.into_owned()),
+            SpotRef::ToLocal(tl) => Spot::ToLocal(tl.into_owned()),
+            SpotRef::Custom(custom) => Spot::Custom(custom),
+        }
+    }
+}
+
+impl From<Spot> for SpotRef<'_> {
+    fn from(spot: Spot) -> Self {
+        match spot {
+            Spot::DX(dx) => SpotRef::DX(DXRef {
+                call_de: Cow::Owned(dx.call_de),
+                call_dx: Cow::Owned(dx.call_dx),
+                freq: dx.freq,
+                utc: dx.utc,
+                loc: dx.loc.map(Cow::Owned),
+                comment: dx.comment.map(Cow::Owned),
+                truncated: dx.truncated,
+            }),
+            Spot::WWV(wwv) => SpotRef::WWV(WWVRef {
+                call_de: Cow::Owned(wwv.call_de),
+                utc: wwv.utc,
+                sfi: wwv.sfi,
+                a: wwv.a,
+                k: wwv.k,
+                info1: Cow::Owned(wwv.info1),
+                info2: Cow::Owned(wwv.info2),
+            }),
+            Spot::WCY(wcy) => SpotRef::WCY(WCYRef {
+                call_de: Cow::Owned(wcy.call_de),
+                utc: wcy.utc,
+                k: wcy.k,
+                expk: wcy.expk,
+                a: wcy.a,
+                r: wcy.r,
+                sfi: wcy.sfi,
+                sa: Cow::Owned(wcy.sa),
+                gmf: Cow::Owned(wcy.gmf),
+                au: Cow::Owned(wcy.au),
+            }),
+            Spot::WX(wx) => SpotRef::WX(WXRef {
+                call_de: Cow::Owned(wx.call_de),
+                utc: wx.utc,
+                msg: wx.msg.map(Cow::Owned),
+            }),
+            Spot::ToAll(ta) => SpotRef::ToAll(ToAllRef {
+                call_de: Cow::Owned(ta.call_de),
+                utc: ta.utc,
+                msg: ta.msg.map(Cow::Owned),
+            }),
+            Spot::ToLocal(tl) => SpotRef::ToLocal(ToLocalRef {
+                call_de: Cow::Owned(tl.call_de),
+                utc: tl.utc,
+                msg: tl.msg.map(Cow::Owned),
+            }),
+            Spot::Custom(custom) => SpotRef::Custom(custom),
+        }
+    }
+}
+
+/// DX Spot, see [`DX`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct DXRef<'a> {
+    /// Call of spotting station
+    pub call_de: Cow<'a, str>,
+
+    /// Call of target station
+    pub call_dx: Cow<'a, str>,
+
+    /// Frequency (in Hz)
+    pub freq: u64,
+
+    /// Time in UTC
+    pub utc: u16,
+
+    /// Locator
+    pub loc: Option<Cow<'a, str>>,
+
+    /// Comment
+    pub comment: Option<Cow<'a, str>>,
+
+    /// The comment filled its whole column and was probably truncated by the cluster
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl DXRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> DX {
+        DX {
+            call_de: self.call_de.into_owned(),
+            call_dx: self.call_dx.into_owned(),
+            freq: self.freq,
+            utc: self.utc,
+            loc: self.loc.map(Cow::into_owned),
+            comment: self.comment.map(Cow::into_owned),
+            truncated: self.truncated,
+        }
+    }
+}
+
+/// RBN spot, see [`RBN`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct RBNRef<'a> {
+    /// Mode: CW, RTTY or FT8
+    pub mode: Cow<'a, str>,
+
+    /// Signal strength
+    pub db: i16,
+
+    /// Speed
+    pub speed: Option<u16>,
+
+    /// Unit of speed
+    pub speed_unit: Option<Cow<'a, str>>,
+
+    /// Additional information
+    pub info: Cow<'a, str>,
+
+    /// Locator
+    pub loc: Option<Cow<'a, str>>,
+}
+
+impl RBNRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> RBN {
+        RBN {
+            mode: self.mode.into_owned(),
+            db: self.db,
+            speed: self.speed,
+            speed_unit: self.speed_unit.map(Cow::into_owned),
+            info: self.info.into_owned(),
+            loc: self.loc.map(Cow::into_owned),
+        }
+    }
+}
+
+/// WWV spot, see [`WWV`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct WWVRef<'a> {
+    /// Call of spotting station
+    pub call_de: Cow<'a, str>,
+
+    /// Time in UTC
+    pub utc: u8,
+
+    /// SFI index
+    pub sfi: u16,
+
+    /// A index
+    pub a: u16,
+
+    /// K index
+    pub k: u16,
+
+    /// Information 1
+    pub info1: Cow<'a, str>,
+
+    /// Information 2
+    pub info2: Cow<'a, str>,
+}
+
+impl WWVRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> WWV {
+        WWV {
+            call_de: self.call_de.into_owned(),
+            utc: self.utc,
+            sfi: self.sfi,
+            a: self.a,
+            k: self.k,
+            info1: self.info1.into_owned(),
+            info2: self.info2.into_owned(),
+        }
+    }
+}
+
+/// WCY spot, see [`WCY`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct WCYRef<'a> {
+    /// Call of spotting station
+    pub call_de: Cow<'a, str>,
+
+    /// Time in UTC
+    pub utc: u8,
+
+    /// K index
+    pub k: u16,
+
+    /// expK index
+    pub expk: u16,
+
+    /// A index
+    pub a: u16,
+
+    /// R index
+    pub r: u16,
+
+    /// SFI index
+    pub sfi: u16,
+
+    /// SA index
+    pub sa: Cow<'a, str>,
+
+    /// GMF
+    pub gmf: Cow<'a, str>,
+
+    /// Aurora
+    pub au: Cow<'a, str>,
+}
+
+impl WCYRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> WCY {
+        WCY {
+            call_de: self.call_de.into_owned(),
+            utc: self.utc,
+            k: self.k,
+            expk: self.expk,
+            a: self.a,
+            r: self.r,
+            sfi: self.sfi,
+            sa: self.sa.into_owned(),
+            gmf: self.gmf.into_owned(),
+            au: self.au.into_owned(),
+        }
+    }
+}
+
+/// WX spot, see [`WX`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct WXRef<'a> {
+    /// Call of spotting station
+    pub call_de: Cow<'a, str>,
+
+    /// Time in UTC
+    pub utc: Option<u16>,
+
+    /// Message sent with spot
+    pub msg: Option<Cow<'a, str>>,
+}
+
+impl WXRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> WX {
+        WX {
+            call_de: self.call_de.into_owned(),
+            utc: self.utc,
+            msg: self.msg.map(Cow::into_owned),
+        }
+    }
+}
+
+/// To all spot, see [`ToAll`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct ToAllRef<'a> {
+    /// Call of spotting station
+    pub call_de: Cow<'a, str>,
+
+    /// Time in UTC
+    pub utc: Option<u16>,
+
+    /// Message sent with spot
+    pub msg: Option<Cow<'a, str>>,
+}
+
+impl ToAllRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> ToAll {
+        ToAll {
+            call_de: self.call_de.into_owned(),
+            utc: self.utc,
+            msg: self.msg.map(Cow::into_owned),
+        }
+    }
+}
+
+/// To local spot, see [`ToLocal`]
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct ToLocalRef<'a> {
+    /// Call of spotting station
+    pub call_de: Cow<'a, str>,
+
+    /// Time in UTC
+    pub utc: Option<u16>,
+
+    /// Message sent with spot
+    pub msg: Option<Cow<'a, str>>,
+}
+
+impl ToLocalRef<'_> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an owned spot.
+    pub fn into_owned(self) -> ToLocal {
+        ToLocal {
+            call_de: self.call_de.into_owned(),
+            utc: self.utc,
+            msg: self.msg.map(Cow::into_owned),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn ref_borrows_from_line() {
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI                 1812Z";
+        let parser = Parser::new();
+        match parser.parse_ref(spot) {
+            Ok(SpotRef::DX(dx)) => {
+                assert!(matches!(dx.call_de, Cow::Borrowed("KE8GX")));
+                assert!(matches!(dx.comment, Some(Cow::Borrowed("599 into N. MI"))));
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn ref_to_owned() {
+        let spot = "WCY de DK0WCY-1 <17> : K=2 expK=3 A=15 R=0 SFI=68 SA=qui GMF=min Au=no";
+        let parser = Parser::new();
+        let res = parser.parse_ref(spot).unwrap();
+        assert_eq!(res.to_spot(), parser.parse(spot).unwrap());
+        assert_eq!(res.to_json(), res.into_owned().to_json());
+    }
+
+    #[test]
+    fn ref_rbn() {
+        let raw = "FT8  -12 dB  FK68    CQ";
+        let parser = Parser::new();
+        let res = parser.parse_rbn_ref(raw).unwrap();
+        assert!(matches!(res.loc, Some(Cow::Borrowed("FK68"))));
+        assert_eq!(res.into_owned(), parser.parse_rbn(raw).unwrap());
+    }
+
+    #[test]
+    fn ref_from_owned() {
+        let spot = Spot::ToAll(ToAll {
+            call_de: "CT2IDL".into(),
+            utc: Some(1044),
+            msg: Some("TNX qso..".into()),
+        });
+        let res = SpotRef::from(spot.clone());
+        assert_eq!(res.into_owned(), spot);
+    }
+}
@@ -0,0 +1,204 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Continent
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Continent {
+    /// Africa
+    AF,
+
+    /// Antarctica
+    AN,
+
+    /// Asia
+    AS,
+
+    /// Europe
+    EU,
+
+    /// North America
+    NA,
+
+    /// Oceania
+    OC,
+
+    /// South America
+    SA,
+}
+
+impl Continent {
+    /// All continents
+    pub const ALL: [Continent; 7] = [
+        Continent::AF,
+        Continent::AN,
+        Continent::AS,
+        Continent::EU,
+        Continent::NA,
+        Continent::OC,
+        Continent::SA,
+    ];
+
+    /// Get the abbreviation of the continent, e.g. `EU`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Continent::AF => "AF",
+            Continent::AN => "AN",
+            Continent::AS => "AS",
+            Continent::EU => "EU",
+            Continent::NA => "NA",
+            Continent::OC => "OC",
+            Continent::SA => "SA",
+        }
+    }
+}
+
+impl fmt::Display for Continent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Continent {
+    type Err = ();
+
+    /// Parse the abbreviation of a continent, e.g. `EU` or `eu`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Continent::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// Information about the origin of a callsign
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct CallData {
+    /// Continent
+    pub continent: Option<Continent>,
+
+    /// CQ zone
+    pub cq_zone: Option<u8>,
+
+    /// ITU zone
+    pub itu_zone: Option<u8>,
+
+    /// DXCC entity number
+    pub dxcc: Option<u16>,
+}
+
+/// Source of information about callsigns, e.g. a country file
+pub trait CallInfo {
+    /// Look up the information about a callsign, `None` is returned for unknown callsigns.
+    fn lookup(&self, call: &str) -> Option<CallData>;
+}
+
+/// Information about callsigns looked up by their longest known prefix, e.g. filled from a country file
+///
+/// ```
+/// use dxclparser::{CallData, CallInfo, Continent, PrefixTable};
+///
+/// let mut table = PrefixTable::new();
+/// table.insert("DL", CallData { continent: Some(Continent::EU), cq_zone: Some(14), ..Default::default() });
+/// assert_eq!(table.lookup("DL1ABC-2").unwrap().cq_zone, Some(14));
+/// assert_eq!(table.lookup("OH5Z"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PrefixTable {
+    prefixes: HashMap<String, CallData>,
+}
+
+impl PrefixTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        PrefixTable {
+            prefixes: HashMap::new(),
+        }
+    }
+
+    /// Add a prefix, an existing entry of the prefix is replaced.
+    /// A full callsign may be added as prefix to cover an exception, e.g. a station operating from another zone.
+    pub fn insert(&mut self, prefix: &str, data: CallData) {
+        self.prefixes.insert(prefix.to_ascii_uppercase(), data);
+    }
+
+    /// Get the number of prefixes.
+    pub fn len(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    /// Check whether the table holds no prefixes.
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+}
+
+impl CallInfo for PrefixTable {
+    /// Look up a callsign by its longest prefix within the table.
+    /// The SSID and suffixes like `/P` are ignored, for a call like `DL/OH5Z` the prefix in front of the slash is looked up.
+    fn lookup(&self, call: &str) -> Option<CallData> {
+        let call = base_call(call);
+        (1..=call.len())
+            .rev()
+            .filter(|n| call.is_char_boundary(*n))
+            .find_map(|n| self.prefixes.get(&call[..n]))
+            .cloned()
+    }
+}
+
+/// Get the part of a callsign determining its origin, in upper case and without SSID.
+/// Of a call with a slash, like `DL/OH5Z` or `OH5Z/DL`, the shorter part is taken, ignoring suffixes like `/P`.
+fn base_call(call: &str) -> String {
+    let call = call.trim().to_ascii_uppercase();
+    let call = call.split_once('-').map_or(call.as_str(), |(c, _)| c);
+    call.split('/')
+        .filter(|p| !p.is_empty() && !["P", "M", "MM", "AM", "QRP"].contains(p))
+        .min_by_key(|p| p.len())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn callinfo_lookup() {
+        let mut table = PrefixTable::new();
+        let data = |cont, zone| CallData {
+            continent: Some(cont),
+            cq_zone: Some(zone),
+            ..Default::default()
+        };
+        table.insert("DL", data(Continent::EU, 14));
+        table.insert("OH", data(Continent::EU, 15));
+        table.insert("K", data(Continent::NA, 5));
+        table.insert("KH6", data(Continent::OC, 31));
+        assert_eq!(table.len(), 4);
+
+        let zone = |call| table.lookup(call).and_then(|d| d.cq_zone);
+        assert_eq!(zone("DL1ABC"), Some(14));
+        assert_eq!(zone("dl1abc-2"), Some(14));
+        assert_eq!(zone("K1ABC"), Some(5));
+        assert_eq!(zone("KH6ABC"), Some(31));
+        assert_eq!(zone("DL/OH5Z"), Some(14));
+        assert_eq!(zone("OH5Z/P"), Some(15));
+        assert_eq!(zone("OH5Z/KH6"), Some(31));
+        assert_eq!(zone("3B9FR"), None);
+        assert_eq!(zone(""), None);
+    }
+
+    #[test]
+    fn callinfo_continent() {
+        assert_eq!("eu".parse(), Ok(Continent::EU));
+        assert_eq!("OC".parse(), Ok(Continent::OC));
+        assert_eq!("XX".parse::<Continent>(), Err(()));
+        assert_eq!(Continent::SA.to_string(), "SA");
+    }
+}
@@ -0,0 +1,697 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::command::Command;
+use crate::error::{ClientError, ParseError, SubmitError};
+use crate::framer::*;
+use crate::parser::Parser;
+use crate::submit::Submission;
+use crate::types::{Spot, DX};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Default time to wait for each step of the login
+pub const DEFAULT_LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time without any data after which an incomplete line is checked for a prompt, see [`Framer::idle`]
+pub(crate) const PROMPT_IDLE: Duration = Duration::from_millis(250);
+
+/// Words a prompt of the cluster asking for the callsign contains
+const LOGIN_PROMPTS: &[&str] = &["login", "call"];
+
+/// Words a prompt of the cluster asking for the password contains
+const PASSWORD_PROMPTS: &[&str] = &["password"];
+
+/// Builder for a [`ClusterClient`]
+pub struct ClientBuilder {
+    callsign: String,
+    password: Option<String>,
+    commands: Vec<String>,
+    parser: Option<Parser>,
+    login_timeout: Duration,
+    read_timeout: Option<Duration>,
+}
+
+impl ClientBuilder {
+    /// Create a builder logging in with the given callsign.
+    pub fn new(callsign: &str) -> Self {
+        ClientBuilder {
+            callsign: String::from(callsign),
+            password: None,
+            commands: Vec::new(),
+            parser: None,
+            login_timeout: DEFAULT_LOGIN_TIMEOUT,
+            read_timeout: None,
+        }
+    }
+
+    /// Set the password sent in case the cluster asks for one.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(String::from(password));
+        self
+    }
+
+    /// Add a command sent right after the login, e.g. a filter or `set/dxgrid`.
+    /// Commands are sent in the order they were added.
+    pub fn command(mut self, command: &str) -> Self {
+        self.commands.push(String::from(command));
+        self
+    }
+
+    /// Set the parser used for the received lines, by default [`Parser::new`].
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = Some(parser);
+        self
+    }
+
+    /// Set the time to wait for each step of the login, by default [`DEFAULT_LOGIN_TIMEOUT`].
+    pub fn login_timeout(mut self, timeout: Duration) -> Self {
+        self.login_timeout = timeout;
+        self
+    }
+
+    /// Set the time to wait for data after the login, by default reading blocks until data arrives.
+    /// In case the time elapses the iteration of the client ends with a timeout, see [`ClusterClient::take_error`].
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Connect to a cluster, log in and send the initial commands.
+    ///
+    /// The callsign is sent as soon as the cluster prompts for it, e.g. with `login:` or `Please enter your call:`.
+    /// In case the cluster then prompts for a password, the configured password is sent.
+    /// The login is complete as soon as the cluster shows its prompt or sends a spot.
+    /// Some clusters show no prompt at all, in this case the login is considered complete once the login timeout elapsed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address of the cluster
+    ///
+    /// ## Result
+    ///
+    /// In case the login succeeded, the connected client shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn connect<A: ToSocketAddrs>(self, addr: A) -> Result<ClusterClient, ClientError> {
+        let stream = Transport::Tcp(TcpStream::connect(addr)?);
+        self.start(stream)
+    }
+
+    /// Connect to a cluster via TLS, log in and send the initial commands, see [`ClientBuilder::connect`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address of the cluster given as `host:port`, the host is verified against the certificate of the cluster
+    /// * `tls`: Configuration of the TLS connection
+    ///
+    /// ## Result
+    ///
+    /// In case the login succeeded, the connected client shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(
+        self,
+        addr: &str,
+        tls: &crate::tls::TlsConfig,
+    ) -> Result<ClusterClient, ClientError> {
+        let name = tls.server_name_of(addr)?;
+        let conn = rustls::ClientConnection::new(tls.client_config()?, name)?;
+        let stream = rustls::StreamOwned::new(conn, TcpStream::connect(addr)?);
+        self.start(Transport::Tls(Box::new(stream)))
+    }
+
+    fn start(self, stream: Transport) -> Result<ClusterClient, ClientError> {
+        let mut client = ClusterClient {
+            stream,
+            framer: Framer::new(),
+            parser: self.parser.unwrap_or_default(),
+            pending: VecDeque::new(),
+            error: None,
+            callsign: self.callsign.clone(),
+            read_timeout: self.read_timeout,
+        };
+
+        client.login(&self.callsign, self.password.as_deref(), self.login_timeout)?;
+        client.stream.tcp().set_read_timeout(self.read_timeout)?;
+        for command in self.commands.iter() {
+            client.send(command)?;
+        }
+
+        Ok(client)
+    }
+}
+
+/// Blocking client of a DX Cluster reachable via telnet.
+///
+/// The client iterates the spots received from the cluster.
+/// Each line is cleaned by the [`Framer`] and parsed by [`Parser::parse_bytes`],
+/// lines that are no spots, e.g. the answers to commands, are yielded as an error.
+/// Prompts of the cluster are skipped.
+/// The iteration ends as soon as the connection is closed or an error occurs, see [`ClusterClient::take_error`].
+///
+/// ```no_run
+/// let client = dxclparser::ClusterClient::builder("DL1ABC")
+///     .command("set/dxgrid")
+///     .connect("dxc.example.org:7300")
+///     .unwrap();
+/// for spot in client.flatten() {
+///     println!("{}", spot.to_json());
+/// }
+/// ```
+pub struct ClusterClient {
+    stream: Transport,
+    framer: Framer,
+    parser: Parser,
+    pending: VecDeque<FramedLine>,
+    error: Option<ClientError>,
+    callsign: String,
+    read_timeout: Option<Duration>,
+}
+
+impl ClusterClient {
+    /// Create a builder for a client logging in with the given callsign.
+    pub fn builder(callsign: &str) -> ClientBuilder {
+        ClientBuilder::new(callsign)
+    }
+
+    /// Connect to a cluster and log in with the given callsign, see [`ClientBuilder::connect`].
+    pub fn connect<A: ToSocketAddrs>(addr: A, callsign: &str) -> Result<Self, ClientError> {
+        ClientBuilder::new(callsign).connect(addr)
+    }
+
+    /// Get the parser used for the received lines.
+    pub fn parser(&self) -> &Parser {
+        &self.parser
+    }
+
+    /// Send a typed command in the syntax of the flavour configured for the parser, see [`Command::to_line`].
+    pub fn send_command(&mut self, command: &Command) -> Result<(), ClientError> {
+        let line = command.to_line(self.parser.flavor());
+        self.send(&line)
+    }
+
+    /// Send a DX spot and wait until the cluster confirms it by echoing the spot as `DX de <mycall>`.
+    /// Lines received meanwhile that are not related to the spot are kept for the iteration.
+    ///
+    /// ## Arguments
+    ///
+    /// * `spot`: Spot to send, must be a [`Command::Dx`]
+    /// * `timeout`: Time to wait for the confirmation
+    ///
+    /// ## Result
+    ///
+    /// In case the cluster confirmed the spot, the echoed spot shall be returned.
+    /// In case the cluster rejected the spot or did not confirm it in time, the typed failure shall be returned, see [`SubmitError`].
+    pub fn submit(&mut self, spot: &Command, timeout: Duration) -> Result<DX, SubmitError> {
+        let submission = Submission::new(&self.callsign, spot)?;
+        self.send_command(spot)?;
+
+        let deadline = Instant::now() + timeout;
+        let res = loop {
+            let line = match self.read_line_until(deadline) {
+                Ok(Some(line)) => line,
+                Ok(None) => break Err(SubmitError::Timeout),
+                Err(e) => break Err(e.into()),
+            };
+            match submission.check(&line, &self.parser) {
+                Some(res) => break res,
+                None if line.boundary != Boundary::Prompt => self.pending.push_back(line),
+                None => {}
+            }
+        };
+
+        self.stream
+            .tcp()
+            .set_read_timeout(self.read_timeout)
+            .map_err(ClientError::from)?;
+        res
+    }
+
+    /// Send a command to the cluster, the line ending is appended.
+    pub fn send(&mut self, command: &str) -> Result<(), ClientError> {
+        self.stream
+            .write_all(format!("{}\r\n", command).as_bytes())?;
+        Ok(self.stream.flush()?)
+    }
+
+    /// Read the next line received from the cluster, blocking until one is complete.
+    ///
+    /// ## Result
+    ///
+    /// In case a line was received, the line shall be returned.
+    /// In case the connection was closed, `None` shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn read_line(&mut self) -> Result<Option<FramedLine>, ClientError> {
+        loop {
+            if let Some(line) = self.pending.pop_front().or_else(|| self.framer.next()) {
+                return Ok(Some(line));
+            }
+            if !self.fill()? {
+                // The flush at the end of the stream may have completed a last line
+                return Ok(self.framer.next());
+            }
+        }
+    }
+
+    /// Take the error that ended the iteration, if any.
+    pub fn take_error(&mut self) -> Option<ClientError> {
+        self.error.take()
+    }
+
+    /// Get a handle to the socket of the connection, e.g. to shut it down from another thread.
+    pub(crate) fn socket(&self) -> std::io::Result<TcpStream> {
+        self.stream.tcp().try_clone()
+    }
+
+    /// Log out with `bye` and close the connection.
+    pub fn disconnect(mut self) -> Result<(), ClientError> {
+        self.send("bye")?;
+        Ok(self.stream.tcp().shutdown(Shutdown::Both)?)
+    }
+
+    fn login(
+        &mut self,
+        callsign: &str,
+        password: Option<&str>,
+        timeout: Duration,
+    ) -> Result<(), ClientError> {
+        let mut login = Login::new(callsign, password);
+        let mut deadline = Instant::now() + timeout;
+        loop {
+            let line = match self.read_line_until(deadline)? {
+                Some(line) => line,
+                None => return login.timeout(),
+            };
+            match login.step(&line, &self.parser)? {
+                Step::Skip => {}
+                Step::Send(text) => {
+                    self.send(&text)?;
+                    deadline = Instant::now() + timeout;
+                }
+                Step::Keep => self.pending.push_back(line),
+                Step::Done => return Ok(()),
+                Step::KeepDone => {
+                    self.pending.push_back(line);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Read the next line until the deadline, `None` is returned in case the deadline passed.
+    fn read_line_until(&mut self, deadline: Instant) -> Result<Option<FramedLine>, ClientError> {
+        loop {
+            if let Some(line) = self.framer.next() {
+                return Ok(Some(line));
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            // An incomplete line may be a prompt, which is only known once the cluster stays silent
+            let wait = match self.framer.buffered() {
+                0 => deadline - now,
+                _ => PROMPT_IDLE.min(deadline - now),
+            };
+            self.stream.tcp().set_read_timeout(Some(wait))?;
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => return self.framer.next().map(Some).ok_or(ClientError::Closed),
+                Err(ClientError::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    self.framer.idle()
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Read a chunk from the stream into the framer, `false` is returned at the end of the stream.
+    fn fill(&mut self) -> Result<bool, ClientError> {
+        let mut buf = [0; 4096];
+        let n = self.stream.read(&mut buf)?;
+        if n == 0 {
+            self.framer.flush();
+            return Ok(false);
+        }
+        self.framer.push(&buf[..n]);
+        Ok(true)
+    }
+}
+
+/// Connection to a cluster, either plain or via TLS
+enum Transport {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Transport {
+    /// Get the underlying socket, e.g. to set a timeout.
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Transport::Tcp(stream) => stream,
+            #[cfg(feature = "tls")]
+            Transport::Tls(stream) => stream.get_ref(),
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Transport::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Transport::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Transport::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Transport::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Transport::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Transport::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+impl Iterator for ClusterClient {
+    type Item = Result<Spot, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.read_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            };
+
+            if line.boundary != Boundary::Prompt {
+                return Some(self.parser.parse_bytes(&line.bytes).map(|res| res.spot));
+            }
+        }
+    }
+}
+
+/// Next step of the login after a line was received
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// Drop the line, e.g. the banner of the cluster
+    Skip,
+
+    /// Send the text to the cluster
+    Send(String),
+
+    /// Keep the line for the caller
+    Keep,
+
+    /// Login complete
+    Done,
+
+    /// Keep the line for the caller, login complete
+    KeepDone,
+}
+
+/// Login to a cluster, independent of the transport.
+///
+/// The callsign is sent as soon as the cluster prompts for it, everything in front of the prompt is dropped.
+/// Lines received after the callsign was sent are kept.
+pub(crate) struct Login {
+    callsign: String,
+    password: Option<String>,
+    sent: bool,
+}
+
+impl Login {
+    pub(crate) fn new(callsign: &str, password: Option<&str>) -> Self {
+        Login {
+            callsign: String::from(callsign),
+            password: password.map(String::from),
+            sent: false,
+        }
+    }
+
+    /// Get the next step after a line was received.
+    pub(crate) fn step(&mut self, line: &FramedLine, parser: &Parser) -> Result<Step, ClientError> {
+        if !self.sent {
+            if is_prompt(line, LOGIN_PROMPTS) {
+                self.sent = true;
+                return Ok(Step::Send(self.callsign.clone()));
+            }
+            return Ok(Step::Skip);
+        }
+
+        if is_prompt(line, PASSWORD_PROMPTS) {
+            return self
+                .password
+                .clone()
+                .map(Step::Send)
+                .ok_or(ClientError::PasswordRequired);
+        }
+        if line.boundary == Boundary::Prompt {
+            return Ok(Step::Done);
+        }
+        match parser.parse_bytes(&line.bytes) {
+            Ok(_) => Ok(Step::KeepDone),
+            Err(_) => Ok(Step::Keep),
+        }
+    }
+
+    /// Get the result in case no line was received in time.
+    /// Without the prompt for the callsign the login failed, otherwise the cluster simply shows no prompt.
+    pub(crate) fn timeout(&self) -> Result<(), ClientError> {
+        if self.sent {
+            Ok(())
+        } else {
+            Err(ClientError::LoginTimeout)
+        }
+    }
+}
+
+/// Check whether a line is a prompt containing one of the given words.
+fn is_prompt(line: &FramedLine, words: &[&str]) -> bool {
+    let text = String::from_utf8_lossy(&line.bytes).to_lowercase();
+    (line.boundary == Boundary::Prompt || text.ends_with(':'))
+        && words.iter().any(|w| text.contains(w))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::fixtures::SPOT;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Start a stand-in cluster running the script on the first connection.
+    /// The script gets the stream and a reader for the lines sent by the client.
+    fn cluster<F>(script: F) -> (String, JoinHandle<()>)
+    where
+        F: FnOnce(&mut TcpStream, &mut dyn FnMut() -> String) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut read = move || {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                String::from(line.trim_end())
+            };
+            script(&mut stream, &mut read);
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn client_login_and_commands() {
+        let (addr, handle) = cluster(|stream, read| {
+            stream
+                .write_all(b"\xff\xfb\x01Welcome to DB0SUE-7\r\n\r\nlogin: ")
+                .unwrap();
+            assert_eq!(read(), "DL1ABC");
+            write!(
+                stream,
+                "Hello DL1ABC\r\nDL1ABC de DB0SUE-7 10-Sep-2022 2200Z dxspider >\x07\x07"
+            )
+            .unwrap();
+            assert_eq!(read(), "set/dxgrid");
+            assert_eq!(read(), "accept/spots on hf");
+            write!(
+                stream,
+                "\r\n\x1b[0;32m{}\x1b[0m\x07\r\nWX de A1BC: sunny\r\n",
+                SPOT
+            )
+            .unwrap();
+        });
+
+        let client = ClusterClient::builder("DL1ABC")
+            .command("set/dxgrid")
+            .command("accept/spots on hf")
+            .connect(addr)
+            .unwrap();
+        let res: Vec<_> = client.collect();
+        handle.join().unwrap();
+
+        assert_eq!(res.len(), 3);
+        assert!(res[0].is_err());
+        assert_eq!(res[1], crate::parse(SPOT));
+        assert!(matches!(res[2], Ok(Spot::WX(_))));
+    }
+
+    #[test]
+    fn client_password() {
+        let (addr, handle) = cluster(|stream, read| {
+            stream.write_all(b"Please enter your call: ").unwrap();
+            assert_eq!(read(), "DL1ABC");
+            stream.write_all(b"password: ").unwrap();
+            assert_eq!(read(), "secret");
+            write!(stream, "{}\r\n", SPOT).unwrap();
+            assert_eq!(read(), "bye");
+        });
+
+        let mut client = ClusterClient::builder("DL1ABC")
+            .password("secret")
+            .connect(addr)
+            .unwrap();
+        assert_eq!(client.next(), Some(crate::parse(SPOT)));
+        client.disconnect().unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn client_password_required() {
+        let (addr, handle) = cluster(|stream, read| {
+            stream.write_all(b"login: ").unwrap();
+            read();
+            stream.write_all(b"password: ").unwrap();
+        });
+
+        let res = ClusterClient::connect(addr, "DL1ABC");
+        assert!(matches!(res, Err(ClientError::PasswordRequired)));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn client_no_prompt_after_login() {
+        let (addr, handle) = cluster(|stream, read| {
+            stream.write_all(b"login: ").unwrap();
+            read();
+            stream
+                .write_all(b"Hello DL1ABC, this is W1NR-7\r\n")
+                .unwrap();
+            thread::sleep(Duration::from_millis(300));
+            write!(stream, "{}\r\n", SPOT).unwrap();
+        });
+
+        let client = ClusterClient::builder("DL1ABC")
+            .login_timeout(Duration::from_millis(100))
+            .connect(addr)
+            .unwrap();
+        let res: Vec<_> = client.collect();
+        handle.join().unwrap();
+
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[1], crate::parse(SPOT));
+    }
+
+    #[test]
+    fn client_login_timeout_and_closed() {
+        let (addr, handle) = cluster(|stream, _| {
+            stream.write_all(b"Welcome\r\n").unwrap();
+            thread::sleep(Duration::from_millis(300));
+        });
+        let res = ClusterClient::builder("DL1ABC")
+            .login_timeout(Duration::from_millis(100))
+            .connect(addr);
+        assert!(matches!(res, Err(ClientError::LoginTimeout)));
+        handle.join().unwrap();
+
+        let (addr, handle) = cluster(|stream, _| {
+            stream.write_all(b"Welcome\r\n").unwrap();
+        });
+        let res = ClusterClient::connect(addr, "DL1ABC");
+        assert!(matches!(res, Err(ClientError::Closed)));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn client_read_timeout() {
+        let (addr, handle) = cluster(|stream, read| {
+            stream.write_all(b"login: ").unwrap();
+            read();
+            stream.write_all(b"DL1ABC de DB0SUE-7 >").unwrap();
+            thread::sleep(PROMPT_IDLE + Duration::from_millis(300));
+        });
+
+        let mut client = ClusterClient::builder("DL1ABC")
+            .read_timeout(Duration::from_millis(100))
+            .connect(addr)
+            .unwrap();
+        assert_eq!(client.next(), None);
+        assert!(matches!(client.take_error(), Some(ClientError::Io(_))));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn client_submit() {
+        let (addr, handle) = cluster(|stream, read| {
+            stream.write_all(b"login: ").unwrap();
+            read();
+            stream.write_all(b"DL1ABC de DB0SUE-7 >").unwrap();
+            assert_eq!(read(), "DX 14025.0 3B9FR 599");
+            write!(
+                stream,
+                "{}\r\nDX de DL1ABC:     14025.0  3B9FR        599                            1812Z\r\n",
+                SPOT
+            )
+            .unwrap();
+            assert_eq!(read(), "DX 14025.0 3B9FR 599");
+            stream.write_all(b"Duplicate spot, not sent\r\n").unwrap();
+            assert_eq!(read(), "DX 7025.0 3B9FR");
+            thread::sleep(Duration::from_millis(300));
+        });
+
+        let mut client = ClusterClient::builder("DL1ABC").connect(addr).unwrap();
+        let spot = Command::dx(14025.0, "3B9FR", Some("599")).unwrap();
+        let dx = client.submit(&spot, Duration::from_secs(5)).unwrap();
+        assert_eq!(dx.call_de, "DL1ABC");
+        assert!(matches!(
+            client.submit(&spot, Duration::from_secs(5)),
+            Err(SubmitError::Duplicate(_))
+        ));
+        let spot = Command::dx(7025.0, "3B9FR", None).unwrap();
+        assert!(matches!(
+            client.submit(&spot, Duration::from_millis(100)),
+            Err(SubmitError::Timeout)
+        ));
+        assert!(matches!(
+            client.submit(&Command::Bye, Duration::from_millis(100)),
+            Err(SubmitError::NotSpot)
+        ));
+
+        // The spot of another station received meanwhile is kept
+        assert_eq!(client.next(), Some(crate::parse(SPOT)));
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::command::Command;
+use crate::error::ClientError;
+use crate::framer::*;
+use crate::parser::Parser;
+use crate::types::Spot;
+use bytes::BytesMut;
+use std::sync::Arc;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Frame decoded by a [`ClusterCodec`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ClusterFrame {
+    /// Line parsed into a spot
+    Spot(Spot),
+
+    /// Line that is no spot, e.g. a prompt or the answer to a command
+    Line(FramedLine),
+}
+
+/// Codec for the telnet interface of a DX Cluster, to be used with `tokio_util::codec::Framed`.
+///
+/// The decoder splits the received bytes into sanitized lines by a [`Framer`] and parses them by [`Parser::parse_bytes`].
+/// Lines that are no spots are yielded as they are.
+/// Since the codec does not know about time and `Framed` only decodes after new bytes arrived, a prompt is not yielded by the decoder.
+/// Instead it is returned by [`ClusterCodec::idle`], to be called via `Framed::codec_mut` once no frame arrived for a while.
+/// The encoder writes a command, either a [`Command`] or plain text, followed by CRLF.
+///
+/// ```
+/// use tokio_util::codec::Decoder;
+///
+/// let mut codec = dxclparser::ClusterCodec::new();
+/// let mut buf = bytes::BytesMut::from("login: ");
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// let frame = codec.idle();
+/// assert!(matches!(frame, Some(dxclparser::ClusterFrame::Line(_))));
+/// ```
+pub struct ClusterCodec {
+    framer: Framer,
+    parser: Arc<Parser>,
+}
+
+impl ClusterCodec {
+    /// Create a codec with the default framer and parser.
+    pub fn new() -> Self {
+        ClusterCodec {
+            framer: Framer::new(),
+            parser: Arc::new(Parser::new()),
+        }
+    }
+
+    /// Set the framer splitting the received bytes, e.g. with a different maximum length of a line.
+    pub fn framer(mut self, framer: Framer) -> Self {
+        self.framer = framer;
+        self
+    }
+
+    /// Set the parser used for the received lines, the parser may be shared with other codecs.
+    pub fn parser(mut self, parser: Arc<Parser>) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Signal that no bytes arrived for a while and take the buffered prompt, see [`Framer::idle`].
+    ///
+    /// ## Result
+    ///
+    /// The next frame shall be returned, i.e. the prompt in case all complete lines were already decoded.
+    /// `None` shall be returned in case the buffered bytes do not look like a prompt.
+    pub fn idle(&mut self) -> Option<ClusterFrame> {
+        self.framer.idle();
+        self.frame()
+    }
+
+    fn frame(&mut self) -> Option<ClusterFrame> {
+        let line = self.framer.next()?;
+        if line.boundary == Boundary::Prompt {
+            return Some(ClusterFrame::Line(line));
+        }
+
+        Some(match self.parser.parse_bytes(&line.bytes) {
+            Ok(res) => ClusterFrame::Spot(res.spot),
+            Err(_) => ClusterFrame::Line(line),
+        })
+    }
+}
+
+impl Default for ClusterCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for ClusterCodec {
+    type Item = ClusterFrame;
+    type Error = ClientError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if !src.is_empty() {
+            self.framer.push(&src.split());
+        }
+        Ok(self.frame())
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+        self.framer.flush();
+        Ok(self.frame())
+    }
+}
+
+impl Encoder<&str> for ClusterCodec {
+    type Error = ClientError;
+
+    fn encode(&mut self, item: &str, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(item.as_bytes());
+        dst.extend_from_slice(b"\r\n");
+        Ok(())
+    }
+}
+
+impl Encoder<Command> for ClusterCodec {
+    type Error = ClientError;
+
+    /// Write the command in the syntax of the flavour configured for the parser, see [`Command::to_line`].
+    fn encode(&mut self, item: Command, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let line = item.to_line(self.parser.flavor());
+        self.encode(line.as_str(), dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::fixtures::SPOT;
+
+    fn decode_all(codec: &mut ClusterCodec, chunks: &[&[u8]]) -> Vec<ClusterFrame> {
+        let mut buf = BytesMut::new();
+        let mut frames = Vec::new();
+        for chunk in chunks {
+            buf.extend_from_slice(chunk);
+            while let Some(frame) = codec.decode(&mut buf).unwrap() {
+                frames.push(frame);
+            }
+        }
+        while let Some(frame) = codec.decode_eof(&mut buf).unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn codec_decode() {
+        let mut codec = ClusterCodec::new();
+        let raw = format!("Hello\r\n\x1b[0;32m{}\x1b[0m\x07\r\nWX de A1BC: last", SPOT);
+        let chunks: Vec<_> = raw.as_bytes().chunks(5).collect();
+        let frames = decode_all(&mut codec, &chunks);
+
+        assert_eq!(frames.len(), 3);
+        assert!(matches!(&frames[0], ClusterFrame::Line(l) if l.bytes == b"Hello"));
+        assert_eq!(frames[1], ClusterFrame::Spot(crate::parse(SPOT).unwrap()));
+        assert!(matches!(&frames[2], ClusterFrame::Spot(Spot::WX(_))));
+    }
+
+    #[test]
+    fn codec_decode_prompt() {
+        let mut codec = ClusterCodec::new();
+        let mut buf = BytesMut::from("Hello DL1ABC, this is DB0SUE-7 in JO62:");
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b" welcome\r\nlogin: ");
+        assert!(
+            matches!(codec.decode(&mut buf).unwrap(), Some(ClusterFrame::Line(l)) if l.boundary == Boundary::Newline)
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        let frame = codec.idle();
+        assert!(
+            matches!(frame, Some(ClusterFrame::Line(l)) if l.bytes == b"login:" && l.boundary == Boundary::Prompt)
+        );
+    }
+
+    #[test]
+    fn codec_encode() {
+        let mut codec = ClusterCodec::new();
+        let mut buf = BytesMut::new();
+        codec.encode("DL1ABC", &mut buf).unwrap();
+        codec.encode("set/dxgrid", &mut buf).unwrap();
+        codec.encode("bye", &mut buf).unwrap();
+        assert_eq!(&buf[..], b"DL1ABC\r\nset/dxgrid\r\nbye\r\n");
+    }
+
+    #[test]
+    fn codec_encode_command() {
+        let parser = Parser::builder()
+            .flavor(crate::types::ClusterFlavor::ARCluster)
+            .build()
+            .unwrap();
+        let mut codec = ClusterCodec::new().parser(Arc::new(parser));
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                Command::dx(14025.0, "3B9FR", Some("599")).unwrap(),
+                &mut buf,
+            )
+            .unwrap();
+        codec
+            .encode(
+                Command::ShowDx {
+                    count: Some(5),
+                    call: None,
+                },
+                &mut buf,
+            )
+            .unwrap();
+        assert_eq!(&buf[..], b"DX 14025.0 3B9FR 599\r\nSH/DX/5\r\n");
+    }
+
+    #[tokio::test]
+    async fn codec_framed() {
+        use tokio::io::AsyncWriteExt;
+        use tokio_util::codec::Framed;
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut framed = Framed::new(client, ClusterCodec::new());
+        let writer = tokio::spawn(async move {
+            server
+                .write_all(format!("{}\r\n", SPOT).as_bytes())
+                .await
+                .unwrap();
+        });
+
+        let mut frames = Vec::new();
+        while let Some(frame) = std::future::poll_fn(|cx| {
+            futures_core::Stream::poll_next(std::pin::Pin::new(&mut framed), cx)
+        })
+        .await
+        {
+            frames.push(frame.unwrap());
+        }
+        writer.await.unwrap();
+        assert_eq!(
+            frames,
+            vec![ClusterFrame::Spot(crate::parse(SPOT).unwrap())]
+        );
+    }
+
+    /// Get the next frame, `None` in case no frame arrived for a while.
+    async fn next_frame<T>(
+        framed: &mut tokio_util::codec::Framed<T, ClusterCodec>,
+    ) -> Option<ClusterFrame>
+    where
+        T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        let next = std::future::poll_fn(|cx| {
+            futures_core::Stream::poll_next(std::pin::Pin::new(&mut *framed), cx)
+        });
+        let res = tokio::time::timeout(std::time::Duration::from_millis(200), next).await;
+        res.ok().flatten().map(Result::unwrap)
+    }
+
+    #[tokio::test]
+    async fn codec_framed_login() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        use tokio::net::{TcpListener, TcpStream};
+        use tokio_util::codec::Framed;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            stream.write_all(b"Hello\r\nlogin: ").await.unwrap();
+            let mut call = String::new();
+            stream.read_line(&mut call).await.unwrap();
+            stream
+                .write_all(format!("{}\r\n", SPOT).as_bytes())
+                .await
+                .unwrap();
+            call
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut framed = Framed::new(stream, ClusterCodec::new());
+        let frame = next_frame(&mut framed).await;
+        assert!(matches!(frame, Some(ClusterFrame::Line(l)) if l.bytes == b"Hello"));
+
+        // The prompt is not decoded, it is taken from the codec once nothing arrived for a while
+        assert_eq!(next_frame(&mut framed).await, None);
+        let frame = framed.codec_mut().idle();
+        assert!(
+            matches!(frame, Some(ClusterFrame::Line(l)) if l.bytes == b"login:" && l.boundary == Boundary::Prompt)
+        );
+
+        let mut buf = BytesMut::new();
+        framed.codec_mut().encode("DL1ABC", &mut buf).unwrap();
+        framed.get_mut().write_all(&buf).await.unwrap();
+        let frame = next_frame(&mut framed).await;
+        assert_eq!(frame, Some(ClusterFrame::Spot(crate::parse(SPOT).unwrap())));
+        assert_eq!(server.await.unwrap(), "DL1ABC\r\n");
+    }
+}
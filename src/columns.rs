@@ -0,0 +1,372 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::error::{ParseError, ParseErrorKind};
+use crate::parser::Parser;
+use crate::types::*;
+
+/// Layout of the columns of a DX spot.
+///
+/// Positions are given in characters from the start of the line.
+/// In case a field overflows its column, the fields following it are shifted to the right.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ColumnLayout {
+    /// Column the call of the spotting station starts at
+    pub call_de: usize,
+
+    /// Column the right-aligned frequency ends at (exclusive)
+    pub freq_end: usize,
+
+    /// Column the call of the DX station starts at
+    pub call_dx: usize,
+
+    /// Column the comment starts at
+    pub comment: usize,
+
+    /// Column the comment ends at (exclusive), longer comments are truncated by the cluster
+    pub comment_end: usize,
+
+    /// Column the time starts at
+    pub time: usize,
+
+    /// Whether the locator is appended after the time
+    pub loc: bool,
+}
+
+impl ColumnLayout {
+    /// Layout of the DX spots common to all cluster software, with the locator of the spotter appended after the time
+    pub const DEFAULT: ColumnLayout = ColumnLayout {
+        call_de: 6,
+        freq_end: 24,
+        call_dx: 26,
+        comment: 39,
+        comment_end: 69,
+        time: 70,
+        loc: true,
+    };
+
+    /// Get the layout of the DX spots of a cluster software.
+    ///
+    /// All cluster software share the columns of [`ColumnLayout::DEFAULT`], they only differ in whether a locator follows the time.
+    pub fn of(flavor: ClusterFlavor) -> Self {
+        match flavor {
+            // The locator of the spotter is appended after the time with SET/DXGRID or SET/GRID
+            ClusterFlavor::DXSpider | ClusterFlavor::CCCluster | ClusterFlavor::Unknown => {
+                ColumnLayout::DEFAULT
+            }
+            // The line ends with the time
+            ClusterFlavor::ARCluster | ClusterFlavor::RBN => ColumnLayout {
+                loc: false,
+                ..ColumnLayout::DEFAULT
+            },
+        }
+    }
+
+    /// Check whether the comment of a DX spot filled its whole column and was therefore probably truncated by the cluster.
+    /// Columns overflowed by preceding fields are taken into account by the position of the time.
+    pub(crate) fn truncates(&self, line: &str) -> bool {
+        let line = line.trim_end();
+        let time_pos = match line.rsplit_once(' ') {
+            Some((head, last)) if is_loc(last) => {
+                let head = head.trim_end();
+                head.rfind(' ').map_or(0, |p| p + 1)
+            }
+            Some((head, _)) => head.len() + 1,
+            None => return false,
+        };
+        let comment_end = line[..time_pos].trim_end().len();
+        comment_end >= self.comment_end + time_pos.saturating_sub(self.time)
+    }
+}
+
+impl Parser {
+    /// Parse a spot of the type DX by the columns of its line.
+    ///
+    /// The layout of the columns is taken from the flavour configured for the parser, see [`ColumnLayout::of`].
+    /// Unlike the regular expression this copes with fields that overflowed their column and merged with the following field,
+    /// e.g. a long call of the spotting station glued to the frequency.
+    /// [`Parser::parse`] falls back to this in case a spot of the type DX does not match its regular expression.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw spot that is already cleaned from newline or bell characters etc.
+    ///
+    /// ## Result
+    ///
+    /// In case the spot was parsed successfully, the spot shall be returned. Whether the comment was truncated is given by [`DX::truncated`].
+    /// In case of an error the occurred error shall be returned.
+    pub fn parse_columns(&self, raw: &str) -> Result<DX, ParseError> {
+        parse_columns(raw, &ColumnLayout::of(self.flavor()))
+            .map_err(|e| e.with_spot_type(SpotType::DX))
+    }
+}
+
+fn parse_columns(raw: &str, layout: &ColumnLayout) -> Result<DX, ParseError> {
+    if !raw.is_ascii() || !raw.starts_with("DX de ") {
+        return Err(ParseErrorKind::InvalidContent.into());
+    }
+
+    let mut dx = DX::new();
+    let line = raw.trim_end();
+
+    // Time and locator, right-most fields of the line
+    let (time_pos, time) = match line.rsplit_once(' ') {
+        Some((head, last)) if layout.loc && is_loc(last) => {
+            dx.loc = Some(String::from(last));
+            let head = head.trim_end();
+            let pos = head.rfind(' ').map_or(0, |p| p + 1);
+            (pos, &head[pos..])
+        }
+        Some((head, last)) => (head.len() + 1, last),
+        None => (0, line),
+    };
+    if time.len() != 5 || !time.ends_with('Z') {
+        return Err(invalid("utc", time_pos, time, "time as HHMM followed by Z"));
+    }
+    dx.utc = time[..4]
+        .parse()
+        .map_err(|_| invalid("utc", time_pos, time, "time as HHMM followed by Z"))?;
+
+    // Call of the spotting station, followed by the right-aligned frequency.
+    // In case the call overflowed its column, the colon may be missing.
+    if time_pos < layout.call_de {
+        return Err(invalid("utc", time_pos, time, "time as HHMM followed by Z"));
+    }
+    let body = &line[layout.call_de..time_pos];
+    let word_end = body.find(' ').unwrap_or(body.len());
+    let (call_de, rest) = match body[..word_end].find(':') {
+        Some(colon) => (&body[..colon], &body[colon + 1..]),
+        None => {
+            let word = &body[..word_end];
+            let freq_start = freq_suffix_start(word);
+            (&word[..freq_start], &body[freq_start..])
+        }
+    };
+    if !is_call(call_de) {
+        return Err(invalid(
+            "call_de",
+            layout.call_de,
+            call_de,
+            "callsign of the spotter",
+        ));
+    }
+    dx.call_de = String::from(call_de);
+
+    let rest = rest.trim_start();
+    let freq_len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rest.len());
+    let freq = &rest[..freq_len];
+    match freq.parse::<f64>() {
+        Ok(f) if f > 0.0 => dx.freq = (f * 1000.0).round() as u64,
+        _ => {
+            return Err(invalid(
+                "freq",
+                offset(line, freq),
+                freq,
+                "frequency in kHz",
+            ))
+        }
+    }
+
+    // Call of the DX station, possibly merged with the comment
+    let rest = rest[freq_len..].trim_start();
+    let call_len = rest.find(|c: char| !is_call_char(c)).unwrap_or(rest.len());
+    let call_dx = &rest[..call_len];
+    if !is_call(call_dx) {
+        return Err(invalid(
+            "call_dx",
+            offset(line, call_dx),
+            call_dx,
+            "callsign of the DX station",
+        ));
+    }
+    dx.call_dx = String::from(call_dx);
+
+    let comment = rest[call_len..].trim();
+    if !comment.is_empty() {
+        dx.comment = Some(String::from(comment));
+        dx.truncated = layout.truncates(line);
+    }
+
+    Ok(dx)
+}
+
+/// Get the start of the frequency glued to the end of a word.
+/// The frequency is formatted with a single decimal place.
+fn freq_suffix_start(word: &str) -> usize {
+    let b = word.as_bytes();
+    if b.len() < 3 || b[b.len() - 2] != b'.' || !b[b.len() - 1].is_ascii_digit() {
+        return b.len();
+    }
+
+    let mut start = b.len() - 2;
+    while start > 0 && b[start - 1].is_ascii_digit() {
+        start -= 1;
+    }
+    start
+}
+
+/// Get the position of a slice within the line it was taken from.
+fn offset(line: &str, sub: &str) -> usize {
+    sub.as_ptr() as usize - line.as_ptr() as usize
+}
+
+fn is_call_char(c: char) -> bool {
+    c.is_ascii_uppercase() || c.is_ascii_digit() || "/-#".contains(c)
+}
+
+fn is_call(val: &str) -> bool {
+    val.len() >= 3 && val.chars().all(is_call_char)
+}
+
+fn is_loc(val: &str) -> bool {
+    let b = val.as_bytes();
+    b.len() == 4
+        && b[0].is_ascii_alphabetic()
+        && b[1].is_ascii_alphabetic()
+        && b[2].is_ascii_digit()
+        && b[3].is_ascii_digit()
+}
+
+fn invalid(field: &str, pos: usize, text: &str, expected: &str) -> ParseError {
+    ParseError::new(ParseErrorKind::InvalidContent)
+        .with_field(field)
+        .with_span(pos..pos + text.len(), text)
+        .with_expected(expected)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn columns_valid_dxspider() {
+        let spot =
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62";
+        let res = Parser::new().parse_columns(spot).unwrap();
+        assert_eq!(Ok(Spot::DX(res.clone())), crate::parse(spot));
+        assert!(!res.truncated);
+    }
+
+    #[test]
+    fn columns_spotter_glued_to_freq() {
+        let spot =
+            "DX de PA/DL1ABC/P14025.0  3B9FR        599                            1812Z JO62";
+        let res = Parser::new().parse(spot);
+        let exp = Spot::DX(DX {
+            call_de: "PA/DL1ABC/P".into(),
+            call_dx: "3B9FR".into(),
+            freq: 14025000,
+            utc: 1812,
+            loc: Some("JO62".into()),
+            comment: Some("599".into()),
+            truncated: false,
+        });
+        assert_eq!(res, Ok(exp));
+    }
+
+    #[test]
+    fn columns_dx_merged_with_comment() {
+        let spot = "DX de DL1ABC:     14025.0  VP2V/DL1ABC/MMtnx fer qso                 1812Z";
+        let res = Parser::new().parse(spot);
+        let exp = Spot::DX(DX {
+            call_de: "DL1ABC".into(),
+            call_dx: "VP2V/DL1ABC/MM".into(),
+            freq: 14025000,
+            utc: 1812,
+            loc: None,
+            comment: Some("tnx fer qso".into()),
+            truncated: false,
+        });
+        assert_eq!(res, Ok(exp));
+    }
+
+    #[test]
+    fn columns_truncated_comment() {
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI, strong signals 1812Z";
+        let res = Parser::new().parse_columns(spot).unwrap();
+        assert_eq!(res.comment, Some("599 into N. MI, strong signals".into()));
+        assert!(res.truncated);
+        assert_eq!(crate::parse(spot), Ok(Spot::DX(res)));
+    }
+
+    #[test]
+    fn columns_truncated_after_overflow() {
+        let spot =
+            "DX de PA/DL1ABC/P14025.0  3B9FR        599 into N. MI, strong signals 1812Z JO62";
+        match Parser::new().parse(spot) {
+            Ok(Spot::DX(dx)) => {
+                assert_eq!(dx.call_de, "PA/DL1ABC/P");
+                assert!(dx.truncated);
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn columns_truncated_in_json() {
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI, strong signals 1812Z";
+        let json = crate::parse(spot).unwrap().to_json();
+        assert!(json.contains("\"truncated\":true"));
+
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI                 1812Z";
+        let json = crate::parse(spot).unwrap().to_json();
+        assert!(!json.contains("truncated"));
+    }
+
+    #[test]
+    fn columns_layout_of_flavor() {
+        let flavors = [
+            ClusterFlavor::DXSpider,
+            ClusterFlavor::ARCluster,
+            ClusterFlavor::CCCluster,
+            ClusterFlavor::RBN,
+            ClusterFlavor::Unknown,
+        ];
+        let parser = |flavor| Parser::builder().flavor(flavor).build().unwrap();
+
+        // Spots without locator are parsed alike
+        let spot = "DX de PA/DL1ABC/P14025.0  3B9FR        599 into N. MI, strong signals 1812Z";
+        let exp = Parser::new().parse_columns(spot).unwrap();
+        for flavor in flavors {
+            assert_eq!(parser(flavor).parse_columns(spot), Ok(exp.clone()));
+        }
+
+        // A locator after the time is only taken by the cluster software appending one
+        let spot =
+            "DX de PA/DL1ABC/P14025.0  3B9FR        599 into N. MI, strong signals 1812Z JO62";
+        for flavor in flavors {
+            let res = parser(flavor).parse_columns(spot);
+            if ColumnLayout::of(flavor).loc {
+                assert_eq!(res.unwrap().loc, Some("JO62".into()));
+            } else {
+                assert_eq!(res.unwrap_err().field(), Some("utc"));
+            }
+        }
+        assert!(ColumnLayout::of(ClusterFlavor::DXSpider).loc);
+        assert!(ColumnLayout::of(ClusterFlavor::CCCluster).loc);
+        assert!(!ColumnLayout::of(ClusterFlavor::ARCluster).loc);
+        assert!(!ColumnLayout::of(ClusterFlavor::RBN).loc);
+    }
+
+    #[test]
+    fn columns_arcluster_no_loc() {
+        let spot = "DX de N2CQ:      14036.1  W0BH         OK QSO Party: Major            1624Z";
+        let parser = Parser::builder()
+            .flavor(ClusterFlavor::ARCluster)
+            .build()
+            .unwrap();
+        let res = parser.parse_columns(spot).unwrap();
+        assert_eq!(res.comment, Some("OK QSO Party: Major".into()));
+        assert_eq!(res.loc, None);
+    }
+
+    #[test]
+    fn columns_invalid() {
+        let res = Parser::new().parse_columns("DX de DF2MX");
+        assert_eq!(res.unwrap_err().field(), Some("utc"));
+    }
+}
@@ -0,0 +1,625 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::band::Band;
+use crate::error::CommandError;
+use crate::types::{ClusterFlavor, Spot};
+use std::fmt;
+use std::str::FromStr;
+
+/// Maximum length of the comment of a DX spot, longer comments are truncated by the cluster
+pub const MAX_COMMENT_LEN: usize = 30;
+
+/// Maximum length of the text of an announcement or a talk message
+pub const MAX_TEXT_LEN: usize = 200;
+
+/// Frequency of a spot, validated to be within the range of amateur radio
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Frequency(u64);
+
+impl Frequency {
+    /// Create a frequency from Hz.
+    ///
+    /// ## Arguments
+    ///
+    /// * `hz`: Frequency in Hz, between 100 kHz and 300 GHz
+    ///
+    /// ## Result
+    ///
+    /// In case the frequency is within range, the frequency shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn from_hz(hz: u64) -> Result<Self, CommandError> {
+        if (100_000..=300_000_000_000).contains(&hz) {
+            Ok(Frequency(hz))
+        } else {
+            Err(CommandError::InvalidFrequency(format!("{} Hz", hz)))
+        }
+    }
+
+    /// Create a frequency from kHz, like it is given in a spot, see [`Frequency::from_hz`].
+    pub fn from_khz(khz: f64) -> Result<Self, CommandError> {
+        if !khz.is_finite() || khz < 0.0 {
+            return Err(CommandError::InvalidFrequency(format!("{} kHz", khz)));
+        }
+        Self::from_hz((khz * 1000.0).round() as u64)
+    }
+
+    /// Get the frequency in Hz.
+    pub fn hz(&self) -> u64 {
+        self.0
+    }
+
+    /// Get the band the frequency lies within.
+    pub fn band(&self) -> Option<Band> {
+        Band::of(self.0)
+    }
+}
+
+impl fmt::Display for Frequency {
+    /// Format the frequency in kHz with one decimal place, e.g. `14025.0`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1}", self.0 as f64 / 1000.0)
+    }
+}
+
+/// Callsign, validated to consist of letters, digits and slashes only
+#[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
+pub struct Callsign(String);
+
+impl Callsign {
+    /// Create a callsign, letters are converted to uppercase.
+    ///
+    /// ## Arguments
+    ///
+    /// * `call`: Callsign with optional prefix or suffix, e.g. `PA/DL1ABC/P`
+    ///
+    /// ## Result
+    ///
+    /// In case the callsign is valid, the callsign shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn new(call: &str) -> Result<Self, CommandError> {
+        let call = call.to_ascii_uppercase();
+        let valid = (3..=20).contains(&call.len())
+            && call
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '/')
+            && call.chars().any(|c| c.is_ascii_digit())
+            && call.chars().any(|c| c.is_ascii_uppercase())
+            && !call.starts_with('/')
+            && !call.ends_with('/');
+
+        match valid {
+            true => Ok(Callsign(call)),
+            false => Err(CommandError::InvalidCallsign(call)),
+        }
+    }
+
+    /// Get the callsign as text.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Callsign {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Maidenhead locator of four or six characters
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Locator(String);
+
+impl Locator {
+    /// Create a locator, the field is converted to uppercase and the subsquare to lowercase, e.g. `JO62qm`.
+    pub fn new(loc: &str) -> Result<Self, CommandError> {
+        let b = loc.as_bytes();
+        let field = |c: u8| (b'A'..=b'R').contains(&c.to_ascii_uppercase());
+        let subsquare = |c: u8| (b'a'..=b'x').contains(&c.to_ascii_lowercase());
+        let valid = (b.len() == 4 || b.len() == 6)
+            && field(b[0])
+            && field(b[1])
+            && b[2].is_ascii_digit()
+            && b[3].is_ascii_digit()
+            && b[4..].iter().all(|&c| subsquare(c));
+
+        match valid {
+            true => Ok(Locator(format!(
+                "{}{}",
+                loc[..4].to_ascii_uppercase(),
+                loc[4..].to_ascii_lowercase()
+            ))),
+            false => Err(CommandError::InvalidLocator(String::from(loc))),
+        }
+    }
+
+    /// Get the locator as text.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Locator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Free text of a command, validated to be printable ASCII within a maximum length
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Text(String);
+
+impl Text {
+    /// Create a text of at most the given number of characters.
+    ///
+    /// ## Arguments
+    ///
+    /// * `text`: Text consisting of printable ASCII characters, surrounding whitespace is removed
+    /// * `max_len`: Maximum number of characters
+    ///
+    /// ## Result
+    ///
+    /// In case the text is valid, the text shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn new(text: &str, max_len: usize) -> Result<Self, CommandError> {
+        let text = text.trim();
+        if text.is_empty() || !text.bytes().all(|b| (0x20..0x7f).contains(&b)) {
+            return Err(CommandError::InvalidText(String::from(text)));
+        }
+        if text.len() > max_len {
+            return Err(CommandError::TextTooLong {
+                len: text.len(),
+                max: max_len,
+            });
+        }
+        Ok(Text(String::from(text)))
+    }
+
+    /// Get the text.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Spot filter set by a [`Command::Filter`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Filter {
+    /// Pass spots on the given bands only
+    AcceptBands(Vec<Band>),
+
+    /// Drop spots on the given bands
+    RejectBands(Vec<Band>),
+
+    /// Remove all spot filters
+    Clear,
+}
+
+impl Filter {
+    /// Check whether a spot passes the filter.
+    /// Only DX spots are filtered, spots of other types always pass.
+    pub fn passes(&self, spot: &Spot) -> bool {
+        let band = match spot {
+            Spot::DX(dx) => Band::of(dx.freq),
+            _ => return true,
+        };
+        match self {
+            Filter::AcceptBands(bands) => band.is_some_and(|b| bands.contains(&b)),
+            Filter::RejectBands(bands) => !band.is_some_and(|b| bands.contains(&b)),
+            Filter::Clear => true,
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = CommandError;
+
+    /// Parse a filter command in the syntax of DXSpider or AR-Cluster, see [`Command::to_line`].
+    /// The case is ignored and the commands of DXSpider may be abbreviated, e.g. `acc/spot on 20m,40m`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.trim().to_ascii_lowercase();
+        let unknown = || CommandError::UnknownCommand(String::from(s.trim()));
+        let (cmd, args) = line.split_once(' ').unwrap_or((&line, ""));
+        let (verb, target) = cmd.split_once('/').ok_or_else(unknown)?;
+        let mut args = args.split_whitespace();
+
+        match (verb, target) {
+            ("set", "nofilter") => Ok(Filter::Clear),
+            ("set", "filter") => match args.next() {
+                Some("dxband/pass") => Ok(Filter::AcceptBands(parse_bands(args)?)),
+                Some("dxband/reject") => Ok(Filter::RejectBands(parse_bands(args)?)),
+                _ => Err(unknown()),
+            },
+            (verb, target) if abbreviates(target, "spots", 3) => {
+                // Slots of DXSpider are not supported, every filter replaces the previous one
+                let mut args = args.skip_while(|a| a.bytes().all(|b| b.is_ascii_digit()));
+                if abbreviates(verb, "clear", 3) {
+                    Ok(Filter::Clear)
+                } else if args.next() != Some("on") {
+                    Err(unknown())
+                } else if abbreviates(verb, "accept", 3) {
+                    Ok(Filter::AcceptBands(parse_bands(args)?))
+                } else if abbreviates(verb, "reject", 3) {
+                    Ok(Filter::RejectBands(parse_bands(args)?))
+                } else {
+                    Err(unknown())
+                }
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+/// Parse a list of bands separated by commas or spaces, e.g. `20m,40m`.
+fn parse_bands<'a>(args: impl Iterator<Item = &'a str>) -> Result<Vec<Band>, CommandError> {
+    let bands = args
+        .flat_map(|a| a.split(','))
+        .filter(|b| !b.is_empty())
+        .map(|b| {
+            b.parse()
+                .map_err(|_| CommandError::InvalidBand(String::from(b)))
+        })
+        .collect::<Result<Vec<Band>, _>>()?;
+    match bands.is_empty() {
+        true => Err(CommandError::InvalidBand(String::new())),
+        false => Ok(bands),
+    }
+}
+
+/// Check whether a word is an abbreviation of a keyword of at least `min` characters.
+pub(crate) fn abbreviates(word: &str, keyword: &str, min: usize) -> bool {
+    word.len() >= min && keyword.starts_with(word)
+}
+
+/// Command sent to a DX Cluster.
+///
+/// The arguments are validated on creation, e.g. by [`Command::dx`].
+/// The syntax of some commands differs between the cluster softwares, see [`Command::to_line`].
+///
+/// ```
+/// use dxclparser::{ClusterFlavor, Command};
+///
+/// let cmd = Command::dx(14025.0, "3B9FR", Some("599")).unwrap();
+/// assert_eq!(cmd.to_line(ClusterFlavor::DXSpider), "DX 14025.0 3B9FR 599");
+///
+/// let cmd = Command::ShowDx { count: Some(20), call: None };
+/// assert_eq!(cmd.to_line(ClusterFlavor::DXSpider), "SH/DX 20");
+/// assert_eq!(cmd.to_line(ClusterFlavor::ARCluster), "SH/DX/20");
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    /// Spot a DX station
+    Dx {
+        freq: Frequency,
+        call: Callsign,
+        comment: Option<Text>,
+    },
+
+    /// Announcement to the users of the local node or, if `full`, of all nodes
+    Announce { text: Text, full: bool },
+
+    /// Message to a single user
+    Talk { call: Callsign, text: Text },
+
+    /// Set the locator of the own station
+    SetQra(Locator),
+
+    /// Show the last spots, optionally limited to a number and a DX station
+    ShowDx {
+        count: Option<u16>,
+        call: Option<Callsign>,
+    },
+
+    /// Show the last WWV announcements
+    ShowWwv { count: Option<u16> },
+
+    /// Set or clear a spot filter
+    Filter(Filter),
+
+    /// Log out
+    Bye,
+}
+
+impl Command {
+    /// Create a command spotting a DX station.
+    ///
+    /// ## Arguments
+    ///
+    /// * `khz`: Frequency in kHz
+    /// * `call`: Callsign of the DX station
+    /// * `comment`: Optional comment of at most [`MAX_COMMENT_LEN`] characters
+    ///
+    /// ## Result
+    ///
+    /// In case all arguments are valid, the command shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn dx(khz: f64, call: &str, comment: Option<&str>) -> Result<Self, CommandError> {
+        Ok(Command::Dx {
+            freq: Frequency::from_khz(khz)?,
+            call: Callsign::new(call)?,
+            comment: comment.map(|c| Text::new(c, MAX_COMMENT_LEN)).transpose()?,
+        })
+    }
+
+    /// Create an announcement to the users of the local node.
+    pub fn announce(text: &str) -> Result<Self, CommandError> {
+        Ok(Command::Announce {
+            text: Text::new(text, MAX_TEXT_LEN)?,
+            full: false,
+        })
+    }
+
+    /// Create a message to a single user.
+    pub fn talk(call: &str, text: &str) -> Result<Self, CommandError> {
+        Ok(Command::Talk {
+            call: Callsign::new(call)?,
+            text: Text::new(text, MAX_TEXT_LEN)?,
+        })
+    }
+
+    /// Create a command setting the locator of the own station.
+    pub fn set_qra(loc: &str) -> Result<Self, CommandError> {
+        Ok(Command::SetQra(Locator::new(loc)?))
+    }
+
+    /// Get the line sent to a cluster of the given software, without the line ending.
+    ///
+    /// DXSpider is the reference for the syntax, AR-Cluster and CC Cluster differ in passing the number of shown spots
+    /// as a qualifier, in the command setting the locator and in the syntax of filters.
+    /// RBN runs on CC Cluster, an unknown software is treated like DXSpider.
+    pub fn to_line(&self, flavor: ClusterFlavor) -> String {
+        let spider = matches!(flavor, ClusterFlavor::DXSpider | ClusterFlavor::Unknown);
+
+        match self {
+            Command::Dx {
+                freq,
+                call,
+                comment,
+            } => match comment {
+                Some(comment) => format!("DX {} {} {}", freq, call, comment),
+                None => format!("DX {} {}", freq, call),
+            },
+            Command::Announce { text, full } => match full {
+                true => format!("ANNOUNCE/FULL {}", text),
+                false => format!("ANNOUNCE {}", text),
+            },
+            Command::Talk { call, text } => format!("TALK {} {}", call, text),
+            Command::SetQra(loc) => match flavor {
+                ClusterFlavor::CCCluster | ClusterFlavor::RBN => format!("SET/GRID {}", loc),
+                _ => format!("SET/QRA {}", loc),
+            },
+            Command::ShowDx { count, call } => {
+                let mut line = String::from("SH/DX");
+                match (count, spider) {
+                    (Some(count), true) => line.push_str(&format!(" {}", count)),
+                    (Some(count), false) => line.push_str(&format!("/{}", count)),
+                    (None, _) => {}
+                }
+                if let Some(call) = call {
+                    line.push_str(&format!(" {}", call));
+                }
+                line
+            }
+            Command::ShowWwv { count } => match (count, spider) {
+                (Some(count), true) => format!("SH/WWV {}", count),
+                (Some(count), false) => format!("SH/WWV/{}", count),
+                (None, _) => String::from("SH/WWV"),
+            },
+            Command::Filter(filter) => filter_line(filter, spider),
+            Command::Bye => String::from("BYE"),
+        }
+    }
+}
+
+fn filter_line(filter: &Filter, spider: bool) -> String {
+    let bands = |bands: &[Band]| bands.iter().map(Band::name).collect::<Vec<_>>().join(",");
+
+    match (filter, spider) {
+        (Filter::AcceptBands(b), true) => format!("ACCEPT/SPOTS on {}", bands(b)),
+        (Filter::RejectBands(b), true) => format!("REJECT/SPOTS on {}", bands(b)),
+        (Filter::Clear, true) => String::from("CLEAR/SPOTS ALL"),
+        (Filter::AcceptBands(b), false) => format!("SET/FILTER DXBAND/PASS {}", bands(b)),
+        (Filter::RejectBands(b), false) => format!("SET/FILTER DXBAND/REJECT {}", bands(b)),
+        (Filter::Clear, false) => String::from("SET/NOFILTER"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn command_frequency() {
+        assert_eq!(Frequency::from_khz(14025.0).unwrap().hz(), 14025000);
+        assert_eq!(Frequency::from_khz(3780.05).unwrap().to_string(), "3780.1");
+        assert_eq!(
+            Frequency::from_hz(144300000).unwrap().band(),
+            Some(Band::M2)
+        );
+        assert!(Frequency::from_khz(0.0).is_err());
+        assert!(Frequency::from_khz(-14025.0).is_err());
+        assert!(Frequency::from_khz(f64::NAN).is_err());
+        assert!(matches!(
+            Frequency::from_hz(50),
+            Err(CommandError::InvalidFrequency(_))
+        ));
+    }
+
+    #[test]
+    fn command_callsign() {
+        assert_eq!(
+            Callsign::new("pa/dl1abc/p").unwrap().as_str(),
+            "PA/DL1ABC/P"
+        );
+        assert!(Callsign::new("3B9FR").is_ok());
+        for call in ["DL", "DLABC", "12345", "DL1ABC-7", "/DL1ABC", "DL1 ABC"] {
+            assert!(
+                matches!(Callsign::new(call), Err(CommandError::InvalidCallsign(_))),
+                "{}",
+                call
+            );
+        }
+    }
+
+    #[test]
+    fn command_locator() {
+        assert_eq!(Locator::new("jo62QM").unwrap().as_str(), "JO62qm");
+        assert_eq!(Locator::new("JO62").unwrap().as_str(), "JO62");
+        for loc in ["JO6", "ZZ62", "JO62q", "JO62zz", "J062"] {
+            assert!(Locator::new(loc).is_err(), "{}", loc);
+        }
+    }
+
+    #[test]
+    fn command_text() {
+        assert!(matches!(
+            Command::dx(
+                14025.0,
+                "3B9FR",
+                Some("599 into N. MI, strong signals, tnx")
+            ),
+            Err(CommandError::TextTooLong { len: 35, max: 30 })
+        ));
+        assert!(matches!(
+            Command::announce("hi\r\nbye"),
+            Err(CommandError::InvalidText(_))
+        ));
+        assert!(matches!(
+            Command::talk("DL1ABC", "   "),
+            Err(CommandError::InvalidText(_))
+        ));
+    }
+
+    #[test]
+    fn command_dx() {
+        let cmd = Command::dx(14025.0, "3b9fr", Some(" 599 ")).unwrap();
+        for flavor in [
+            ClusterFlavor::DXSpider,
+            ClusterFlavor::ARCluster,
+            ClusterFlavor::CCCluster,
+        ] {
+            assert_eq!(cmd.to_line(flavor), "DX 14025.0 3B9FR 599");
+        }
+        let cmd = Command::dx(3780.0, "OH5Z", None).unwrap();
+        assert_eq!(cmd.to_line(ClusterFlavor::DXSpider), "DX 3780.0 OH5Z");
+    }
+
+    #[test]
+    fn command_flavors() {
+        let cases = [
+            (
+                Command::announce("QRV on 6m").unwrap(),
+                "ANNOUNCE QRV on 6m",
+                "ANNOUNCE QRV on 6m",
+                "ANNOUNCE QRV on 6m",
+            ),
+            (
+                Command::talk("dl1abc", "tnx fer qso").unwrap(),
+                "TALK DL1ABC tnx fer qso",
+                "TALK DL1ABC tnx fer qso",
+                "TALK DL1ABC tnx fer qso",
+            ),
+            (
+                Command::set_qra("JO62qm").unwrap(),
+                "SET/QRA JO62qm",
+                "SET/QRA JO62qm",
+                "SET/GRID JO62qm",
+            ),
+            (
+                Command::ShowDx {
+                    count: Some(20),
+                    call: Some(Callsign::new("3B9FR").unwrap()),
+                },
+                "SH/DX 20 3B9FR",
+                "SH/DX/20 3B9FR",
+                "SH/DX/20 3B9FR",
+            ),
+            (
+                Command::ShowWwv { count: None },
+                "SH/WWV",
+                "SH/WWV",
+                "SH/WWV",
+            ),
+            (
+                Command::Filter(Filter::AcceptBands(vec![Band::M20, Band::M40])),
+                "ACCEPT/SPOTS on 20m,40m",
+                "SET/FILTER DXBAND/PASS 20m,40m",
+                "SET/FILTER DXBAND/PASS 20m,40m",
+            ),
+            (
+                Command::Filter(Filter::Clear),
+                "CLEAR/SPOTS ALL",
+                "SET/NOFILTER",
+                "SET/NOFILTER",
+            ),
+            (Command::Bye, "BYE", "BYE", "BYE"),
+        ];
+
+        for (cmd, spider, ar, cc) in cases {
+            if let Command::Filter(filter) = &cmd {
+                assert_eq!(spider.parse().as_ref(), Ok(filter));
+                assert_eq!(ar.parse().as_ref(), Ok(filter));
+            }
+            assert_eq!(cmd.to_line(ClusterFlavor::DXSpider), spider);
+            assert_eq!(cmd.to_line(ClusterFlavor::Unknown), spider);
+            assert_eq!(cmd.to_line(ClusterFlavor::ARCluster), ar);
+            assert_eq!(cmd.to_line(ClusterFlavor::CCCluster), cc);
+        }
+    }
+
+    #[test]
+    fn command_filter_parse() {
+        assert_eq!(
+            "acc/spot on 20m, 40 ".parse(),
+            Ok(Filter::AcceptBands(vec![Band::M20, Band::M40]))
+        );
+        assert_eq!(
+            "REJECT/SPOTS 1 on 6m".parse(),
+            Ok(Filter::RejectBands(vec![Band::M6]))
+        );
+        assert_eq!("clear/spots 1".parse(), Ok(Filter::Clear));
+        assert_eq!(
+            "accept/spots on 11m".parse::<Filter>(),
+            Err(CommandError::InvalidBand("11m".into()))
+        );
+        assert_eq!(
+            "accept/spots on".parse::<Filter>(),
+            Err(CommandError::InvalidBand("".into()))
+        );
+        for line in ["sh/dx", "accept/announce on 20m", "accept/spots 20m", "bye"] {
+            assert!(
+                matches!(line.parse::<Filter>(), Err(CommandError::UnknownCommand(_))),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn command_filter_passes() {
+        let dx = |freq| {
+            Spot::DX(crate::types::DX {
+                freq,
+                ..Default::default()
+            })
+        };
+        let filter = Filter::AcceptBands(vec![Band::M20]);
+        assert!(filter.passes(&dx(14025000)));
+        assert!(!filter.passes(&dx(7025000)));
+        assert!(!filter.passes(&dx(15000000)));
+        assert!(filter.passes(&Spot::WWV(Default::default())));
+
+        let filter = Filter::RejectBands(vec![Band::M20]);
+        assert!(!filter.passes(&dx(14025000)));
+        assert!(filter.passes(&dx(7025000)));
+        assert!(filter.passes(&dx(15000000)));
+        assert!(Filter::Clear.passes(&dx(14025000)));
+    }
+}
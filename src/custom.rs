@@ -0,0 +1,225 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::error::{BuildError, ParseError, ParseErrorKind};
+use crate::types::{Custom, Spot};
+use regex::Regex;
+use serde::Serialize;
+
+/// Recognizer and parser for a user-defined type of line.
+///
+/// Parsers registered with [`ParserBuilder::spot_parser`](crate::ParserBuilder::spot_parser) are tried in order of registration before the built-in types.
+/// The first parser recognizing a line parses it into a [`Spot::Custom`](crate::Spot::Custom).
+pub trait SpotParser: Send + Sync {
+    /// User-defined payload of the resulting spot
+    type Payload: Serialize;
+
+    /// Name of the custom type, used as kind of the resulting spot.
+    fn kind(&self) -> &str;
+
+    /// Check whether a line is of the custom type.
+    fn recognize(&self, raw: &str) -> bool;
+
+    /// Parse a line recognized as the custom type.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw line that is already cleaned from newline or bell characters etc.
+    ///
+    /// ## Result
+    ///
+    /// In case the line was parsed successfully, the payload shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    fn parse(&self, raw: &str) -> Result<Self::Payload, ParseError>;
+}
+
+/// Object safe recognizer and parser for additional types of lines registered with a parser.
+pub(crate) trait DynSpotParser: Send + Sync {
+    fn recognize(&self, raw: &str) -> bool;
+
+    fn parse_spot(&self, raw: &str) -> Result<Spot, ParseError>;
+}
+
+impl<P: SpotParser> DynSpotParser for P {
+    fn recognize(&self, raw: &str) -> bool {
+        SpotParser::recognize(self, raw)
+    }
+
+    fn parse_spot(&self, raw: &str) -> Result<Spot, ParseError> {
+        let payload = SpotParser::parse(self, raw)?;
+
+        Ok(Spot::Custom(Custom {
+            kind: String::from(self.kind()),
+            payload: serde_json::to_value(payload)
+                .map_err(|_| ParseError::new(ParseErrorKind::InternalError))?,
+        }))
+    }
+}
+
+/// Custom type of line recognized by its prefix and parsed by a regular expression.
+///
+/// The named capture groups of the expression make up the payload.
+pub struct RegexSpotParser {
+    kind: String,
+    prefix: String,
+    re: Regex,
+}
+
+impl RegexSpotParser {
+    /// Create a new parser for a custom type.
+    ///
+    /// ## Arguments
+    ///
+    /// * `kind`: Name of the custom type, used as kind of the resulting spot
+    /// * `prefix`: Prefix a line must start with to be considered of the custom type
+    /// * `pattern`: Regular expression the line must match, the named capture groups form the payload
+    ///
+    /// ## Result
+    ///
+    /// In case the pattern is invalid, an error shall be returned.
+    pub fn new(kind: &str, prefix: &str, pattern: &str) -> Result<Self, BuildError> {
+        let re = Regex::new(pattern)
+            .map_err(|e| BuildError::InvalidPattern(String::from(kind), e.to_string()))?;
+
+        Ok(RegexSpotParser {
+            kind: String::from(kind),
+            prefix: String::from(prefix),
+            re,
+        })
+    }
+}
+
+impl SpotParser for RegexSpotParser {
+    type Payload = serde_json::Map<String, serde_json::Value>;
+
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    fn recognize(&self, raw: &str) -> bool {
+        raw.starts_with(&self.prefix)
+    }
+
+    fn parse(&self, raw: &str) -> Result<Self::Payload, ParseError> {
+        let c = self.re.captures(raw).ok_or_else(|| {
+            ParseError::new(ParseErrorKind::InvalidContent).with_expected(&self.kind)
+        })?;
+
+        Ok(self
+            .re
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                c.name(name).map(|val| {
+                    (
+                        String::from(name),
+                        serde_json::Value::String(val.as_str().into()),
+                    )
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::Parser;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+    struct Bulletin {
+        club: String,
+        text: String,
+    }
+
+    struct BulletinParser;
+
+    impl SpotParser for BulletinParser {
+        type Payload = Bulletin;
+
+        fn kind(&self) -> &str {
+            "bulletin"
+        }
+
+        fn recognize(&self, raw: &str) -> bool {
+            raw.starts_with("BULLETIN ")
+        }
+
+        fn parse(&self, raw: &str) -> Result<Bulletin, ParseError> {
+            let (club, text) = raw["BULLETIN ".len()..]
+                .split_once(':')
+                .ok_or_else(|| ParseError::new(ParseErrorKind::InvalidContent))?;
+
+            Ok(Bulletin {
+                club: club.trim().into(),
+                text: text.trim().into(),
+            })
+        }
+    }
+
+    #[test]
+    fn custom_bulletin() {
+        let parser = Parser::builder()
+            .spot_parser(BulletinParser)
+            .build()
+            .unwrap();
+        let res = parser.parse("BULLETIN DARC: Field day on Saturday");
+        let exp = Bulletin {
+            club: "DARC".into(),
+            text: "Field day on Saturday".into(),
+        };
+        match res {
+            Ok(Spot::Custom(custom)) => {
+                assert_eq!(custom.kind, "bulletin");
+                assert_eq!(custom.payload_as::<Bulletin>().unwrap(), exp);
+            }
+            _ => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn custom_bulletin_invalid() {
+        let parser = Parser::builder()
+            .spot_parser(BulletinParser)
+            .build()
+            .unwrap();
+        let res = parser.parse("BULLETIN without club");
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
+    }
+
+    #[test]
+    fn custom_alongside_builtin() {
+        let parser = Parser::builder()
+            .spot_parser(BulletinParser)
+            .build()
+            .unwrap();
+        let spot = "DX de ZS6WN:     21075.4  CX2DAJ       FT8                            1625Z";
+        assert!(matches!(parser.parse(spot), Ok(Spot::DX(_))));
+        assert_eq!(
+            parser.parse("NOTHING").map_err(|e| e.kind()),
+            Err(ParseErrorKind::UnknownType)
+        );
+    }
+
+    #[test]
+    fn custom_regex_mismatch() {
+        let contest = RegexSpotParser::new(
+            "contest",
+            "CONTEST de",
+            r#"^CONTEST de (?P<call_de>[A-Z0-9/\-#]+): (?P<name>.*)$"#,
+        )
+        .unwrap();
+        let parser = Parser::builder().spot_parser(contest).build().unwrap();
+        let res = parser.parse("CONTEST de DL1ABC");
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
+    }
+}
@@ -0,0 +1,416 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::types::{Spot, DX};
+
+/// Default maximum difference of the frequencies of two spots of the same station (in Hz)
+pub const DEFAULT_FREQ_TOLERANCE: u64 = 1_000;
+
+/// Default time after the first spot of a station within which further spots are duplicates (in minutes)
+pub const DEFAULT_TIME_WINDOW: u16 = 10;
+
+/// Suffixes of the DX call that do not change the station, e.g. `/P` for portable operation
+const IGNORED_SUFFIXES: &[&str] = &["/P", "/M", "/MM", "/AM", "/QRP"];
+
+/// Minutes of a day
+const DAY: i32 = 24 * 60;
+
+/// Rule for the spots of a spotter
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpotterRule {
+    /// Drop all spots of the spotter
+    Ignore,
+
+    /// Never drop a spot of the spotter as duplicate, e.g. for the own callsign
+    Pass,
+
+    /// Use a different frequency tolerance (in Hz) for the spots of the spotter, e.g. a small one for skimmers
+    Tolerance(u64),
+}
+
+/// Result of checking a spot for duplicates
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Verdict {
+    /// First spot of the station on the frequency within the time window, or a spot of another type than DX
+    New,
+
+    /// Further spot of the station by another spotter
+    AlsoSpotted {
+        /// First spot of the station
+        first: DX,
+
+        /// Calls of all spotters of the station so far, in the order they spotted it
+        spotters: Vec<String>,
+    },
+
+    /// Repeated spot of the station, dropped
+    Duplicate,
+
+    /// Spot of an ignored spotter, dropped
+    Ignored,
+}
+
+/// Builder for a [`Dedup`]
+#[derive(Debug, Clone)]
+pub struct DedupBuilder {
+    freq_tolerance: u64,
+    time_window: u16,
+    also_spotted: bool,
+    rules: Vec<(String, SpotterRule)>,
+}
+
+impl DedupBuilder {
+    /// Create a builder with the default tolerances and without spotter rules.
+    pub fn new() -> Self {
+        DedupBuilder {
+            freq_tolerance: DEFAULT_FREQ_TOLERANCE,
+            time_window: DEFAULT_TIME_WINDOW,
+            also_spotted: false,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Set the maximum difference of the frequencies of two spots of the same station (in Hz), by default [`DEFAULT_FREQ_TOLERANCE`].
+    pub fn freq_tolerance(mut self, hz: u64) -> Self {
+        self.freq_tolerance = hz;
+        self
+    }
+
+    /// Set the time after the first spot of a station within which further spots are duplicates (in minutes), by default [`DEFAULT_TIME_WINDOW`].
+    pub fn time_window(mut self, minutes: u16) -> Self {
+        self.time_window = minutes;
+        self
+    }
+
+    /// Report further spotters of a station as [`Verdict::AlsoSpotted`] instead of dropping their spots as duplicates.
+    pub fn also_spotted(mut self, also_spotted: bool) -> Self {
+        self.also_spotted = also_spotted;
+        self
+    }
+
+    /// Add a rule for the spots of the matching spotters.
+    /// The first added rule matching a spotter applies.
+    ///
+    /// ## Arguments
+    ///
+    /// * `pattern`: Call of the spotter, `*` matches any number of characters, e.g. `DL1ABC*` or `*-#` for the skimmers of the RBN
+    /// * `rule`: Rule for the spots of the spotter
+    pub fn spotter(mut self, pattern: &str, rule: SpotterRule) -> Self {
+        self.rules.push((pattern.to_ascii_uppercase(), rule));
+        self
+    }
+
+    /// Create the deduplicator.
+    pub fn build(self) -> Dedup {
+        Dedup {
+            config: self,
+            seen: Vec::new(),
+        }
+    }
+}
+
+impl Default for DedupBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Deduplication of DX spots, e.g. of the merged feeds of several clusters.
+///
+/// Spots of the same station are recognized by the normalized DX call, a frequency within the tolerance
+/// and a time within the window after the first spot of the station.
+/// The DX call is compared case-insensitively and without suffixes like `/P`.
+/// As the spots only carry the time of the day, the time wraps around at midnight.
+///
+/// ```
+/// use dxclparser::{Dedup, Verdict};
+///
+/// let mut dedup = Dedup::builder().also_spotted(true).build();
+/// let first = dxclparser::parse("DX de DL1ABC:    14025.0  3B9FR        599                            1812Z").unwrap();
+/// let second = dxclparser::parse("DX de OH5Z:      14025.1  3B9FR        CQ                             1814Z").unwrap();
+/// assert_eq!(dedup.check(&first), Verdict::New);
+/// assert!(matches!(dedup.check(&second), Verdict::AlsoSpotted { spotters, .. } if spotters == ["DL1ABC", "OH5Z"]));
+/// assert_eq!(dedup.check(&second), Verdict::Duplicate);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dedup {
+    config: DedupBuilder,
+    seen: Vec<Seen>,
+}
+
+/// First spot of a station within the time window
+#[derive(Debug, Clone)]
+struct Seen {
+    call: String,
+    minute: i32,
+    first: DX,
+    spotters: Vec<String>,
+}
+
+impl Dedup {
+    /// Create a builder for a deduplicator.
+    pub fn builder() -> DedupBuilder {
+        DedupBuilder::new()
+    }
+
+    /// Check whether a spot is a duplicate of a spot checked before and remember it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `spot`: Spot to check, spots should be checked in the order they were received
+    ///
+    /// ## Result
+    ///
+    /// The verdict on the spot shall be returned, spots of other types than DX are always new.
+    pub fn check(&mut self, spot: &Spot) -> Verdict {
+        match spot {
+            Spot::DX(dx) => self.check_dx(dx),
+            _ => Verdict::New,
+        }
+    }
+
+    /// Check whether a DX spot is a duplicate of a spot checked before and remember it, see [`Dedup::check`].
+    pub fn check_dx(&mut self, dx: &DX) -> Verdict {
+        let spotter = dx.call_de.trim().to_ascii_uppercase();
+        let rule = self
+            .config
+            .rules
+            .iter()
+            .find(|(pattern, _)| matches_wildcard(pattern, &spotter))
+            .map(|(_, rule)| *rule);
+        if rule == Some(SpotterRule::Ignore) {
+            return Verdict::Ignored;
+        }
+
+        let minute = i32::from(dx.utc / 100) * 60 + i32::from(dx.utc % 100);
+        let window = i32::from(self.config.time_window);
+        self.seen.retain(|s| since(s.minute, minute) <= window);
+
+        let call = normalize_call(&dx.call_dx);
+        let tolerance = match rule {
+            Some(SpotterRule::Tolerance(hz)) => hz,
+            _ => self.config.freq_tolerance,
+        };
+        let Some(seen) = self.seen.iter_mut().find(|s| {
+            s.call == call
+                && s.first.freq.abs_diff(dx.freq) <= tolerance
+                && since(s.minute, minute).abs() <= window
+        }) else {
+            self.seen.push(Seen {
+                call,
+                minute,
+                first: dx.clone(),
+                spotters: vec![spotter],
+            });
+            return Verdict::New;
+        };
+
+        let known = seen.spotters.contains(&spotter);
+        if !known {
+            seen.spotters.push(spotter);
+        }
+        match rule {
+            Some(SpotterRule::Pass) => Verdict::New,
+            _ if known || !self.config.also_spotted => Verdict::Duplicate,
+            _ => Verdict::AlsoSpotted {
+                first: seen.first.clone(),
+                spotters: seen.spotters.clone(),
+            },
+        }
+    }
+}
+
+impl Default for Dedup {
+    fn default() -> Self {
+        DedupBuilder::new().build()
+    }
+}
+
+/// Get the minutes from `first` to `minute`, wrapped around midnight into the range of -12 to +12 hours.
+fn since(first: i32, minute: i32) -> i32 {
+    (minute - first + DAY / 2).rem_euclid(DAY) - DAY / 2
+}
+
+/// Normalize a DX call for the comparison, i.e. in upper case and without suffixes that do not change the station.
+fn normalize_call(call: &str) -> String {
+    let mut call = call.trim().to_ascii_uppercase();
+    while let Some(suffix) = IGNORED_SUFFIXES.iter().find(|s| call.ends_with(*s)) {
+        call.truncate(call.len() - suffix.len());
+    }
+    call
+}
+
+/// Check whether a text matches a pattern, in which `*` matches any number of characters.
+pub(crate) fn matches_wildcard(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, tail)) => {
+            let Some(rest) = text.strip_prefix(head) else {
+                return false;
+            };
+            (0..=rest.len())
+                .filter(|i| rest.is_char_boundary(*i))
+                .any(|i| matches_wildcard(tail, &rest[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn dx(call_de: &str, khz: f64, call_dx: &str, utc: u16) -> Spot {
+        Spot::DX(DX {
+            call_de: String::from(call_de),
+            call_dx: String::from(call_dx),
+            freq: (khz * 1000.0).round() as u64,
+            utc,
+            ..DX::new()
+        })
+    }
+
+    #[test]
+    fn dedup_tolerance() {
+        let mut dedup = Dedup::builder().freq_tolerance(500).time_window(5).build();
+        assert_eq!(
+            dedup.check(&dx("DL1ABC", 14025.0, "3B9FR", 1812)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 14025.1, "3B9FR", 1813)),
+            Verdict::Duplicate
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 14024.5, "3b9fr/p", 1817)),
+            Verdict::Duplicate
+        );
+
+        // Other frequency, other station, outside of the window
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 14025.6, "3B9FR", 1814)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 14025.0, "3B9F", 1814)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 14025.0, "3B9FR", 1818)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("DJ1TO", 14025.0, "3B9FR", 1823)),
+            Verdict::Duplicate
+        );
+
+        // Other types of spots are not deduplicated
+        let wwv =
+            crate::parse("WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms").unwrap();
+        assert_eq!(dedup.check(&wwv), Verdict::New);
+        assert_eq!(dedup.check(&wwv), Verdict::New);
+    }
+
+    #[test]
+    fn dedup_midnight() {
+        let mut dedup = Dedup::default();
+        assert_eq!(
+            dedup.check(&dx("DL1ABC", 7010.0, "ZL1AA", 2355)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 7010.0, "ZL1AA", 3)),
+            Verdict::Duplicate
+        );
+        assert_eq!(dedup.check(&dx("OH5Z", 7010.0, "ZL1AA", 10)), Verdict::New);
+
+        // Spots arriving slightly out of order
+        assert_eq!(
+            dedup.check(&dx("DJ1TO", 7010.0, "ZL1AA", 8)),
+            Verdict::Duplicate
+        );
+    }
+
+    #[test]
+    fn dedup_also_spotted() {
+        let mut dedup = Dedup::builder().also_spotted(true).build();
+        let first = dx("DL1ABC", 14025.0, "3B9FR", 1812);
+        assert_eq!(dedup.check(&first), Verdict::New);
+
+        let Spot::DX(first) = first else {
+            unreachable!()
+        };
+        assert_eq!(
+            dedup.check(&dx("oh5z", 14025.1, "3B9FR", 1813)),
+            Verdict::AlsoSpotted {
+                first: first.clone(),
+                spotters: vec![String::from("DL1ABC"), String::from("OH5Z")],
+            }
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z", 14025.1, "3B9FR", 1815)),
+            Verdict::Duplicate
+        );
+        assert_eq!(
+            dedup.check(&dx("DL1ABC", 14025.0, "3B9FR", 1815)),
+            Verdict::Duplicate
+        );
+        assert_eq!(
+            dedup.check(&dx("DJ1TO", 14024.9, "3B9FR", 1816)),
+            Verdict::AlsoSpotted {
+                first,
+                spotters: vec![
+                    String::from("DL1ABC"),
+                    String::from("OH5Z"),
+                    String::from("DJ1TO")
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn dedup_spotter_rules() {
+        let mut dedup = Dedup::builder()
+            .spotter("DL1ABC*", SpotterRule::Pass)
+            .spotter("*-#", SpotterRule::Tolerance(100))
+            .spotter("N0CALL", SpotterRule::Ignore)
+            .build();
+        assert_eq!(
+            dedup.check(&dx("N0CALL", 14025.0, "3B9FR", 1812)),
+            Verdict::Ignored
+        );
+        assert_eq!(
+            dedup.check(&dx("OH5Z-#", 14025.0, "3B9FR", 1812)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("DJ1TO-#", 14025.2, "3B9FR", 1812)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("DJ1TO", 14025.2, "3B9FR", 1812)),
+            Verdict::Duplicate
+        );
+        assert_eq!(
+            dedup.check(&dx("dl1abc-2", 14025.0, "3B9FR", 1813)),
+            Verdict::New
+        );
+        assert_eq!(
+            dedup.check(&dx("DL1ABC-2", 14025.0, "3B9FR", 1813)),
+            Verdict::New
+        );
+    }
+
+    #[test]
+    fn dedup_wildcard() {
+        assert!(matches_wildcard("DL1ABC", "DL1ABC"));
+        assert!(!matches_wildcard("DL1ABC", "DL1ABC-2"));
+        assert!(matches_wildcard("DL1ABC*", "DL1ABC-2"));
+        assert!(matches_wildcard("*-#", "OH5Z-#"));
+        assert!(matches_wildcard("D*1*C", "DL1ABC"));
+        assert!(matches_wildcard("*", ""));
+        assert!(!matches_wildcard("D*1*C", "DL2ABC"));
+        assert!(!matches_wildcard("*-#", "OH5Z-2"));
+    }
+}
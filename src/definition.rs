@@ -0,0 +1,393 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::custom::DynSpotParser;
+use crate::error::{BuildError, DefinitionError, ParseError, ParseErrorKind};
+use crate::types::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Set of line definitions, e.g. loaded from a config file
+///
+/// In toml format each definition is given as `[[line]]` table, in json format as element of the `line` array.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Definitions {
+    /// Definitions of types of lines
+    #[serde(rename = "line", default)]
+    pub lines: Vec<LineDefinition>,
+}
+
+impl Definitions {
+    /// Parse definitions given in toml format.
+    pub fn from_toml(raw: &str) -> Result<Self, DefinitionError> {
+        toml::from_str(raw).map_err(|e| DefinitionError::Toml(e.to_string()))
+    }
+
+    /// Parse definitions given in json format.
+    pub fn from_json(raw: &str) -> Result<Self, DefinitionError> {
+        serde_json::from_str(raw).map_err(|e| DefinitionError::Json(e.to_string()))
+    }
+
+    /// Load definitions from a file.
+    /// The format is detected by the file extension, either `.toml` or `.json`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DefinitionError> {
+        let path = path.as_ref();
+        let format = path.extension().and_then(|ext| ext.to_str());
+
+        match format {
+            Some("toml") => Self::from_toml(&fs::read_to_string(path)?),
+            Some("json") => Self::from_json(&fs::read_to_string(path)?),
+            _ => Err(DefinitionError::UnknownFormat),
+        }
+    }
+}
+
+/// Definition of a type of line
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LineDefinition {
+    /// Name of the type of line, used as kind in case a custom spot is produced
+    pub kind: String,
+
+    /// Built-in type of spot the line is parsed into, a custom spot if not set
+    #[serde(default)]
+    pub spot_type: Option<SpotType>,
+
+    /// Prefix a line must start with to be considered of this type
+    pub prefix: String,
+
+    /// Regular expression the line must match
+    pub regex: String,
+
+    /// Mapping of the fields of the spot to the capture groups of the regular expression
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldDefinition>,
+}
+
+/// Definition of a single field
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldDefinition {
+    /// Capture group holding the value of the field
+    pub capture: Capture,
+
+    /// Type of the field
+    #[serde(rename = "type", default)]
+    pub field_type: FieldType,
+
+    /// Whether the field may be missing
+    #[serde(default)]
+    pub optional: bool,
+}
+
+/// Capture group of a regular expression
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Capture {
+    /// Capture group identified by its index
+    Index(usize),
+
+    /// Capture group identified by its name
+    Name(String),
+}
+
+/// Type of a field
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    /// Text
+    #[default]
+    String,
+
+    /// Signed or unsigned integer
+    Integer,
+
+    /// Floating point number
+    Float,
+
+    /// Frequency in kHz, converted into Hz
+    Khz,
+}
+
+/// Required fields of the built-in types of spots
+fn required_fields(spot_type: SpotType) -> &'static [&'static str] {
+    match spot_type {
+        SpotType::DX => &["call_de", "call_dx", "freq", "utc"],
+        SpotType::WWV => &["call_de", "utc", "sfi", "a", "k", "info1", "info2"],
+        SpotType::WCY => &[
+            "call_de", "utc", "k", "expk", "a", "r", "sfi", "sa", "gmf", "au",
+        ],
+        SpotType::WX | SpotType::ToAll | SpotType::ToLocal => &["call_de"],
+    }
+}
+
+/// Compiled line definition
+pub(crate) struct DefinitionParser {
+    kind: String,
+    spot_type: Option<SpotType>,
+    prefix: String,
+    re: Regex,
+    fields: Vec<(String, FieldDefinition)>,
+}
+
+impl DefinitionParser {
+    pub(crate) fn new(def: LineDefinition) -> Result<Self, BuildError> {
+        let re = Regex::new(&def.regex)
+            .map_err(|e| BuildError::InvalidPattern(def.kind.clone(), e.to_string()))?;
+
+        for (name, field) in def.fields.iter() {
+            let known = match &field.capture {
+                Capture::Index(idx) => *idx < re.captures_len(),
+                Capture::Name(n) => re.capture_names().flatten().any(|c| c == n),
+            };
+            if !known {
+                return Err(BuildError::InvalidDefinition(
+                    def.kind.clone(),
+                    format!("unknown capture group for field {}", name),
+                ));
+            }
+        }
+
+        if let Some(spot_type) = def.spot_type {
+            for name in required_fields(spot_type) {
+                match def.fields.get(*name) {
+                    Some(field) if !field.optional => {}
+                    _ => {
+                        return Err(BuildError::InvalidDefinition(
+                            def.kind.clone(),
+                            format!("required field {} is not mapped", name),
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(DefinitionParser {
+            kind: def.kind,
+            spot_type: def.spot_type,
+            prefix: def.prefix,
+            re,
+            fields: def.fields.into_iter().collect(),
+        })
+    }
+}
+
+impl DynSpotParser for DefinitionParser {
+    fn recognize(&self, raw: &str) -> bool {
+        raw.starts_with(&self.prefix)
+    }
+
+    fn parse_spot(&self, raw: &str) -> Result<Spot, ParseError> {
+        self.parse_fields(raw).map_err(|e| match self.spot_type {
+            Some(t) => e.with_spot_type(t),
+            None => e,
+        })
+    }
+}
+
+impl DefinitionParser {
+    fn parse_fields(&self, raw: &str) -> Result<Spot, ParseError> {
+        let c = self.re.captures(raw).ok_or_else(|| {
+            ParseError::new(ParseErrorKind::InvalidContent).with_expected(&self.regex_desc())
+        })?;
+
+        let mut payload = serde_json::Map::new();
+        for (name, field) in self.fields.iter() {
+            let val = match &field.capture {
+                Capture::Index(idx) => c.get(*idx),
+                Capture::Name(n) => c.name(n),
+            };
+
+            match val {
+                Some(val) => {
+                    let converted = convert(val.as_str(), field.field_type).ok_or_else(|| {
+                        ParseError::new(ParseErrorKind::InvalidContent)
+                            .with_field(name)
+                            .with_span(val.range(), val.as_str())
+                            .with_expected(field.field_type.description())
+                    })?;
+                    payload.insert(name.clone(), converted);
+                }
+                None if field.optional => {}
+                None => return Err(ParseError::new(ParseErrorKind::MissingField).with_field(name)),
+            }
+        }
+
+        let payload = serde_json::Value::Object(payload);
+        let spot = match self.spot_type {
+            Some(SpotType::DX) => Spot::DX(from_payload(payload)?),
+            Some(SpotType::WWV) => Spot::WWV(from_payload(payload)?),
+            Some(SpotType::WCY) => Spot::WCY(from_payload(payload)?),
+            Some(SpotType::WX) => Spot::WX(from_payload(payload)?),
+            Some(SpotType::ToAll) => Spot::ToAll(from_payload(payload)?),
+            Some(SpotType::ToLocal) => Spot::ToLocal(from_payload(payload)?),
+            None => Spot::Custom(Custom {
+                kind: self.kind.clone(),
+                payload,
+            }),
+        };
+
+        Ok(spot)
+    }
+
+    fn regex_desc(&self) -> String {
+        format!("line of type {} matching {}", self.kind, self.re.as_str())
+    }
+}
+
+impl FieldType {
+    fn description(&self) -> &'static str {
+        match self {
+            FieldType::String => "text",
+            FieldType::Integer => "integer",
+            FieldType::Float => "floating point number",
+            FieldType::Khz => "frequency in kHz",
+        }
+    }
+}
+
+fn convert(val: &str, field_type: FieldType) -> Option<serde_json::Value> {
+    let val = match field_type {
+        FieldType::String => serde_json::Value::from(val),
+        FieldType::Integer => match val.parse::<u64>() {
+            Ok(v) => serde_json::Value::from(v),
+            Err(_) => serde_json::Value::from(val.parse::<i64>().ok()?),
+        },
+        FieldType::Float => serde_json::Value::from(val.parse::<f64>().ok()?),
+        FieldType::Khz => {
+            serde_json::Value::from((val.parse::<f64>().ok()? * 1000.0).round() as u64)
+        }
+    };
+
+    Some(val)
+}
+
+fn from_payload<T>(payload: serde_json::Value) -> Result<T, ParseError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    serde_json::from_value(payload)
+        .map_err(|e| ParseError::new(ParseErrorKind::InvalidContent).with_expected(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::Parser;
+
+    const DEFINITIONS_TOML: &str = r#"
+[[line]]
+kind = "dx-compact"
+spot_type = "DX"
+prefix = "DX:"
+regex = '^DX: (\S+) > (\S+) @ (\d+\.\d) (\d{4})Z(?: (.*))?$'
+
+[line.fields]
+call_de = { capture = 1 }
+call_dx = { capture = 2 }
+freq = { capture = 3, type = "khz" }
+utc = { capture = 4, type = "integer" }
+comment = { capture = 5, optional = true }
+
+[[line]]
+kind = "contest"
+prefix = "CONTEST de"
+regex = '^CONTEST de (?P<call>\S+): (?P<name>.+) (?P<hours>\d+)h$'
+
+[line.fields]
+call = { capture = "call" }
+name = { capture = "name" }
+hours = { capture = "hours", type = "integer" }
+"#;
+
+    #[test]
+    fn definition_toml_builtin() {
+        let defs = Definitions::from_toml(DEFINITIONS_TOML).unwrap();
+        let parser = Parser::builder().definitions(defs).build().unwrap();
+        let res = parser.parse("DX: DL1ABC > 3B9FR @ 14025.1 1812Z up 2");
+        let exp = Spot::DX(DX {
+            call_de: "DL1ABC".into(),
+            call_dx: "3B9FR".into(),
+            freq: 14025100,
+            utc: 1812,
+            loc: None,
+            comment: Some("up 2".into()),
+            truncated: false,
+        });
+        assert_eq!(res, Ok(exp));
+    }
+
+    #[test]
+    fn definition_toml_custom() {
+        let defs = Definitions::from_toml(DEFINITIONS_TOML).unwrap();
+        let parser = Parser::builder().definitions(defs).build().unwrap();
+        let res = parser.parse("CONTEST de DL1ABC: CQ WW DX CW 48h");
+        let exp = Spot::Custom(Custom {
+            kind: "contest".into(),
+            payload: serde_json::json!({"call": "DL1ABC", "name": "CQ WW DX CW", "hours": 48}),
+        });
+        assert_eq!(res, Ok(exp));
+    }
+
+    #[test]
+    fn definition_json() {
+        let raw = r#"{"line": [{
+            "kind": "note",
+            "prefix": "NOTE",
+            "regex": "^NOTE (\\d+) (.*)$",
+            "fields": {"id": {"capture": 1, "type": "integer"}, "text": {"capture": 2}}
+        }]}"#;
+        let defs = Definitions::from_json(raw).unwrap();
+        let parser = Parser::builder().definitions(defs).build().unwrap();
+        let res = parser.parse("NOTE 7 node restart at 2200Z");
+        let exp = Spot::Custom(Custom {
+            kind: "note".into(),
+            payload: serde_json::json!({"id": 7, "text": "node restart at 2200Z"}),
+        });
+        assert_eq!(res, Ok(exp));
+    }
+
+    #[test]
+    fn definition_missing_required_field() {
+        let mut defs = Definitions::from_toml(DEFINITIONS_TOML).unwrap();
+        defs.lines[0].fields.remove("utc");
+        let res = Parser::builder().definitions(defs).build();
+        assert!(matches!(res, Err(BuildError::InvalidDefinition(kind, _)) if kind == "dx-compact"));
+    }
+
+    #[test]
+    fn definition_unknown_capture() {
+        let mut defs = Definitions::from_toml(DEFINITIONS_TOML).unwrap();
+        defs.lines[1].fields.get_mut("hours").unwrap().capture = Capture::Name("minutes".into());
+        let res = Parser::builder().definitions(defs).build();
+        assert!(matches!(res, Err(BuildError::InvalidDefinition(kind, _)) if kind == "contest"));
+    }
+
+    #[test]
+    fn definition_invalid_number() {
+        let raw = r#"
+[[line]]
+kind = "count"
+prefix = "COUNT"
+regex = '^COUNT (\S+)$'
+fields = { n = { capture = 1, type = "integer" } }
+"#;
+        let defs = Definitions::from_toml(raw).unwrap();
+        let parser = Parser::builder().definitions(defs).build().unwrap();
+        let err = parser.parse("COUNT many").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidContent);
+        assert_eq!(err.field(), Some("n"));
+        assert_eq!(err.span(), Some(6..10));
+        assert_eq!(err.text(), Some("many"));
+        assert_eq!(err.expected(), Some("integer"));
+    }
+
+    #[test]
+    fn definition_unknown_format() {
+        let res = Definitions::load("definitions.yaml");
+        assert!(matches!(res, Err(DefinitionError::UnknownFormat)));
+    }
+}
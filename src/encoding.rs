@@ -0,0 +1,301 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::error::ParseError;
+use crate::parser::Parser;
+use crate::types::Spot;
+use std::borrow::Cow;
+
+/// Character encoding of raw bytes received from a DX Cluster
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Encoding {
+    /// UTF-8
+    Utf8,
+
+    /// ISO-8859-1
+    Latin1,
+
+    /// Windows code page 1252, a superset of the printable characters of ISO-8859-1
+    Cp1252,
+
+    /// DOS code page 437
+    Cp437,
+}
+
+/// Detection of the character encoding of raw bytes
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Detection {
+    /// Guess the encoding: UTF-8 in case the bytes are valid UTF-8, otherwise either CP1252 or CP437
+    #[default]
+    Auto,
+
+    /// UTF-8 in case the bytes are valid UTF-8, otherwise the given fallback
+    Utf8Or(Encoding),
+
+    /// Always the given encoding
+    Fixed(Encoding),
+}
+
+/// Text decoded from raw bytes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Decoded<'a> {
+    /// Decoded text, borrowed from the raw bytes in case they are valid UTF-8
+    pub text: Cow<'a, str>,
+
+    /// Encoding used to decode the text
+    pub encoding: Encoding,
+
+    /// At least one byte could not be decoded and was replaced by U+FFFD
+    pub lossy: bool,
+}
+
+/// Spot parsed from raw bytes
+#[derive(Debug, PartialEq, Eq)]
+pub struct DecodedSpot {
+    /// Parsed spot
+    pub spot: Spot,
+
+    /// Encoding used to decode the raw bytes
+    pub encoding: Encoding,
+
+    /// At least one byte could not be decoded and was replaced by U+FFFD
+    pub lossy: bool,
+}
+
+impl Parser {
+    /// Parse a spot received from a DX Cluster as raw bytes into a struct.
+    ///
+    /// The bytes are decoded according to the detection configured for the parser, see [`decode`].
+    /// Invalid bytes never cause an error but are replaced by U+FFFD, which is reported in the result.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: Raw bytes of a spot that are already cleaned from newline or bell characters etc.
+    ///
+    /// ## Result
+    ///
+    /// In case the spot was parsed successfully, the structure containing the spot together with the used encoding shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn parse_bytes(&self, raw: &[u8]) -> Result<DecodedSpot, ParseError> {
+        let decoded = decode(raw, self.detection());
+
+        Ok(DecodedSpot {
+            spot: self.parse(&decoded.text)?,
+            encoding: decoded.encoding,
+            lossy: decoded.lossy,
+        })
+    }
+}
+
+/// Decode raw bytes received from a DX Cluster.
+///
+/// ## Arguments
+///
+/// * `raw`: Raw bytes
+/// * `detection`: Detection of the encoding of the bytes
+///
+/// ## Result
+///
+/// The decoded text together with the used encoding shall be returned.
+pub fn decode(raw: &[u8], detection: Detection) -> Decoded<'_> {
+    let encoding = match detection {
+        Detection::Fixed(encoding) => encoding,
+        Detection::Utf8Or(_) | Detection::Auto if std::str::from_utf8(raw).is_ok() => {
+            Encoding::Utf8
+        }
+        Detection::Utf8Or(fallback) => fallback,
+        Detection::Auto => guess(raw),
+    };
+
+    let text = match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(raw),
+        _ if raw.is_ascii() => Cow::Borrowed(std::str::from_utf8(raw).unwrap()),
+        Encoding::Latin1 => Cow::Owned(raw.iter().map(|&b| char::from(b)).collect()),
+        Encoding::Cp1252 => Cow::Owned(raw.iter().map(|&b| decode_cp1252(b)).collect()),
+        Encoding::Cp437 => Cow::Owned(raw.iter().map(|&b| decode_cp437(b)).collect()),
+    };
+    let lossy = match (encoding, &text) {
+        (Encoding::Utf8, Cow::Owned(_)) => true,
+        (Encoding::Cp1252, _) => raw
+            .iter()
+            .any(|&b| decode_cp1252(b) == char::REPLACEMENT_CHARACTER),
+        _ => false,
+    };
+
+    Decoded {
+        text,
+        encoding,
+        lossy,
+    }
+}
+
+/// Guess whether bytes that are not valid UTF-8 are encoded in CP1252 or CP437.
+///
+/// Bytes undefined in CP1252 are a sure sign for CP437.
+/// Otherwise the encoding decoding more bytes next to a letter into latin letters wins, CP1252 in case of a tie.
+fn guess(raw: &[u8]) -> Encoding {
+    let mut score = 0i32;
+
+    for (i, &b) in raw.iter().enumerate().filter(|(_, &b)| b >= 0x80) {
+        let cp1252 = decode_cp1252(b);
+        if cp1252 == char::REPLACEMENT_CHARACTER {
+            return Encoding::Cp437;
+        }
+
+        let in_word = (i > 0 && raw[i - 1].is_ascii_alphabetic())
+            || raw.get(i + 1).is_some_and(u8::is_ascii_alphabetic);
+        if in_word {
+            score +=
+                i32::from(is_latin_letter(decode_cp437(b))) - i32::from(is_latin_letter(cp1252));
+        }
+    }
+
+    if score > 0 {
+        Encoding::Cp437
+    } else {
+        Encoding::Cp1252
+    }
+}
+
+fn is_latin_letter(c: char) -> bool {
+    ('\u{00C0}'..='\u{024F}').contains(&c) && c.is_alphabetic()
+}
+
+fn decode_cp1252(b: u8) -> char {
+    match b {
+        0x80..=0x9F => CP1252[usize::from(b - 0x80)],
+        _ => char::from(b),
+    }
+}
+
+fn decode_cp437(b: u8) -> char {
+    match b {
+        0x80..=0xFF => CP437[usize::from(b - 0x80)],
+        _ => char::from(b),
+    }
+}
+
+/// Characters of the bytes 0x80 to 0x9F in CP1252, the remaining bytes correspond to ISO-8859-1
+const CP1252: [char; 32] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+];
+
+/// Characters of the bytes 0x80 to 0xFF in CP437
+const CP437: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}',
+    '\u{00EA}', '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}',
+    '\u{00C9}', '\u{00E6}', '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}',
+    '\u{00FF}', '\u{00D6}', '\u{00DC}', '\u{00A2}', '\u{00A3}', '\u{00A5}', '\u{20A7}', '\u{0192}',
+    '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}', '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}',
+    '\u{00BF}', '\u{2310}', '\u{00AC}', '\u{00BD}', '\u{00BC}', '\u{00A1}', '\u{00AB}', '\u{00BB}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{255C}', '\u{255B}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{255E}', '\u{255F}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256B}',
+    '\u{256A}', '\u{2518}', '\u{250C}', '\u{2588}', '\u{2584}', '\u{258C}', '\u{2590}', '\u{2580}',
+    '\u{03B1}', '\u{00DF}', '\u{0393}', '\u{03C0}', '\u{03A3}', '\u{03C3}', '\u{00B5}', '\u{03C4}',
+    '\u{03A6}', '\u{0398}', '\u{03A9}', '\u{03B4}', '\u{221E}', '\u{03C6}', '\u{03B5}', '\u{2229}',
+    '\u{2261}', '\u{00B1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00F7}', '\u{2248}',
+    '\u{00B0}', '\u{2219}', '\u{00B7}', '\u{221A}', '\u{207F}', '\u{00B2}', '\u{25A0}', '\u{00A0}',
+];
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::types::DX;
+
+    #[test]
+    fn decode_utf8_borrowed() {
+        let res = decode("tnx für QSO".as_bytes(), Detection::Auto);
+        assert_eq!(res.text, Cow::Borrowed("tnx für QSO"));
+        assert_eq!(res.encoding, Encoding::Utf8);
+        assert!(!res.lossy);
+    }
+
+    #[test]
+    fn decode_auto_cp1252() {
+        let res = decode(b"tnx f\xfcr QSO \x80", Detection::Auto);
+        assert_eq!(res.text, "tnx für QSO €");
+        assert_eq!(res.encoding, Encoding::Cp1252);
+        assert!(!res.lossy);
+    }
+
+    #[test]
+    fn decode_auto_cp437() {
+        let res = decode(b"Gr\x81\xe1e aus M\x81nchen", Detection::Auto);
+        assert_eq!(res.text, "Grüße aus München");
+        assert_eq!(res.encoding, Encoding::Cp437);
+        assert!(!res.lossy);
+
+        let res = decode(b"73 aus K\x94ln", Detection::Auto);
+        assert_eq!(res.text, "73 aus Köln");
+        assert_eq!(res.encoding, Encoding::Cp437);
+    }
+
+    #[test]
+    fn decode_fallback() {
+        let res = decode(b"73 de J\xfcrgen", Detection::Utf8Or(Encoding::Latin1));
+        assert_eq!(res.text, "73 de Jürgen");
+        assert_eq!(res.encoding, Encoding::Latin1);
+    }
+
+    #[test]
+    fn decode_lossy() {
+        let res = decode(b"73 de J\xfcrgen", Detection::Fixed(Encoding::Utf8));
+        assert_eq!(res.text, "73 de J\u{FFFD}rgen");
+        assert!(res.lossy);
+
+        let res = decode(b"\x81", Detection::Fixed(Encoding::Cp1252));
+        assert!(res.lossy);
+    }
+
+    #[test]
+    fn decode_never_panics() {
+        let raw: Vec<u8> = (0..=255).collect();
+        for detection in [
+            Detection::Auto,
+            Detection::Fixed(Encoding::Utf8),
+            Detection::Fixed(Encoding::Latin1),
+            Detection::Fixed(Encoding::Cp1252),
+            Detection::Fixed(Encoding::Cp437),
+        ] {
+            assert!(decode(&raw, detection).text.chars().count() > 0);
+        }
+    }
+
+    #[test]
+    fn parse_bytes_cp437() {
+        let raw = b"DX de DJ1TO:      3780.0  OH5Z         tnx f\x81r QSO                    2200Z";
+        let res = Parser::new().parse_bytes(raw).unwrap();
+        let exp = Spot::DX(DX {
+            call_de: "DJ1TO".into(),
+            call_dx: "OH5Z".into(),
+            freq: 3780000,
+            utc: 2200,
+            loc: None,
+            comment: Some("tnx für QSO".into()),
+            truncated: false,
+        });
+        assert_eq!(res.spot, exp);
+        assert_eq!(res.encoding, Encoding::Cp437);
+        assert!(!res.lossy);
+    }
+
+    #[test]
+    fn parse_bytes_fixed() {
+        let raw = b"To ALL de CT2IDL <1044Z> : obrigado \xe0 todos";
+        let parser = Parser::builder()
+            .detection(Detection::Fixed(Encoding::Latin1))
+            .build()
+            .unwrap();
+        let res = parser.parse_bytes(raw).unwrap();
+        assert_eq!(res.encoding, Encoding::Latin1);
+        assert!(res.spot.to_json().contains("obrigado à todos"));
+    }
+}
@@ -0,0 +1,329 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::types::SpotType;
+use std::fmt;
+use std::ops::Range;
+use thiserror::Error;
+
+/// Kind of error while parsing spot
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ParseErrorKind {
+    #[error("Unknown type of spot")]
+    UnknownType,
+
+    #[error("The content of the spot does not match the detected type")]
+    InvalidContent,
+
+    #[error("Required field of the spot is missing")]
+    MissingField,
+
+    #[error("Internal error occurred while parsing")]
+    InternalError,
+}
+
+/// Possible errors while parsing spot
+///
+/// Besides its kind, the error carries the details about the failure as far as they are known:
+/// The type of the spot, the failing field, its position within the line and the offending text together with the expected shape of the field.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    spot_type: Option<SpotType>,
+    field: Option<String>,
+    span: Option<Range<usize>>,
+    text: Option<String>,
+    expected: Option<String>,
+}
+
+impl ParseError {
+    /// Create an error of the given kind without any details.
+    pub fn new(kind: ParseErrorKind) -> Self {
+        ParseError {
+            kind,
+            spot_type: None,
+            field: None,
+            span: None,
+            text: None,
+            expected: None,
+        }
+    }
+
+    /// Kind of the error
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Type of the spot the error occurred in
+    pub fn spot_type(&self) -> Option<SpotType> {
+        self.spot_type
+    }
+
+    /// Name of the failing field
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    /// Position of the offending text within the line (in bytes)
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    /// Offending text
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    /// Description of the expected shape of the failing field
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    /// Set the type of the spot the error occurred in.
+    pub fn with_spot_type(mut self, spot_type: SpotType) -> Self {
+        self.spot_type = Some(spot_type);
+        self
+    }
+
+    /// Set the name of the failing field.
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(String::from(field));
+        self
+    }
+
+    /// Set the position of the offending text within the line together with the text itself.
+    pub fn with_span(mut self, span: Range<usize>, text: &str) -> Self {
+        self.span = Some(span);
+        self.text = Some(String::from(text));
+        self
+    }
+
+    /// Set the offending text in case its position within the line is not known.
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(String::from(text));
+        self
+    }
+
+    /// Set the description of the expected shape of the failing field.
+    pub fn with_expected(mut self, expected: &str) -> Self {
+        self.expected = Some(String::from(expected));
+        self
+    }
+
+    /// Shift the position of the offending text, e.g. from a position within a field to a position within the line.
+    pub(crate) fn with_offset(mut self, offset: usize) -> Self {
+        self.span = self.span.map(|s| s.start + offset..s.end + offset);
+        self
+    }
+}
+
+impl From<ParseErrorKind> for ParseError {
+    fn from(kind: ParseErrorKind) -> Self {
+        ParseError::new(kind)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(spot_type) = self.spot_type {
+            write!(f, " (type {:?})", spot_type)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, ", field {}", field)?;
+        }
+        match (&self.span, &self.text) {
+            (Some(span), Some(text)) => write!(f, " at {}..{}: '{}'", span.start, span.end, text)?,
+            (None, Some(text)) => write!(f, ": '{}'", text)?,
+            _ => {}
+        }
+        if let Some(expected) = &self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Possible errors while building a parser
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    #[error("Invalid pattern for custom type {0}: {1}")]
+    InvalidPattern(String, String),
+
+    #[error("Invalid definition for type {0}: {1}")]
+    InvalidDefinition(String, String),
+}
+
+/// Possible errors while loading line definitions
+#[derive(Error, Debug)]
+pub enum DefinitionError {
+    #[error("Failed to read definitions: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid definitions in toml format: {0}")]
+    Toml(String),
+
+    #[error("Invalid definitions in json format: {0}")]
+    Json(String),
+
+    #[error("Unknown format of definitions file")]
+    UnknownFormat,
+}
+
+/// Possible errors while creating a command
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    #[error("Invalid frequency: {0}")]
+    InvalidFrequency(String),
+
+    #[error("Invalid callsign: {0}")]
+    InvalidCallsign(String),
+
+    #[error("Invalid locator: {0}")]
+    InvalidLocator(String),
+
+    #[error("Invalid text: {0:?}")]
+    InvalidText(String),
+
+    #[error("Text of {len} characters exceeds the limit of {max}")]
+    TextTooLong { len: usize, max: usize },
+
+    #[error("Invalid band: {0}")]
+    InvalidBand(String),
+
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+}
+
+/// Possible errors while compiling a filter expression, positions are byte offsets within the expression
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ExprError {
+    #[error("Empty filter expression")]
+    Empty,
+
+    #[error("Unexpected end of the expression, expected {0}")]
+    UnexpectedEnd(&'static str),
+
+    #[error("Unexpected {found:?} at position {pos}, expected {expected}")]
+    Unexpected {
+        pos: usize,
+        found: String,
+        expected: &'static str,
+    },
+
+    #[error("Unterminated string starting at position {0}")]
+    UnterminatedString(usize),
+
+    #[error("Parentheses nested too deeply at position {pos}, at most {max} levels are allowed")]
+    TooDeep { pos: usize, max: usize },
+
+    #[error("Unknown field {field:?} at position {pos}, expected one of {expected}")]
+    UnknownField {
+        pos: usize,
+        field: String,
+        expected: &'static str,
+    },
+
+    #[error("Operator {op:?} at position {pos} can not be applied to the field {field}")]
+    InvalidOperator {
+        pos: usize,
+        op: String,
+        field: &'static str,
+    },
+
+    #[error(
+        "Invalid value {value:?} at position {pos} for the field {field}, expected {expected}"
+    )]
+    InvalidValue {
+        pos: usize,
+        value: String,
+        field: &'static str,
+        expected: &'static str,
+    },
+}
+
+/// Possible errors of a cluster client
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Connection closed by the cluster")]
+    Closed,
+
+    #[error("Timeout while logging in")]
+    LoginTimeout,
+
+    #[error("The cluster requested a password but none is configured")]
+    PasswordRequired,
+
+    #[error("No data received from the cluster within the idle timeout")]
+    Idle,
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[cfg(feature = "tls")]
+    #[error("TLS error: {0}")]
+    Tls(#[from] rustls::Error),
+
+    #[cfg(feature = "tls")]
+    #[error("Invalid certificate: {0}")]
+    Certificate(#[from] rustls::pki_types::pem::Error),
+
+    #[cfg(feature = "tls")]
+    #[error("Invalid name of the server: {0}")]
+    ServerName(String),
+}
+
+/// Possible failures of a spot submitted to a cluster
+#[derive(Error, Debug)]
+pub enum SubmitError {
+    #[error("The command is no DX spot")]
+    NotSpot,
+
+    #[error("Spot rejected as duplicate: {0}")]
+    Duplicate(String),
+
+    #[error("Spot rejected since too many spots were sent: {0}")]
+    RateLimited(String),
+
+    #[error("Spot rejected due to an invalid callsign: {0}")]
+    InvalidCall(String),
+
+    #[error("No confirmation of the spot received in time")]
+    Timeout,
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn display_kind_only() {
+        let err = ParseError::new(ParseErrorKind::UnknownType);
+        assert_eq!(err.to_string(), "Unknown type of spot");
+    }
+
+    #[test]
+    fn display_details() {
+        let err = ParseError::new(ParseErrorKind::InvalidContent)
+            .with_spot_type(SpotType::DX)
+            .with_field("freq")
+            .with_span(12..19, "14O25.0")
+            .with_expected("frequency in kHz");
+        assert_eq!(
+            err.to_string(),
+            "The content of the spot does not match the detected type (type DX), field freq at 12..19: '14O25.0', expected frequency in kHz"
+        );
+    }
+}
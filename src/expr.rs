@@ -0,0 +1,861 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::band::Band;
+use crate::callinfo::{CallInfo, Continent};
+use crate::dedup::matches_wildcard;
+use crate::error::ExprError;
+use crate::mode::Mode;
+use crate::types::{Spot, SpotType};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// Maximum nesting of parentheses within a filter expression or a filter of DXSpider
+pub const MAX_NESTING: usize = 32;
+
+/// Names of the fields of an expression
+const FIELDS: &str = "type, band, mode, freq, dx, de, comment, dx_cont, de_cont, dx_zone, de_zone, dx_itu, de_itu, dx_dxcc, de_dxcc";
+
+/// Symbols of an expression, longer ones first
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "!~", "&&", "||", "=", "<", ">", "~", "!", "(", ")", "[", "]", ",",
+];
+
+/// Filter expression compiled into a predicate over spots
+///
+/// An expression consists of comparisons of fields of a spot, combined with `and`, `or`, `not` and parentheses.
+/// `and` binds stronger than `or`, the keywords are case-insensitive and may also be written as `&&`, `||` and `!`.
+///
+/// | Field | Value | Operators |
+/// |-------|-------|-----------|
+/// | `type` | Type of the spot: `dx`, `wwv`, `wcy`, `wx`, `toall`, `tolocal` | `=`, `!=`, `in`, `not in` |
+/// | `band` | Band, e.g. `20m` | `=`, `!=`, `in`, `not in` |
+/// | `mode` | Mode, e.g. `cw`, see [`Mode::of`] | `=`, `!=`, `in`, `not in` |
+/// | `freq` | Frequency in kHz, e.g. `14025` or `14000..14070` | `=`, `!=`, `in`, `not in`, `<`, `<=`, `>`, `>=` |
+/// | `dx`, `de` | Call of the DX station or the spotter, `*` matches any characters, e.g. `DL*` | `=`, `!=`, `in`, `not in` |
+/// | `comment` | Comment of a DX spot or text of an announcement | `=`, `!=` (wildcards), `~`, `!~` (contains) |
+/// | `dx_cont`, `de_cont` | Continent, e.g. `EU` | `=`, `!=`, `in`, `not in` |
+/// | `dx_zone`, `de_zone` | CQ zone, e.g. `14` or `14..16` | like `freq` |
+/// | `dx_itu`, `de_itu` | ITU zone | like `freq` |
+/// | `dx_dxcc`, `de_dxcc` | DXCC entity number | like `freq` |
+///
+/// `in` takes a list of values in brackets, e.g. `band in [20m, 40m]`, `=` and `!=` take a list as well.
+/// Values containing spaces are written in double quotes.
+/// Parentheses may be nested up to [`MAX_NESTING`] levels.
+/// All fields but `type`, `de` and `comment` only apply to DX spots, a comparison with any other type of spot is false.
+/// Continents, zones and DXCC entities are looked up by a [`CallInfo`], without it or for an unknown callsign the comparison is false.
+/// Negated comparisons like `!=` are the negation of the positive comparison, i.e. true in these cases.
+///
+/// ```
+/// use dxclparser::FilterExpr;
+///
+/// let filter: FilterExpr = "band in [20m, 40m] and mode = cw and de = DL*".parse().unwrap();
+/// let spot = dxclparser::parse("DX de DL1ABC:     14025.0  3B9FR        599                            1812Z").unwrap();
+/// assert!(filter.matches(&spot));
+///
+/// let err = "band in [20m, 11m]".parse::<FilterExpr>().unwrap_err();
+/// assert_eq!(err.to_string(), "Invalid value \"11m\" at position 14 for the field band, expected a band like 20m");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FilterExpr {
+    text: String,
+    expr: Expr,
+}
+
+impl FilterExpr {
+    /// Check whether a spot matches the expression, fields that need a lookup of the callsign never match.
+    pub fn matches(&self, spot: &Spot) -> bool {
+        self.expr.eval(spot, None)
+    }
+
+    /// Check whether a spot matches the expression, looking up callsigns by the given information.
+    pub fn matches_with(&self, spot: &Spot, info: &dyn CallInfo) -> bool {
+        self.expr.eval(spot, Some(info))
+    }
+}
+
+impl FromStr for FilterExpr {
+    type Err = ExprError;
+
+    /// Compile an expression, e.g. `band in [20m, 40m] and mode = cw`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = lex(s)?;
+        if tokens.is_empty() {
+            return Err(ExprError::Empty);
+        }
+
+        let mut compiler = Compiler { tokens, next: 0 };
+        let expr = compiler.or(0)?;
+        if compiler.peek().is_some() {
+            return Err(compiler.unexpected("and, or or the end of the expression"));
+        }
+        Ok(FilterExpr {
+            text: String::from(s),
+            expr,
+        })
+    }
+}
+
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Node of a compiled expression
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Test(Test),
+}
+
+/// Station of a spot a field refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+    Dx,
+    De,
+}
+
+/// Numeric field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Number {
+    Freq,
+    Zone(Side),
+    Itu(Side),
+    Dxcc(Side),
+}
+
+/// Comparison of a field with one or more values
+#[derive(Debug, Clone)]
+pub(crate) enum Test {
+    Type(Vec<SpotType>),
+    Band(Vec<Band>),
+    Mode(Vec<Mode>),
+    Number(Number, Vec<RangeInclusive<u64>>),
+    Call(Side, Vec<String>),
+    Comment(Vec<String>),
+    Contains(String),
+    Continent(Side, Vec<Continent>),
+}
+
+impl Expr {
+    pub(crate) fn eval(&self, spot: &Spot, info: Option<&dyn CallInfo>) -> bool {
+        match self {
+            Expr::Or(exprs) => exprs.iter().any(|e| e.eval(spot, info)),
+            Expr::And(exprs) => exprs.iter().all(|e| e.eval(spot, info)),
+            Expr::Not(expr) => !expr.eval(spot, info),
+            Expr::Test(test) => test.eval(spot, info),
+        }
+    }
+}
+
+impl Test {
+    fn eval(&self, spot: &Spot, info: Option<&dyn CallInfo>) -> bool {
+        let dx = match spot {
+            Spot::DX(dx) => Some(dx),
+            _ => None,
+        };
+        let lookup = |side| info?.lookup(call(spot, side)?);
+
+        match self {
+            Test::Type(types) => spot.spot_type().is_some_and(|t| types.contains(&t)),
+            Test::Band(bands) => dx
+                .and_then(|dx| Band::of(dx.freq))
+                .is_some_and(|b| bands.contains(&b)),
+            Test::Mode(modes) => dx.and_then(Mode::of).is_some_and(|m| modes.contains(&m)),
+            Test::Number(number, ranges) => {
+                let value = match number {
+                    Number::Freq => dx.map(|dx| dx.freq),
+                    Number::Zone(side) => lookup(*side).and_then(|d| d.cq_zone).map(u64::from),
+                    Number::Itu(side) => lookup(*side).and_then(|d| d.itu_zone).map(u64::from),
+                    Number::Dxcc(side) => lookup(*side).and_then(|d| d.dxcc).map(u64::from),
+                };
+                value.is_some_and(|v| ranges.iter().any(|r| r.contains(&v)))
+            }
+            Test::Call(side, patterns) => call(spot, *side).is_some_and(|c| {
+                let c = c.to_ascii_uppercase();
+                patterns.iter().any(|p| matches_wildcard(p, &c))
+            }),
+            Test::Comment(patterns) => comment(spot).is_some_and(|c| {
+                let c = c.to_uppercase();
+                patterns.iter().any(|p| matches_wildcard(p, &c))
+            }),
+            Test::Contains(text) => comment(spot).is_some_and(|c| c.to_uppercase().contains(text)),
+            Test::Continent(side, continents) => lookup(*side)
+                .and_then(|d| d.continent)
+                .is_some_and(|c| continents.contains(&c)),
+        }
+    }
+}
+
+/// Get the call of the DX station or the spotter of a spot.
+fn call(spot: &Spot, side: Side) -> Option<&str> {
+    let call = match (spot, side) {
+        (Spot::DX(dx), Side::Dx) => &dx.call_dx,
+        (Spot::DX(dx), Side::De) => &dx.call_de,
+        (Spot::WWV(wwv), Side::De) => &wwv.call_de,
+        (Spot::WCY(wcy), Side::De) => &wcy.call_de,
+        (Spot::WX(wx), Side::De) => &wx.call_de,
+        (Spot::ToAll(all), Side::De) => &all.call_de,
+        (Spot::ToLocal(local), Side::De) => &local.call_de,
+        _ => return None,
+    };
+    Some(call.as_str())
+}
+
+/// Get the comment of a DX spot or the text of an announcement.
+fn comment(spot: &Spot) -> Option<&str> {
+    match spot {
+        Spot::DX(dx) => dx.comment.as_deref(),
+        Spot::WX(wx) => wx.msg.as_deref(),
+        Spot::ToAll(all) => all.msg.as_deref(),
+        Spot::ToLocal(local) => local.msg.as_deref(),
+        _ => None,
+    }
+}
+
+/// Token of an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    /// Position of the token within the expression
+    pos: usize,
+
+    /// Text of the token, for a quoted string without the quotes
+    text: String,
+
+    /// The token is a symbol like `=` or `(`
+    symbol: bool,
+
+    /// The token is a quoted string
+    quoted: bool,
+}
+
+impl Token {
+    /// Check whether the token is the given symbol or keyword.
+    fn is(&self, word: &str) -> bool {
+        !self.quoted && self.text.eq_ignore_ascii_case(word)
+    }
+}
+
+/// Split an expression into tokens.
+fn lex(text: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.char_indices().peekable();
+
+    while let Some(&(pos, c)) = rest.peek() {
+        if c.is_whitespace() {
+            rest.next();
+        } else if c == '"' {
+            rest.next();
+            let mut value = String::new();
+            loop {
+                match rest.next() {
+                    Some((_, '"')) => break,
+                    Some((_, c)) => value.push(c),
+                    None => return Err(ExprError::UnterminatedString(pos)),
+                }
+            }
+            tokens.push(Token {
+                pos,
+                text: value,
+                symbol: false,
+                quoted: true,
+            });
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| text[pos..].starts_with(*s)) {
+            for _ in 0..symbol.len() {
+                rest.next();
+            }
+            tokens.push(Token {
+                pos,
+                text: String::from(*symbol),
+                symbol: true,
+                quoted: false,
+            });
+        } else {
+            // The first character is part of the word, even if it starts a symbol like a single `&`
+            let mut word = String::from(c);
+            rest.next();
+            while let Some(&(_, c)) = rest.peek() {
+                if c.is_whitespace() || c == '"' || SYMBOLS.iter().any(|s| s.starts_with(c)) {
+                    break;
+                }
+                word.push(c);
+                rest.next();
+            }
+            tokens.push(Token {
+                pos,
+                text: word,
+                symbol: false,
+                quoted: false,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Operator of a comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    NotContains,
+}
+
+/// Recursive descent over terms combined by `and`, `or`, `not` and parentheses, shared by [`FilterExpr`] and [`crate::SpiderRule`]
+pub(crate) trait Descent {
+    /// Keywords or symbols of `or`
+    const OR: &'static [&'static str];
+
+    /// Keywords or symbols of `and`
+    const AND: &'static [&'static str];
+
+    /// Keywords or symbols of `not`
+    const NOT: &'static [&'static str];
+
+    /// Consume the next token in case it is one of the given symbols or keywords.
+    fn accept(&mut self, words: &[&str]) -> bool;
+
+    /// Get the position of the next token, `None` at the end.
+    fn position(&self) -> Option<usize>;
+
+    /// Create the error for the next token not being the expected one.
+    fn unexpected(&self, expected: &'static str) -> ExprError;
+
+    /// Compile a single term, e.g. a comparison like `band = 20m`.
+    fn term(&mut self) -> Result<Expr, ExprError>;
+
+    fn or(&mut self, depth: usize) -> Result<Expr, ExprError> {
+        let mut exprs = vec![self.and(depth)?];
+        while self.accept(Self::OR) {
+            exprs.push(self.and(depth)?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::Or(exprs),
+        })
+    }
+
+    fn and(&mut self, depth: usize) -> Result<Expr, ExprError> {
+        let mut exprs = vec![self.unary(depth)?];
+        while self.accept(Self::AND) {
+            exprs.push(self.unary(depth)?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::And(exprs),
+        })
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<Expr, ExprError> {
+        // Negations are counted instead of nested, so any number of them neither deepens the descent nor the expression
+        let mut negated = false;
+        while self.accept(Self::NOT) {
+            negated = !negated;
+        }
+
+        let pos = self.position();
+        let expr = if self.accept(&["("]) {
+            if depth >= MAX_NESTING {
+                return Err(ExprError::TooDeep {
+                    pos: pos.unwrap_or_default(),
+                    max: MAX_NESTING,
+                });
+            }
+            let expr = self.or(depth + 1)?;
+            if !self.accept(&[")"]) {
+                return Err(self.unexpected(")"));
+            }
+            expr
+        } else {
+            self.term()?
+        };
+
+        Ok(match negated {
+            true => Expr::Not(Box::new(expr)),
+            false => expr,
+        })
+    }
+}
+
+/// Recursive descent parser of the tokens of an expression
+struct Compiler {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Descent for Compiler {
+    const OR: &'static [&'static str] = &["or", "||"];
+    const AND: &'static [&'static str] = &["and", "&&"];
+    const NOT: &'static [&'static str] = &["not", "!"];
+
+    fn accept(&mut self, words: &[&str]) -> bool {
+        let found = self.peek().is_some_and(|t| words.iter().any(|w| t.is(w)));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.peek().map(|t| t.pos)
+    }
+
+    fn unexpected(&self, expected: &'static str) -> ExprError {
+        match self.peek() {
+            Some(token) => ExprError::Unexpected {
+                pos: token.pos,
+                found: token.text.clone(),
+                expected,
+            },
+            None => ExprError::UnexpectedEnd(expected),
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        self.comparison()
+    }
+}
+
+impl Compiler {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn take(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let field = match self.peek() {
+            Some(t) if !t.symbol && !t.quoted => self.take().unwrap(),
+            _ => return Err(self.unexpected("a field")),
+        };
+        let name = match field.text.to_ascii_lowercase().as_str() {
+            "type" => "type",
+            "band" => "band",
+            "mode" => "mode",
+            "freq" => "freq",
+            "dx" => "dx",
+            "de" | "spotter" => "de",
+            "comment" => "comment",
+            "dx_cont" => "dx_cont",
+            "de_cont" => "de_cont",
+            "dx_zone" => "dx_zone",
+            "de_zone" => "de_zone",
+            "dx_itu" => "dx_itu",
+            "de_itu" => "de_itu",
+            "dx_dxcc" => "dx_dxcc",
+            "de_dxcc" => "de_dxcc",
+            _ => {
+                return Err(ExprError::UnknownField {
+                    pos: field.pos,
+                    field: field.text,
+                    expected: FIELDS,
+                })
+            }
+        };
+
+        let op_token = self.peek().cloned();
+        let op = if self.accept(&["=", "==", "in"]) {
+            Op::Eq
+        } else if self.accept(&["!="]) {
+            Op::Ne
+        } else if self.peek().is_some_and(|t| t.is("not"))
+            && self.tokens.get(self.next + 1).is_some_and(|t| t.is("in"))
+        {
+            self.next += 2;
+            Op::Ne
+        } else if self.accept(&["<"]) {
+            Op::Lt
+        } else if self.accept(&["<="]) {
+            Op::Le
+        } else if self.accept(&[">"]) {
+            Op::Gt
+        } else if self.accept(&[">="]) {
+            Op::Ge
+        } else if self.accept(&["~"]) {
+            Op::Contains
+        } else if self.accept(&["!~"]) {
+            Op::NotContains
+        } else {
+            return Err(self.unexpected("an operator like =, != or in"));
+        };
+        let op_token = op_token.unwrap();
+        let invalid_op = || ExprError::InvalidOperator {
+            pos: op_token.pos,
+            op: op_token.text.clone(),
+            field: name,
+        };
+
+        let values = self.values()?;
+        let test = match name {
+            "freq" | "dx_zone" | "de_zone" | "dx_itu" | "de_itu" | "dx_dxcc" | "de_dxcc" => {
+                let number = match name {
+                    "freq" => Number::Freq,
+                    "dx_zone" => Number::Zone(Side::Dx),
+                    "de_zone" => Number::Zone(Side::De),
+                    "dx_itu" => Number::Itu(Side::Dx),
+                    "de_itu" => Number::Itu(Side::De),
+                    "dx_dxcc" => Number::Dxcc(Side::Dx),
+                    _ => Number::Dxcc(Side::De),
+                };
+                return numbers(name, number, op, &values, invalid_op);
+            }
+            "comment" => match op {
+                Op::Eq | Op::Ne => {
+                    Test::Comment(values.iter().map(|v| v.text.to_uppercase()).collect())
+                }
+                Op::Contains | Op::NotContains => match values.as_slice() {
+                    [value] => Test::Contains(value.text.to_uppercase()),
+                    _ => return Err(invalid_op()),
+                },
+                _ => return Err(invalid_op()),
+            },
+            _ if !matches!(op, Op::Eq | Op::Ne) => return Err(invalid_op()),
+            "type" => Test::Type(convert(name, &values, "a type like dx or wwv", spot_type)?),
+            "band" => Test::Band(convert(name, &values, "a band like 20m", |v| {
+                v.parse().ok()
+            })?),
+            "mode" => Test::Mode(convert(name, &values, "a mode like cw or ft8", |v| {
+                v.parse().ok()
+            })?),
+            "dx" | "de" => {
+                let side = if name == "dx" { Side::Dx } else { Side::De };
+                Test::Call(
+                    side,
+                    values.iter().map(|v| v.text.to_ascii_uppercase()).collect(),
+                )
+            }
+            _ => {
+                let side = if name == "dx_cont" {
+                    Side::Dx
+                } else {
+                    Side::De
+                };
+                let continents = convert(name, &values, "a continent like EU", |v| v.parse().ok())?;
+                Test::Continent(side, continents)
+            }
+        };
+
+        Ok(negate(
+            Expr::Test(test),
+            matches!(op, Op::Ne | Op::NotContains),
+        ))
+    }
+
+    /// Parse a single value or a list of values in brackets.
+    fn values(&mut self) -> Result<Vec<Token>, ExprError> {
+        if !self.accept(&["["]) {
+            return Ok(vec![self.value()?]);
+        }
+        let mut values = vec![self.value()?];
+        loop {
+            if self.accept(&["]"]) {
+                return Ok(values);
+            }
+            if !self.accept(&[","]) {
+                return Err(self.unexpected(", or ]"));
+            }
+            values.push(self.value()?);
+        }
+    }
+
+    fn value(&mut self) -> Result<Token, ExprError> {
+        match self.peek() {
+            Some(t) if !t.symbol => Ok(self.take().unwrap()),
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+}
+
+/// Negate an expression in case `negate` is set.
+pub(crate) fn negate(expr: Expr, negate: bool) -> Expr {
+    match negate {
+        true => Expr::Not(Box::new(expr)),
+        false => expr,
+    }
+}
+
+/// Convert the values of a comparison, the first invalid value is reported.
+fn convert<T>(
+    field: &'static str,
+    values: &[Token],
+    expected: &'static str,
+    f: impl Fn(&str) -> Option<T>,
+) -> Result<Vec<T>, ExprError> {
+    values
+        .iter()
+        .map(|v| {
+            f(&v.text).ok_or_else(|| ExprError::InvalidValue {
+                pos: v.pos,
+                value: v.text.clone(),
+                field,
+                expected,
+            })
+        })
+        .collect()
+}
+
+/// Compile the comparison of a numeric field, a frequency is given in kHz.
+fn numbers(
+    field: &'static str,
+    number: Number,
+    op: Op,
+    values: &[Token],
+    invalid_op: impl Fn() -> ExprError,
+) -> Result<Expr, ExprError> {
+    let (expected, parse): (_, fn(&str) -> Option<u64>) = match number {
+        Number::Freq => (
+            "a frequency in kHz like 14025 or a range like 14000..14070",
+            |v| {
+                v.parse::<f64>()
+                    .ok()
+                    .filter(|khz| khz.is_finite() && *khz >= 0.0)
+                    .map(|khz| (khz * 1000.0).round() as u64)
+            },
+        ),
+        _ => ("a number like 14 or a range like 14..16", |v| {
+            v.parse().ok()
+        }),
+    };
+    let range = |v: &str| match v.split_once("..") {
+        Some((low, high)) => Some(parse(low)?..=parse(high)?),
+        None => parse(v).map(|v| v..=v),
+    };
+
+    let ranges = match op {
+        Op::Eq | Op::Ne => convert(field, values, expected, range)?,
+        Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+            if values.len() != 1 {
+                return Err(invalid_op());
+            }
+            let bound = convert(field, values, expected, parse)?[0];
+            vec![match op {
+                Op::Lt if bound == 0 => RangeInclusive::new(1, 0),
+                Op::Lt => 0..=bound - 1,
+                Op::Le => 0..=bound,
+                Op::Gt => bound.saturating_add(1)..=u64::MAX,
+                _ => bound..=u64::MAX,
+            }]
+        }
+        Op::Contains | Op::NotContains => return Err(invalid_op()),
+    };
+    Ok(negate(
+        Expr::Test(Test::Number(number, ranges)),
+        op == Op::Ne,
+    ))
+}
+
+/// Parse the name of a type of spot.
+fn spot_type(name: &str) -> Option<SpotType> {
+    SpotType::ALL
+        .into_iter()
+        .find(|t| format!("{:?}", t).eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::callinfo::{CallData, PrefixTable};
+    use crate::fixtures::{SPOT, SPOT2, SPOT3};
+
+    const WWV: &str = "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms";
+
+    fn matching(expr: &str) -> Vec<&'static str> {
+        let filter: FilterExpr = expr.parse().unwrap();
+        [SPOT, SPOT2, SPOT3, WWV]
+            .into_iter()
+            .filter(|line| filter.matches(&crate::parse(line).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn expr_fields() {
+        assert_eq!(matching("type = dx"), vec![SPOT, SPOT2, SPOT3]);
+        assert_eq!(matching("type in [wwv, wcy]"), vec![WWV]);
+        assert_eq!(matching("band = 20m"), vec![SPOT2, SPOT3]);
+        assert_eq!(matching("band in [80m, 40m]"), vec![SPOT]);
+        assert_eq!(matching("mode = ssb"), vec![SPOT, SPOT3]);
+        assert_eq!(matching("mode in [cw, ft8]"), vec![SPOT2]);
+        assert_eq!(matching("freq in 14000..14070"), vec![SPOT2]);
+        assert_eq!(matching("freq = [3780, 14205]"), vec![SPOT, SPOT3]);
+        assert_eq!(matching("freq < 14036.1"), vec![SPOT]);
+        assert_eq!(matching("freq >= 14036.1"), vec![SPOT2, SPOT3]);
+        assert_eq!(matching("dx = 3b9*"), vec![SPOT3]);
+        assert_eq!(matching("de in [DL*, VE7CC]"), vec![SPOT3, WWV]);
+        assert_eq!(matching("spotter = *1*"), vec![SPOT, SPOT3]);
+        assert_eq!(matching("comment ~ party"), vec![SPOT2]);
+        assert_eq!(matching("comment = \"up *\""), vec![SPOT3]);
+        assert_eq!(matching("comment !~ party"), vec![SPOT, SPOT3, WWV]);
+    }
+
+    #[test]
+    fn expr_logic() {
+        assert_eq!(
+            matching("band = 20m or band = 80m and mode = cw"),
+            vec![SPOT2, SPOT3]
+        );
+        assert_eq!(
+            matching("(band = 20m or band = 80m) and mode = ssb"),
+            vec![SPOT, SPOT3]
+        );
+        assert_eq!(matching("not band = 20m"), vec![SPOT, WWV]);
+        assert_eq!(matching("band != 20m"), vec![SPOT, WWV]);
+        assert_eq!(matching("band not in [20m, 40m]"), vec![SPOT, WWV]);
+        assert_eq!(
+            matching("! (type = dx && mode = ssb) || de = N2CQ"),
+            vec![SPOT2, WWV]
+        );
+        assert_eq!(
+            matching("type = dx AND NOT mode = cw AND NOT dx = OH*"),
+            vec![SPOT3]
+        );
+    }
+
+    #[test]
+    fn expr_call_info() {
+        let mut table = PrefixTable::new();
+        let data = |cont, zone| CallData {
+            continent: Some(cont),
+            cq_zone: Some(zone),
+            ..Default::default()
+        };
+        table.insert("DJ", data(Continent::EU, 14));
+        table.insert("DL", data(Continent::EU, 14));
+        table.insert("OH", data(Continent::EU, 15));
+        table.insert("N", data(Continent::NA, 5));
+        table.insert("W", data(Continent::NA, 4));
+        table.insert("3B9", data(Continent::AF, 39));
+
+        let matching = |expr: &str| {
+            let filter: FilterExpr = expr.parse().unwrap();
+            [SPOT, SPOT2, SPOT3, WWV]
+                .into_iter()
+                .filter(|line| filter.matches_with(&crate::parse(line).unwrap(), &table))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching("dx_cont != EU and type = dx"), vec![SPOT2, SPOT3]);
+        assert_eq!(matching("de_cont = EU"), vec![SPOT, SPOT3]);
+        assert_eq!(matching("dx_zone in [14..16, 39]"), vec![SPOT, SPOT3]);
+        assert_eq!(matching("de_zone < 10"), vec![SPOT2]);
+        assert_eq!(
+            matching("band in [20m, 40m] and dx_cont not in EU and de_cont = EU"),
+            vec![SPOT3]
+        );
+
+        // Without information about the calls
+        let filter: FilterExpr = "dx_cont = AF".parse().unwrap();
+        assert!(!filter.matches(&crate::parse(SPOT3).unwrap()));
+    }
+
+    #[test]
+    fn expr_errors() {
+        let err = |expr: &str| expr.parse::<FilterExpr>().unwrap_err().to_string();
+        assert_eq!(err(""), "Empty filter expression");
+        assert_eq!(
+            err("band = 20m and"),
+            "Unexpected end of the expression, expected a field"
+        );
+        assert_eq!(
+            err("band 20m"),
+            "Unexpected \"20m\" at position 5, expected an operator like =, != or in"
+        );
+        assert_eq!(
+            err("color = red"),
+            format!(
+                "Unknown field \"color\" at position 0, expected one of {}",
+                FIELDS
+            )
+        );
+        assert_eq!(
+            err("mode = cw or mode = morse"),
+            "Invalid value \"morse\" at position 20 for the field mode, expected a mode like cw or ft8"
+        );
+        assert_eq!(
+            err("band < 20m"),
+            "Operator \"<\" at position 5 can not be applied to the field band"
+        );
+        assert_eq!(
+            err("freq in [14000..x]"),
+            "Invalid value \"14000..x\" at position 9 for the field freq, expected a frequency in kHz like 14025 or a range like 14000..14070"
+        );
+        assert_eq!(
+            err("(band = 20m"),
+            "Unexpected end of the expression, expected )"
+        );
+        assert_eq!(
+            err("band in [20m 40m]"),
+            "Unexpected \"40m\" at position 13, expected , or ]"
+        );
+        assert_eq!(
+            err("band = 20m mode = cw"),
+            "Unexpected \"mode\" at position 11, expected and, or or the end of the expression"
+        );
+        assert_eq!(
+            err("comment ~ \"up"),
+            "Unterminated string starting at position 10"
+        );
+        assert_eq!(
+            err("dx = ="),
+            "Unexpected \"=\" at position 5, expected a value"
+        );
+        assert_eq!(
+            err("band = 20m & mode = cw"),
+            "Unexpected \"&\" at position 11, expected and, or or the end of the expression"
+        );
+    }
+
+    #[test]
+    fn expr_nesting() {
+        let spot = crate::parse(
+            "DX de DL1ABC:     14025.0  3B9FR        599                            1812Z",
+        )
+        .unwrap();
+        let expr = |text: &str| text.parse::<FilterExpr>();
+
+        // Negations do not nest
+        let negations = "not ".repeat(100_000);
+        assert!(expr(&format!("{}band = 20m", negations))
+            .unwrap()
+            .matches(&spot));
+        assert!(!expr(&format!("! {}band = 20m", negations))
+            .unwrap()
+            .matches(&spot));
+
+        let nested = |depth| format!("{}band = 20m{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(expr(&nested(MAX_NESTING)).unwrap().matches(&spot));
+        assert_eq!(
+            expr(&nested(MAX_NESTING + 1)).unwrap_err(),
+            ExprError::TooDeep {
+                pos: MAX_NESTING,
+                max: MAX_NESTING
+            }
+        );
+        assert_eq!(
+            expr(&"(not ".repeat(100_000)).unwrap_err().to_string(),
+            format!(
+                "Parentheses nested too deeply at position {}, at most {} levels are allowed",
+                5 * MAX_NESTING,
+                MAX_NESTING
+            )
+        );
+    }
+
+    #[test]
+    fn expr_display() {
+        let filter: FilterExpr = "band = 20m and mode = cw".parse().unwrap();
+        assert_eq!(filter.to_string(), "band = 20m and mode = cw");
+    }
+}
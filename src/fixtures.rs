@@ -0,0 +1,15 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+/// DX spot of DXSpider with the locator of the spotter, on 80m
+pub(crate) const SPOT: &str =
+    "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62";
+
+/// DX spot without locator, on 20m in the CW segment
+pub(crate) const SPOT2: &str =
+    "DX de N2CQ:      14036.1  W0BH         OK QSO Party: Major            1624Z";
+
+/// DX spot without locator, on 20m in the SSB segment
+pub(crate) const SPOT3: &str =
+    "DX de DL1ABC:     14205.0  3B9FR        up 5                           1812Z";
@@ -0,0 +1,291 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::columns::ColumnLayout;
+use crate::types::*;
+use std::fmt::Write;
+
+impl Spot {
+    /// Format the spot as a line in the layout of a cluster software.
+    ///
+    /// The line is formatted without line ending and parses back to the same spot,
+    /// i.e. `parse(&spot.to_line(flavor).unwrap()) == Ok(spot)` for spots produced by the parser.
+    ///
+    /// ## Arguments
+    ///
+    /// * `flavor`: Cluster software whose layout is used, the layout of DXSpider is used for an unknown cluster software
+    ///
+    /// ## Result
+    ///
+    /// In case the spot is of a built-in type, the formatted line shall be returned.
+    /// In case of a custom spot `None` shall be returned, since the layout of custom spots is not known.
+    pub fn to_line(&self, flavor: ClusterFlavor) -> Option<String> {
+        match self {
+            Spot::DX(dx) => Some(dx.to_line(flavor)),
+            Spot::WWV(wwv) => Some(wwv.to_line(flavor)),
+            Spot::WCY(wcy) => Some(wcy.to_line(flavor)),
+            Spot::WX(wx) => Some(message_line("WX", &wx.call_de, wx.utc, &wx.msg)),
+            Spot::ToAll(ta) => Some(message_line("To ALL", &ta.call_de, ta.utc, &ta.msg)),
+            Spot::ToLocal(tl) => Some(message_line("To LOCAL", &tl.call_de, tl.utc, &tl.msg)),
+            Spot::Custom(_) => None,
+        }
+    }
+}
+
+impl DX {
+    /// Format the spot as a line in the columns of a cluster software, see [`ColumnLayout::of`].
+    ///
+    /// Fields overflowing their column shift the following fields to the right, like the cluster software does.
+    /// The comment is not cut to the width of its column.
+    /// The frequency is given with as many decimal places as needed, down to 1 Hz.
+    /// The locator is omitted for layouts without locator, since the parser rejects it for these flavours.
+    pub fn to_line(&self, flavor: ClusterFlavor) -> String {
+        let layout = ColumnLayout::of(flavor);
+        let mut line = String::from("DX de");
+
+        pad(&mut line, layout.call_de, 1);
+        write!(line, "{}:", self.call_de).unwrap();
+        let freq = format_khz(self.freq);
+        pad(&mut line, layout.freq_end.saturating_sub(freq.len()), 0);
+        line.push_str(&freq);
+
+        pad(&mut line, layout.call_dx, 1);
+        line.push_str(&self.call_dx);
+        pad(&mut line, layout.comment, 1);
+        if let Some(comment) = &self.comment {
+            line.push_str(comment.trim());
+        }
+
+        pad(&mut line, layout.time, 1);
+        write!(line, "{:04}Z", self.utc).unwrap();
+        if let Some(loc) = self.loc.as_ref().filter(|_| layout.loc) {
+            write!(line, " {}", loc).unwrap();
+        }
+
+        line
+    }
+}
+
+impl WWV {
+    /// Format the spot as a line of a cluster software.
+    /// CC Cluster marks the hour with `Z` and separates it by a space from the colon.
+    pub fn to_line(&self, flavor: ClusterFlavor) -> String {
+        let hour = match flavor {
+            ClusterFlavor::CCCluster => format!("<{:02}Z> :", self.utc),
+            _ => format!("<{:02}>:", self.utc),
+        };
+        format!(
+            "WWV de {} {}   SFI={}, A={}, K={}, {} -> {}",
+            self.call_de, hour, self.sfi, self.a, self.k, self.info1, self.info2
+        )
+    }
+}
+
+impl WCY {
+    /// Format the spot as a line of a cluster software, the layout is the same for all of them.
+    pub fn to_line(&self, _flavor: ClusterFlavor) -> String {
+        format!(
+            "WCY de {} <{:02}> : K={} expK={} A={} R={} SFI={} SA={} GMF={} Au={}",
+            self.call_de,
+            self.utc,
+            self.k,
+            self.expk,
+            self.a,
+            self.r,
+            self.sfi,
+            self.sa,
+            self.gmf,
+            self.au
+        )
+    }
+}
+
+/// Format a spot of the types WX, ToAll or ToLocal, which only differ in their prefix.
+fn message_line(prefix: &str, call_de: &str, utc: Option<u16>, msg: &Option<String>) -> String {
+    let mut line = format!("{} de {}", prefix, call_de);
+    match utc {
+        Some(utc) => write!(line, " <{:04}Z> :", utc).unwrap(),
+        None => line.push(':'),
+    }
+    if let Some(msg) = msg.as_deref().filter(|m| !m.is_empty()) {
+        write!(line, " {}", msg).unwrap();
+    }
+    line
+}
+
+/// Pad a line with spaces up to a column, at least `min` spaces are appended.
+fn pad(line: &mut String, column: usize, min: usize) {
+    let len = column.max(line.len() + min);
+    while line.len() < len {
+        line.push(' ');
+    }
+}
+
+/// Format a frequency (in Hz) in kHz, with at least one and up to three decimal places as needed.
+fn format_khz(hz: u64) -> String {
+    let mut khz = format!("{}.{:03}", hz / 1000, hz % 1000);
+    while khz.ends_with('0') && !khz.ends_with(".0") {
+        khz.pop();
+    }
+    khz
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::Parser;
+
+    /// Lines as sent by the cluster software, each formatted back exactly
+    const GOLDEN: &[(ClusterFlavor, &str)] = &[
+        (
+            ClusterFlavor::DXSpider,
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62",
+        ),
+        (
+            ClusterFlavor::DXSpider,
+            "DX de OZ1FJB:     3527.6  DL2ASG                                      1815Z JO55",
+        ),
+        (
+            ClusterFlavor::DXSpider,
+            "DX de KE8GX:     14025.0  3B9FR        599 into N. MI, strong signals 1812Z",
+        ),
+        (
+            ClusterFlavor::DXSpider,
+            "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms",
+        ),
+        (
+            ClusterFlavor::DXSpider,
+            "WCY de DK0WCY-1 <22> : K=9 expK=0 A=139 R=156 SFI=223 SA=maj GMF=sev Au=aurora",
+        ),
+        (ClusterFlavor::DXSpider, "WX de VA3SAE: va3sub"),
+        (
+            ClusterFlavor::DXSpider,
+            "To ALL de EA8CEN-9: carnaval de tenerife ea8urt",
+        ),
+        (
+            ClusterFlavor::DXSpider,
+            "To ALL de CT2IDL <1044Z> : TNX qso..",
+        ),
+        (ClusterFlavor::DXSpider, "To LOCAL de IW5CLM: off"),
+        (
+            ClusterFlavor::ARCluster,
+            "DX de N2CQ:      14036.1  W0BH         OK QSO Party: Major            1624Z",
+        ),
+        (
+            ClusterFlavor::CCCluster,
+            "DX de RK9UE:      7115.0  RK6BP                                       1625Z",
+        ),
+        (
+            ClusterFlavor::CCCluster,
+            "WWV de VE7CC <15Z> :   SFI=68, A=9, K=2, No Storms -> Minor w/G1",
+        ),
+        (
+            ClusterFlavor::RBN,
+            "DX de W3LPL-#:   14025.0  DL1ABC       CW    12 dB  22 WPM  CQ        1234Z",
+        ),
+    ];
+
+    fn parser(flavor: ClusterFlavor) -> Parser {
+        Parser::builder().flavor(flavor).build().unwrap()
+    }
+
+    #[test]
+    fn format_golden() {
+        for (flavor, line) in GOLDEN {
+            let spot = parser(*flavor).parse(line).unwrap();
+            assert_eq!(spot.to_line(*flavor).as_deref(), Some(*line));
+        }
+    }
+
+    #[test]
+    fn format_round_trip() {
+        let lines = [
+            "DX de PA/DL1ABC/P14025.0  3B9FR        599                            1812Z JO62",
+            "DX de DL1ABC:     14025.0  VP2V/DL1ABC/MMtnx fer qso                 1812Z",
+            "DX de DL1ABC:   14025.123  3B9FR        599                            1812Z JO62",
+            "WWV de AE5E <00>:   SFI=69, A=15, K=4, No Storms -> No Storms",
+            "WX de LA3WAA <1001Z> :  The command WX will send a local weather announcement.",
+            "To LOCAL de IW5CLM <1044Z> : off",
+        ];
+        for flavor in [
+            ClusterFlavor::DXSpider,
+            ClusterFlavor::ARCluster,
+            ClusterFlavor::CCCluster,
+            ClusterFlavor::RBN,
+            ClusterFlavor::Unknown,
+        ] {
+            for line in lines {
+                // The cluster software without locator ends the line with the time
+                let line = match ColumnLayout::of(flavor).loc {
+                    true => line,
+                    false => line.trim_end_matches(" JO62"),
+                };
+                let spot = parser(flavor).parse(line).unwrap();
+                let formatted = spot.to_line(flavor).unwrap();
+                assert_eq!(parser(flavor).parse(&formatted), Ok(spot), "{}", formatted);
+            }
+        }
+    }
+
+    #[test]
+    fn format_dx_columns() {
+        let mut dx = DX {
+            call_de: "DL1ABC-12".into(),
+            call_dx: "VP2V/DL1ABC/MM".into(),
+            freq: 14025150,
+            utc: 812,
+            loc: Some("JO62".into()),
+            comment: None,
+            truncated: false,
+        };
+        assert_eq!(
+            dx.to_line(ClusterFlavor::DXSpider),
+            "DX de DL1ABC-12:14025.15  VP2V/DL1ABC/MM                              0812Z JO62"
+        );
+        assert_eq!(
+            dx.to_line(ClusterFlavor::ARCluster),
+            "DX de DL1ABC-12:14025.15  VP2V/DL1ABC/MM                              0812Z"
+        );
+
+        // Overflowing fields are still separated
+        dx.call_de = "PA/DL1ABC/QRP".into();
+        dx.freq = 432100000;
+        dx.comment = Some("tropo, 59 into JO62 and beyond!".into());
+        let line = dx.to_line(ClusterFlavor::DXSpider);
+        assert_eq!(
+            line,
+            "DX de PA/DL1ABC/QRP:432100.0 VP2V/DL1ABC/MM tropo, 59 into JO62 and beyond! 0812Z JO62"
+        );
+        dx.truncated = true;
+        assert_eq!(crate::parse(&line), Ok(Spot::DX(dx)));
+    }
+
+    #[test]
+    fn format_frequencies() {
+        for khz in [
+            1810.0, 3527.6, 7025.3, 14025.15, 14025.123, 50313.0, 144300.05, 144300.001,
+        ] {
+            let hz = (khz * 1000.0_f64).round() as u64;
+            let dx = DX {
+                call_de: "DL1ABC".into(),
+                call_dx: "3B9FR".into(),
+                freq: hz,
+                utc: 1812,
+                ..DX::new()
+            };
+            let line = dx.to_line(ClusterFlavor::DXSpider);
+            assert_eq!(crate::parse(&line), Ok(Spot::DX(dx)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn format_custom() {
+        let spot = Spot::Custom(Custom {
+            kind: "contest".into(),
+            payload: serde_json::Value::Null,
+        });
+        assert_eq!(spot.to_line(ClusterFlavor::DXSpider), None);
+    }
+}
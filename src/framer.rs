@@ -0,0 +1,386 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::sanitize::*;
+use std::collections::VecDeque;
+
+const NUL: u8 = 0x00;
+const LF: u8 = 0x0A;
+const CR: u8 = 0x0D;
+
+/// Default maximum length of a line in bytes
+pub const DEFAULT_MAX_LINE_LEN: usize = 1024;
+
+/// Reason a line was completed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Boundary {
+    /// Line ending, either CRLF, LF or CR
+    Newline,
+
+    /// Prompt that arrived without a line ending
+    Prompt,
+
+    /// Maximum length of a line exceeded, the rest of the line is dropped
+    MaxLength,
+
+    /// Remaining bytes flushed by [`Framer::flush`]
+    Flush,
+}
+
+/// Complete and sanitized line yielded by a [`Framer`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FramedLine {
+    /// Sanitized bytes of the line, to be parsed by [`crate::Parser::parse_bytes`]
+    pub bytes: Vec<u8>,
+
+    /// Noise removed by the sanitizer, see [`sanitize_bytes`]
+    pub removed: Removed,
+
+    /// Reason the line was completed
+    pub boundary: Boundary,
+}
+
+/// State of the telnet commands within the stream
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Telnet {
+    Data,
+    Command,
+    Option,
+    Sub,
+    SubIac,
+}
+
+/// Framer splitting a stream of bytes received from a DX Cluster into lines.
+///
+/// Chunks of bytes are pushed into the framer as they arrive from the socket, the framer then yields the complete lines.
+/// Lines may end with CRLF, LF or CR only. Telnet commands are never split, even in case they contain a line ending.
+/// Each line is cleaned by [`sanitize_bytes`], empty lines are skipped.
+///
+/// A prompt of the cluster, e.g. `login: `, usually arrives without a line ending.
+/// Since a chunk may end anywhere within a line, a prompt is only detected once no bytes arrived for a while, see [`Framer::idle`].
+/// In case the buffered bytes end with one of the prompt suffixes and do not start with a known type of spot,
+/// they are yielded as a line then.
+///
+/// ```
+/// let mut framer = dxclparser::Framer::new();
+/// framer.push(b"DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62\r\n");
+/// for line in &mut framer {
+///     println!("{:?}", dxclparser::parse_bytes(&line.bytes));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Framer {
+    max_len: usize,
+    prompts: Vec<Vec<u8>>,
+    buf: Vec<u8>,
+    lines: VecDeque<FramedLine>,
+    telnet: Telnet,
+    pending_cr: bool,
+    discard: bool,
+}
+
+impl Framer {
+    /// Create a framer with the default maximum length of a line and the default prompt suffixes `>` and `:`.
+    pub fn new() -> Self {
+        Framer {
+            max_len: DEFAULT_MAX_LINE_LEN,
+            prompts: vec![b">".to_vec(), b":".to_vec()],
+            buf: Vec::new(),
+            lines: VecDeque::new(),
+            telnet: Telnet::Data,
+            pending_cr: false,
+            discard: false,
+        }
+    }
+
+    /// Set the maximum length of a line in bytes.
+    /// Longer lines are cut and yielded with [`Boundary::MaxLength`], the rest of the line is dropped.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    /// Set the suffixes a prompt ends with, in addition to optional trailing whitespace.
+    /// Without any suffix, bytes without a line ending are only yielded by [`Framer::flush`], never by [`Framer::idle`].
+    pub fn prompts(mut self, suffixes: &[&str]) -> Self {
+        self.prompts = suffixes.iter().map(|s| s.as_bytes().to_vec()).collect();
+        self
+    }
+
+    /// Push a chunk of bytes received from the cluster.
+    /// Lines completed by the chunk are yielded by the framer afterwards.
+    pub fn push(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            self.feed(b);
+        }
+    }
+
+    /// Signal that no bytes arrived for a while, e.g. after a read timed out.
+    /// In case the buffered bytes look like a prompt, they are yielded as a line with [`Boundary::Prompt`].
+    /// Other incomplete lines stay buffered.
+    pub fn idle(&mut self) {
+        if self.telnet != Telnet::Data || self.discard || self.buf.is_empty() {
+            return;
+        }
+
+        let res = sanitize_bytes(&self.buf);
+        let prompt = !is_spot(&res.line) && self.prompts.iter().any(|p| res.line.ends_with(p));
+        if prompt {
+            self.lines.push_back(FramedLine {
+                bytes: res.line.into_owned(),
+                removed: res.removed,
+                boundary: Boundary::Prompt,
+            });
+            self.buf.clear();
+        }
+    }
+
+    /// Yield the buffered bytes as a line even though the line is not complete yet, e.g. at the end of the stream.
+    pub fn flush(&mut self) {
+        if !self.discard {
+            self.emit(Boundary::Flush);
+        }
+        self.buf.clear();
+        self.telnet = Telnet::Data;
+        self.discard = false;
+    }
+
+    /// Get the number of buffered bytes of the incomplete line.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn feed(&mut self, b: u8) {
+        match self.telnet {
+            Telnet::Data => {}
+            Telnet::Command => {
+                self.telnet = match b {
+                    SB => Telnet::Sub,
+                    WILL..=DONT => Telnet::Option,
+                    _ => Telnet::Data,
+                };
+                return self.keep(b);
+            }
+            Telnet::Option => {
+                self.telnet = Telnet::Data;
+                return self.keep(b);
+            }
+            Telnet::Sub => {
+                if b == IAC {
+                    self.telnet = Telnet::SubIac;
+                }
+                return self.keep(b);
+            }
+            Telnet::SubIac => {
+                self.telnet = if b == SE { Telnet::Data } else { Telnet::Sub };
+                return self.keep(b);
+            }
+        }
+
+        // A CR may be followed by LF or, according to telnet, by NUL
+        if std::mem::take(&mut self.pending_cr) && (b == LF || b == NUL) {
+            return;
+        }
+
+        match b {
+            IAC => {
+                self.telnet = Telnet::Command;
+                self.keep(b);
+            }
+            LF => self.end_line(),
+            CR => {
+                self.end_line();
+                self.pending_cr = true;
+            }
+            _ => self.keep(b),
+        }
+    }
+
+    fn keep(&mut self, b: u8) {
+        if self.discard {
+            return;
+        }
+
+        if self.buf.len() >= self.max_len {
+            self.emit(Boundary::MaxLength);
+            self.buf.clear();
+            self.discard = true;
+        } else {
+            self.buf.push(b);
+        }
+    }
+
+    fn end_line(&mut self) {
+        if !self.discard {
+            self.emit(Boundary::Newline);
+        }
+        self.buf.clear();
+        self.discard = false;
+    }
+
+    fn emit(&mut self, boundary: Boundary) {
+        let res = sanitize_bytes(&self.buf);
+        if !res.line.is_empty() {
+            self.lines.push_back(FramedLine {
+                bytes: res.line.into_owned(),
+                removed: res.removed,
+                boundary,
+            });
+        }
+    }
+}
+
+impl Default for Framer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Framer {
+    type Item = FramedLine;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::fixtures::SPOT;
+
+    fn lines(framer: &mut Framer) -> Vec<String> {
+        framer
+            .map(|l| String::from_utf8(l.bytes).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn framer_line_endings() {
+        let mut framer = Framer::new();
+        framer.push(b"WX de A1BC: crlf\r\nWX de A1BC: lf\nWX de A1BC: cr\rWX de A1BC: last");
+        assert_eq!(
+            lines(&mut framer),
+            vec!["WX de A1BC: crlf", "WX de A1BC: lf", "WX de A1BC: cr"]
+        );
+        assert_eq!(framer.buffered(), 16);
+
+        framer.flush();
+        let line = framer.next().unwrap();
+        assert_eq!(line.bytes, b"WX de A1BC: last");
+        assert_eq!(line.boundary, Boundary::Flush);
+    }
+
+    #[test]
+    fn framer_split_chunks() {
+        let mut framer = Framer::new();
+        let raw = format!("{}\r\n{}\r\n", SPOT, SPOT);
+        for chunk in raw.as_bytes().chunks(7) {
+            framer.push(chunk);
+        }
+        assert_eq!(lines(&mut framer), vec![SPOT, SPOT]);
+    }
+
+    #[test]
+    fn framer_cr_lf_across_chunks() {
+        let mut framer = Framer::new();
+        framer.push(b"WX de A1BC: one\r");
+        framer.push(b"\nWX de A1BC: two\r\0\r\r\n");
+        assert_eq!(
+            lines(&mut framer),
+            vec!["WX de A1BC: one", "WX de A1BC: two"]
+        );
+    }
+
+    #[test]
+    fn framer_prompt() {
+        let mut framer = Framer::new();
+        framer.push(b"Hello from DB0SUE-7\r\nlogin: ");
+        assert_eq!(framer.next().unwrap().bytes, b"Hello from DB0SUE-7");
+        assert_eq!(framer.next(), None);
+        framer.idle();
+        let res: Vec<_> = framer.by_ref().collect();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].bytes, b"login:");
+        assert_eq!(res[0].boundary, Boundary::Prompt);
+        assert_eq!(framer.buffered(), 0);
+
+        // A partial spot is never taken for a prompt
+        framer.push(b"DX de DJ1TO:");
+        framer.idle();
+        assert_eq!(framer.next(), None);
+        framer.push(&SPOT.as_bytes()[12..]);
+        framer.push(b"\n");
+        assert_eq!(lines(&mut framer), vec![SPOT]);
+    }
+
+    #[test]
+    fn framer_prompt_split_line() {
+        // A chunk ending with a prompt suffix does not complete the line
+        let mut framer = Framer::new();
+        framer.push(b"Hello DL1ABC, this is DB0SUE-7 in JO62:");
+        assert_eq!(framer.next(), None);
+        framer.push(b" welcome to the cluster\r\n");
+        let line = framer.next().unwrap();
+        assert_eq!(
+            line.bytes,
+            b"Hello DL1ABC, this is DB0SUE-7 in JO62: welcome to the cluster"
+        );
+        assert_eq!(line.boundary, Boundary::Newline);
+
+        // Without a prompt suffix nothing is yielded when idle
+        framer.push(b"Hello DL1ABC");
+        framer.idle();
+        assert_eq!(framer.next(), None);
+        assert_eq!(framer.buffered(), 12);
+    }
+
+    #[test]
+    fn framer_prompt_glued_to_spot() {
+        let mut framer = Framer::new();
+        framer.push(b"DL1ABC de DB0SUE-7 10-Sep-2022 2200Z dxspider >\x07\x07");
+        assert_eq!(framer.next(), None);
+        framer.push(SPOT.as_bytes());
+        framer.push(b"\r\n");
+        let line = framer.next().unwrap();
+        assert_eq!(line.bytes, SPOT.as_bytes());
+        assert_eq!(line.removed.bell, 2);
+        assert!(line.removed.prefix.is_some());
+    }
+
+    #[test]
+    fn framer_max_len() {
+        let mut framer = Framer::new().max_len(10);
+        framer.push(b"WX de A1BC: a very long line\r\nWX de A1BC\r\n");
+        let res: Vec<_> = framer.collect();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].bytes, b"WX de A1BC");
+        assert_eq!(res[0].boundary, Boundary::MaxLength);
+        assert_eq!(res[1].bytes, b"WX de A1BC");
+        assert_eq!(res[1].boundary, Boundary::Newline);
+    }
+
+    #[test]
+    fn framer_telnet() {
+        let mut framer = Framer::new();
+        // IAC DO NAOCRD with the option being a LF, followed by a subnegotiation holding a CR
+        framer.push(b"\xff\xfd\x0a\xff\xfa\x18\x0d");
+        framer.push(b"\xff\xf0WX de A1BC: hi\r\n");
+        let line = framer.next().unwrap();
+        assert_eq!(line.bytes, b"WX de A1BC: hi");
+        assert_eq!(line.removed.telnet, 2);
+        assert_eq!(framer.next(), None);
+    }
+
+    #[test]
+    fn framer_parse() {
+        let mut framer = Framer::new();
+        framer.push(format!("\x1b[0;32m{}\x1b[0m\x07\r\n", SPOT).as_bytes());
+        let res: Vec<_> = framer
+            .map(|l| crate::parse_bytes(&l.bytes).map(|r| r.spot))
+            .collect();
+        assert_eq!(res, vec![crate::parse(SPOT)]);
+    }
+}
@@ -0,0 +1,388 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::band::Band;
+use crate::command::abbreviates;
+use crate::error::CommandError;
+use crate::types::{Date, Spot, DX, WCY, WWV};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+/// Default number of spots of each type kept by a [`SpotHistory`]
+pub const DEFAULT_HISTORY_LEN: usize = 1000;
+
+/// Number of DX spots shown without a given count, like DXSpider
+const DEFAULT_DX_COUNT: u16 = 10;
+
+/// Number of WWV and WCY spots shown without a given count, like DXSpider
+const DEFAULT_WWV_COUNT: u16 = 5;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Header of the reply to `SH/WWV`
+const WWV_HEADER: &str =
+    "Date        Hour   SFI   A   K Forecast                               Logger";
+
+/// Header of the reply to `SH/WCY`
+const WCY_HEADER: &str = "Date        Hour   SFI   A   K Exp.K   R SA    GMF   Aurora   Logger";
+
+/// Query of the spot history, parsed from the show commands of DXSpider
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Query {
+    /// Show the last DX spots, optionally limited to the DX stations starting with a prefix and to a band
+    Dx {
+        count: Option<u16>,
+        call: Option<String>,
+        band: Option<Band>,
+    },
+
+    /// Show the last WWV spots
+    Wwv { count: Option<u16> },
+
+    /// Show the last WCY spots
+    Wcy { count: Option<u16> },
+}
+
+impl FromStr for Query {
+    type Err = CommandError;
+
+    /// Parse a show command in the syntax of DXSpider, e.g. `SH/DX 20 3B9FR`, `SH/DX on 20m` or `SH/WWV/3`.
+    /// The case is ignored, the count may also be given as qualifier like at AR-Cluster.
+    /// A prefix of the DX station may end with `*`, e.g. `SH/DX 3B*`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.trim().to_ascii_lowercase();
+        let unknown = || CommandError::UnknownCommand(String::from(s.trim()));
+        let mut words = line.split_whitespace();
+        let mut parts = words.next().ok_or_else(unknown)?.split('/');
+
+        if !parts.next().is_some_and(|v| abbreviates(v, "show", 2)) {
+            return Err(unknown());
+        }
+        let target = parts.next().ok_or_else(unknown)?;
+        let mut count = match parts.next() {
+            Some(c) => Some(c.parse::<u16>().map_err(|_| unknown())?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return Err(unknown());
+        }
+
+        match target {
+            "dx" => {
+                let (mut call, mut band) = (None, None);
+                while let Some(word) = words.next() {
+                    if word == "on" {
+                        let name = words.next().unwrap_or_default();
+                        let invalid = || CommandError::InvalidBand(String::from(name));
+                        band = Some(name.parse().map_err(|_| invalid())?);
+                    } else if let (Ok(n), None) = (word.parse(), count) {
+                        count = Some(n);
+                    } else if call.is_none() {
+                        call = Some(word.trim_end_matches('*').to_ascii_uppercase());
+                    } else {
+                        return Err(unknown());
+                    }
+                }
+                Ok(Query::Dx { count, call, band })
+            }
+            "wwv" | "wcy" => {
+                if let Some(word) = words.next() {
+                    count = Some(word.parse().map_err(|_| unknown())?);
+                }
+                if words.next().is_some() {
+                    return Err(unknown());
+                }
+                match target {
+                    "wwv" => Ok(Query::Wwv { count }),
+                    _ => Ok(Query::Wcy { count }),
+                }
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+/// In-memory history of the last DX, WWV and WCY spots, answering the show commands of DXSpider.
+///
+/// ```
+/// use dxclparser::{Date, Query, SpotHistory};
+///
+/// let mut history = SpotHistory::new(100);
+/// let spot = dxclparser::parse(
+///     "DX de DL1ABC:     14025.0  3B9FR        599                            1812Z",
+/// ).unwrap();
+/// history.push(&spot, Date::new(2022, 9, 10));
+///
+/// let query = "SH/DX on 20m".parse::<Query>().unwrap();
+/// assert_eq!(
+///     history.answer(&query),
+///     vec![" 14025.0  3B9FR       10-Sep-2022 1812Z  599                         <DL1ABC>"]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpotHistory {
+    capacity: usize,
+    dx: VecDeque<(Date, DX)>,
+    wwv: VecDeque<(Date, WWV)>,
+    wcy: VecDeque<(Date, WCY)>,
+}
+
+impl SpotHistory {
+    /// Create a history keeping up to `capacity` spots of each type, older spots are dropped.
+    pub fn new(capacity: usize) -> Self {
+        SpotHistory {
+            capacity,
+            dx: VecDeque::new(),
+            wwv: VecDeque::new(),
+            wcy: VecDeque::new(),
+        }
+    }
+
+    /// Add a spot received on the given date, spots of other types than DX, WWV and WCY are ignored.
+    pub fn push(&mut self, spot: &Spot, date: Date) {
+        match spot {
+            Spot::DX(dx) => push(&mut self.dx, (date, dx.clone()), self.capacity),
+            Spot::WWV(wwv) => push(&mut self.wwv, (date, wwv.clone()), self.capacity),
+            Spot::WCY(wcy) => push(&mut self.wcy, (date, wcy.clone()), self.capacity),
+            _ => {}
+        }
+    }
+
+    /// Answer a query in the formatting of DXSpider.
+    ///
+    /// ## Arguments
+    ///
+    /// * `query`: Query of the history
+    ///
+    /// ## Result
+    ///
+    /// The lines of the reply shall be returned, without line endings and with the latest spot first.
+    /// In case no spot matches, no lines shall be returned.
+    pub fn answer(&self, query: &Query) -> Vec<String> {
+        match query {
+            Query::Dx { count, call, band } => self
+                .dx
+                .iter()
+                .rev()
+                .filter(|(_, dx)| call.as_ref().is_none_or(|c| dx.call_dx.starts_with(c)))
+                .filter(|(_, dx)| band.is_none_or(|b| Band::of(dx.freq) == Some(b)))
+                .take(count.unwrap_or(DEFAULT_DX_COUNT).into())
+                .map(|(date, dx)| dx_line(date, dx))
+                .collect(),
+            Query::Wwv { count } => with_header(
+                WWV_HEADER,
+                self.wwv
+                    .iter()
+                    .rev()
+                    .take(count.unwrap_or(DEFAULT_WWV_COUNT).into())
+                    .map(|(date, wwv)| wwv_line(date, wwv)),
+            ),
+            Query::Wcy { count } => with_header(
+                WCY_HEADER,
+                self.wcy
+                    .iter()
+                    .rev()
+                    .take(count.unwrap_or(DEFAULT_WWV_COUNT).into())
+                    .map(|(date, wcy)| wcy_line(date, wcy)),
+            ),
+        }
+    }
+}
+
+impl Default for SpotHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_LEN)
+    }
+}
+
+fn push<T>(queue: &mut VecDeque<T>, item: T, capacity: usize) {
+    if queue.len() >= capacity {
+        queue.pop_front();
+    }
+    if capacity > 0 {
+        queue.push_back(item);
+    }
+}
+
+fn with_header(header: &str, lines: impl Iterator<Item = String>) -> Vec<String> {
+    let lines: Vec<String> = lines.collect();
+    match lines.is_empty() {
+        true => lines,
+        false => std::iter::once(String::from(header)).chain(lines).collect(),
+    }
+}
+
+/// Format a date like DXSpider, e.g. `10-Sep-2022`.
+fn cldate(date: &Date) -> String {
+    let month = MONTHS[usize::from(date.month.clamp(1, 12)) - 1];
+    format!("{:2}-{}-{:04}", date.day, month, date.year)
+}
+
+fn dx_line(date: &Date, dx: &DX) -> String {
+    format!(
+        "{:8.1}  {:<11} {} {:04}Z  {:<28.28}{:>7}>",
+        dx.freq as f64 / 1000.0,
+        dx.call_dx,
+        cldate(date),
+        dx.utc,
+        dx.comment.as_deref().unwrap_or_default(),
+        format!("<{}", dx.call_de)
+    )
+}
+
+fn wwv_line(date: &Date, wwv: &WWV) -> String {
+    format!(
+        "{}   {:02} {:5} {:3} {:3} {:<37} <{}>",
+        cldate(date),
+        wwv.utc,
+        wwv.sfi,
+        wwv.a,
+        wwv.k,
+        format!("{} -> {}", wwv.info1, wwv.info2),
+        wwv.call_de
+    )
+}
+
+fn wcy_line(date: &Date, wcy: &WCY) -> String {
+    format!(
+        "{}   {:02} {:5} {:3} {:3} {:3} {:3} {:<5} {:<5} {:>6} <{}>",
+        cldate(date),
+        wcy.utc,
+        wcy.sfi,
+        wcy.a,
+        wcy.k,
+        wcy.expk,
+        wcy.r,
+        wcy.sa,
+        wcy.gmf,
+        wcy.au,
+        wcy.call_de
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const DATE: Date = Date {
+        year: 2022,
+        month: 9,
+        day: 10,
+    };
+
+    fn history() -> SpotHistory {
+        let mut history = SpotHistory::new(4);
+        for line in [
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62",
+            "DX de N2CQ:      14036.1  W0BH         OK QSO Party: Major            1624Z",
+            "DX de KE8GX:     14025.0  3B9FR        599 into N. MI, strong signals 1812Z",
+            "DX de DL1ABC:    14025.2  3B8CW        599                            1813Z",
+            "DX de DL1ABC:     7025.0  3B9FR                                       1814Z",
+            "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms",
+            "WCY de DK0WCY-1 <22> : K=9 expK=0 A=139 R=156 SFI=223 SA=maj GMF=sev Au=aurora",
+            "To ALL de CT2IDL <1044Z> : TNX qso..",
+        ] {
+            history.push(&crate::parse(line).unwrap(), DATE);
+        }
+        history
+    }
+
+    #[test]
+    fn history_query_parse() {
+        let dx = |count, call: Option<&str>, band| Query::Dx {
+            count,
+            call: call.map(String::from),
+            band,
+        };
+        assert_eq!("SH/DX".parse(), Ok(dx(None, None, None)));
+        assert_eq!("show/dx 3b9fr".parse(), Ok(dx(None, Some("3B9FR"), None)));
+        assert_eq!(
+            "sh/dx 20 3B* on 20m".parse(),
+            Ok(dx(Some(20), Some("3B"), Some(Band::M20)))
+        );
+        assert_eq!(
+            "SH/DX/5 on 40".parse(),
+            Ok(dx(Some(5), None, Some(Band::M40)))
+        );
+        assert_eq!("SH/WWV".parse(), Ok(Query::Wwv { count: None }));
+        assert_eq!("sh/wcy 3".parse(), Ok(Query::Wcy { count: Some(3) }));
+        assert_eq!(
+            "sh/dx on 11m".parse::<Query>(),
+            Err(CommandError::InvalidBand("11m".into()))
+        );
+        for line in [
+            "",
+            "sh",
+            "sh/users",
+            "s/dx",
+            "sh/dx 3B9FR 3B8CW",
+            "sh/wwv x",
+            "sh/dx/x",
+        ] {
+            assert!(
+                matches!(line.parse::<Query>(), Err(CommandError::UnknownCommand(_))),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn history_dx() {
+        let history = history();
+        let query = |line: &str| history.answer(&line.parse().unwrap());
+
+        // The first spot was dropped, the latest spot is shown first
+        assert_eq!(
+            query("SH/DX"),
+            vec![
+                "  7025.0  3B9FR       10-Sep-2022 1814Z                              <DL1ABC>",
+                " 14025.2  3B8CW       10-Sep-2022 1813Z  599                         <DL1ABC>",
+                " 14025.0  3B9FR       10-Sep-2022 1812Z  599 into N. MI, strong signa <KE8GX>",
+                " 14036.1  W0BH        10-Sep-2022 1624Z  OK QSO Party: Major           <N2CQ>",
+            ]
+        );
+        assert_eq!(query("SH/DX 2").len(), 2);
+        assert_eq!(query("SH/DX 3B9FR").len(), 2);
+        assert_eq!(query("SH/DX 3B*").len(), 3);
+        assert_eq!(query("SH/DX 3B9FR on 20m").len(), 1);
+        assert!(query("SH/DX OH5Z").is_empty());
+    }
+
+    #[test]
+    fn history_wwv_wcy() {
+        let history = history();
+        assert_eq!(
+            history.answer(&Query::Wwv { count: None }),
+            vec![
+                WWV_HEADER,
+                "10-Sep-2022   21    70  12   3 No Storms -> No Storms                <VE7CC>",
+            ]
+        );
+        assert_eq!(
+            history.answer(&Query::Wcy { count: None }),
+            vec![
+                WCY_HEADER,
+                "10-Sep-2022   22   223 139   9   0 156 maj   sev   aurora <DK0WCY-1>",
+            ]
+        );
+        assert!(SpotHistory::default()
+            .answer(&Query::Wwv { count: None })
+            .is_empty());
+    }
+
+    #[test]
+    fn history_date_from_timestamp() {
+        assert_eq!(Date::from_timestamp(DATE.timestamp(2200)), DATE);
+        assert_eq!(Date::from_timestamp(0), Date::new(1970, 1, 1));
+        assert_eq!(
+            Date::from_timestamp(Date::new(2024, 2, 29).timestamp(2359)),
+            Date::new(2024, 2, 29)
+        );
+        assert_eq!(cldate(&Date::new(2022, 10, 8)), " 8-Oct-2022");
+    }
+}
@@ -2,8 +2,70 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod aggregator;
+#[cfg(feature = "tokio")]
+pub mod async_client;
+pub mod band;
+pub mod borrowed;
+pub mod callinfo;
+pub mod client;
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod columns;
+pub mod command;
+pub mod custom;
+pub mod dedup;
+pub mod definition;
+pub mod encoding;
+pub mod error;
+pub mod expr;
+#[cfg(test)]
+mod fixtures;
+pub mod format;
+pub mod framer;
+pub mod history;
+pub mod mock;
+pub mod mode;
 pub mod parser;
+pub mod recovery;
+pub mod relay;
+pub mod sanitize;
+mod server;
+pub mod spider;
+pub mod submit;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod tokenizer;
 pub mod types;
 
+pub use aggregator::*;
+#[cfg(feature = "tokio")]
+pub use async_client::*;
+pub use band::*;
+pub use borrowed::*;
+pub use callinfo::*;
+pub use client::*;
+#[cfg(feature = "codec")]
+pub use codec::*;
+pub use columns::*;
+pub use command::*;
+pub use custom::*;
+pub use dedup::*;
+pub use definition::*;
+pub use encoding::*;
+pub use error::*;
+pub use expr::*;
+pub use framer::*;
+pub use history::*;
+pub use mock::*;
+pub use mode::*;
 pub use parser::*;
+pub use recovery::*;
+pub use relay::*;
+pub use sanitize::*;
+pub use spider::*;
+pub use submit::*;
+#[cfg(feature = "tls")]
+pub use tls::*;
+pub use tokenizer::*;
 pub use types::*;
@@ -0,0 +1,430 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::server::Server;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Lines recorded from a cluster, each with its offset from the start of the recording
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Recording {
+    lines: Vec<(Duration, String)>,
+}
+
+impl Recording {
+    /// Create an empty recording.
+    pub fn new() -> Self {
+        Recording { lines: Vec::new() }
+    }
+
+    /// Append a line received at the given offset from the start of the recording.
+    /// An offset before the one of the previous line is raised to it.
+    pub fn line(mut self, offset: Duration, line: &str) -> Self {
+        let offset = offset.max(self.lines.last().map_or(Duration::ZERO, |l| l.0));
+        self.lines.push((offset, String::from(line)));
+        self
+    }
+
+    /// Parse a recording given as text, one line received from the cluster each.
+    ///
+    /// Each line may be prefixed by the unix time (in seconds, with fraction) it was received at, e.g. as written by `ts %.s`.
+    /// The offsets are taken relative to the first timestamp, lines without timestamp keep the offset of the previous line.
+    /// Empty lines are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut recording = Recording::new();
+        let mut first = None;
+        let mut offset = Duration::ZERO;
+
+        for line in text.lines().map(|l| l.trim_end_matches('\r')) {
+            let line = match line.split_once(' ') {
+                Some((ts, rest)) => match ts.parse::<f64>() {
+                    Ok(ts) if ts.is_finite() && ts >= 0.0 => {
+                        let first = *first.get_or_insert(ts);
+                        offset = Duration::from_secs_f64((ts - first).max(0.0));
+                        rest
+                    }
+                    _ => line,
+                },
+                None => line,
+            };
+            if !line.trim().is_empty() {
+                recording = recording.line(offset, line);
+            }
+        }
+
+        recording
+    }
+
+    /// Get the number of recorded lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Check whether no lines were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// Builder for a [`MockCluster`]
+pub struct MockBuilder {
+    node: String,
+    greeting: Option<String>,
+    login_prompt: String,
+    password: Option<String>,
+    commands: Vec<(String, String)>,
+    recording: Recording,
+    speed: f64,
+}
+
+impl MockBuilder {
+    /// Create a builder of a cluster with the callsign `DB0MCK`, which replays nothing and knows no commands.
+    pub fn new() -> Self {
+        MockBuilder {
+            node: String::from("DB0MCK"),
+            greeting: None,
+            login_prompt: String::from("login: "),
+            password: None,
+            commands: Vec::new(),
+            recording: Recording::new(),
+            speed: 1.0,
+        }
+    }
+
+    /// Set the callsign of the cluster, shown in the greeting and the prompt.
+    pub fn node(mut self, node: &str) -> Self {
+        self.node = String::from(node);
+        self
+    }
+
+    /// Set the text sent to a connecting client in front of the login prompt, by default `Welcome to <node>`.
+    pub fn greeting(mut self, greeting: &str) -> Self {
+        self.greeting = Some(String::from(greeting));
+        self
+    }
+
+    /// Set the prompt asking for the callsign, by default `login: `.
+    pub fn login_prompt(mut self, prompt: &str) -> Self {
+        self.login_prompt = String::from(prompt);
+        self
+    }
+
+    /// Ask for a password after the callsign, a client sending another password is disconnected.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(String::from(password));
+        self
+    }
+
+    /// Add a command and the reply sent for it, the lines of the reply are separated by `\n`.
+    /// Commands are matched case-insensitive and regardless of the spacing.
+    pub fn command(mut self, command: &str, reply: &str) -> Self {
+        self.commands
+            .push((normalize(command), String::from(reply)));
+        self
+    }
+
+    /// Set the lines replayed to each client after its login.
+    pub fn replay(mut self, recording: Recording) -> Self {
+        self.recording = recording;
+        self
+    }
+
+    /// Set the speed of the replay relative to the recording, by default `1.0` for real time.
+    /// E.g. `60.0` replays a minute of the recording within a second, `f64::INFINITY` replays all lines at once.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Start listening for clients.
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address to listen on, e.g. `127.0.0.1:0` for a free port chosen by the system
+    ///
+    /// ## Result
+    ///
+    /// In case the cluster listens, the running cluster shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn bind<A: ToSocketAddrs>(self, addr: A) -> io::Result<MockCluster> {
+        if self.speed.is_nan() || self.speed <= 0.0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "speed of the replay must be positive",
+            ));
+        }
+
+        let shared = Arc::new(Shared {
+            config: self,
+            received: Mutex::new(Vec::new()),
+        });
+
+        let server = {
+            let shared = shared.clone();
+            Server::bind(addr, move |stream| {
+                let _ = session(stream, &shared);
+            })?
+        };
+
+        Ok(MockCluster { server, shared })
+    }
+}
+
+impl Default for MockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// DX cluster listening on a local port, e.g. to test clients against it.
+///
+/// Each client is greeted and logged in like by DXSpider, afterwards the recorded lines are replayed to it.
+/// Commands sent by the client are answered by their scripted reply and the prompt.
+/// The cluster stops listening and disconnects all clients as soon as it is dropped.
+///
+/// ```
+/// use dxclparser::{ClusterClient, MockCluster, Recording};
+///
+/// let spot = "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62";
+/// let cluster = MockCluster::builder()
+///     .replay(Recording::new().line(Default::default(), spot))
+///     .bind("127.0.0.1:0")
+///     .unwrap();
+///
+/// let mut client = ClusterClient::connect(cluster.addr(), "DL1ABC").unwrap();
+/// assert_eq!(client.next(), Some(dxclparser::parse(spot)));
+/// ```
+pub struct MockCluster {
+    server: Server,
+    shared: Arc<Shared>,
+}
+
+impl MockCluster {
+    /// Create a builder for a cluster.
+    pub fn builder() -> MockBuilder {
+        MockBuilder::new()
+    }
+
+    /// Get the address the cluster listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.server.addr()
+    }
+
+    /// Get the commands received from all clients so far, in the order they arrived.
+    /// Callsigns and passwords of the login are not included.
+    pub fn received(&self) -> Vec<String> {
+        self.shared.received.lock().unwrap().clone()
+    }
+}
+
+/// State shared by the sessions of the clients
+struct Shared {
+    config: MockBuilder,
+    received: Mutex<Vec<String>>,
+}
+
+/// Serve a single client: login, replay of the recording and answers to its commands.
+fn session(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let config = &shared.config;
+    let lines = read_lines(&stream)?;
+    let closed = || io::Error::from(ErrorKind::UnexpectedEof);
+
+    let greeting = config
+        .greeting
+        .clone()
+        .unwrap_or_else(|| format!("Welcome to {}", config.node));
+    write!(stream, "{}\r\n\r\n{}", greeting, config.login_prompt)?;
+    let call = lines.recv().map_err(|_| closed())?.to_ascii_uppercase();
+    if let Some(password) = &config.password {
+        stream.write_all(b"password: ")?;
+        if lines.recv().map_err(|_| closed())? != *password {
+            stream.write_all(b"Sorry, wrong password\r\n")?;
+            return stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    let prompt = format!("{} de {} >", call, config.node);
+    stream.write_all(prompt.as_bytes())?;
+
+    let start = Instant::now();
+    let mut replay = config.recording.lines.iter().peekable();
+    loop {
+        let received = match replay.peek() {
+            Some((offset, _)) => {
+                let due = start + Duration::from_secs_f64(offset.as_secs_f64() / config.speed);
+                lines.recv_timeout(due.saturating_duration_since(Instant::now()))
+            }
+            None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(command) if command.trim().is_empty() => {}
+            Ok(command) => {
+                shared.received.lock().unwrap().push(command.clone());
+                if normalize(&command) == "BYE" {
+                    break;
+                }
+                let reply = config
+                    .commands
+                    .iter()
+                    .find(|(c, _)| *c == normalize(&command))
+                    .map_or_else(
+                        || format!("Unknown command: {}", command),
+                        |(_, r)| r.clone(),
+                    );
+                for line in reply.lines() {
+                    write!(stream, "{}\r\n", line)?;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // Like DXSpider, the line follows the prompt directly
+                let (_, line) = replay.next().unwrap();
+                write!(stream, "{}\r\n", line)?;
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        stream.write_all(prompt.as_bytes())?;
+    }
+
+    stream.shutdown(Shutdown::Both)
+}
+
+/// Read the lines sent by a client in a thread of its own, the channel disconnects at the end of the stream.
+fn read_lines(stream: &TcpStream) -> io::Result<Receiver<String>> {
+    let reader = BufReader::new(stream.try_clone()?);
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in reader.split(b'\n') {
+            let Ok(line) = line else { break };
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Normalize a command for the comparison, e.g. `sh/dx  20` to `SH/DX 20`.
+fn normalize(command: &str) -> String {
+    command
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::client::ClusterClient;
+    use crate::error::ClientError;
+    use crate::fixtures::{SPOT, SPOT2};
+    use crate::types::Spot;
+
+    #[test]
+    fn mock_recording_parse() {
+        let text =
+            "1662847200.5 Hello DL1ABC\r\n1662847201.0 DX de X\n\nDX de Y\n1662847260.5 WWV de Z";
+        let recording = Recording::parse(text);
+        let exp = Recording::new()
+            .line(Duration::ZERO, "Hello DL1ABC")
+            .line(Duration::from_millis(500), "DX de X")
+            .line(Duration::from_millis(500), "DX de Y")
+            .line(Duration::from_secs(60), "WWV de Z");
+        assert_eq!(recording, exp);
+
+        // Lines without any timestamp are replayed at once
+        assert_eq!(
+            Recording::parse(SPOT),
+            Recording::new().line(Duration::ZERO, SPOT)
+        );
+    }
+
+    #[test]
+    fn mock_replay_accelerated() {
+        let recording = Recording::new()
+            .line(Duration::ZERO, SPOT)
+            .line(Duration::from_secs(60), SPOT2);
+        let cluster = MockCluster::builder()
+            .replay(recording)
+            .speed(300.0)
+            .bind("127.0.0.1:0")
+            .unwrap();
+
+        let mut client = ClusterClient::connect(cluster.addr(), "DL1ABC").unwrap();
+        let start = Instant::now();
+        assert_eq!(client.next(), Some(crate::parse(SPOT)));
+        assert_eq!(client.next(), Some(crate::parse(SPOT2)));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Each client gets its own replay
+        let mut client = ClusterClient::connect(cluster.addr(), "DK1XY").unwrap();
+        assert!(matches!(client.next(), Some(Ok(Spot::DX(_)))));
+    }
+
+    #[test]
+    fn mock_login_dialogue() {
+        let cluster = MockCluster::builder()
+            .node("DB0SUE-7")
+            .greeting("Welcome to the DX cluster of DB0SUE")
+            .login_prompt("Please enter your call: ")
+            .password("secret")
+            .replay(Recording::new().line(Duration::ZERO, SPOT))
+            .bind("127.0.0.1:0")
+            .unwrap();
+
+        let mut client = ClusterClient::builder("dl1abc")
+            .password("secret")
+            .connect(cluster.addr())
+            .unwrap();
+        assert_eq!(client.next(), Some(crate::parse(SPOT)));
+
+        let res = ClusterClient::builder("DK1XY")
+            .password("guess")
+            .connect(cluster.addr());
+        assert!(matches!(res, Err(ClientError::Closed)));
+    }
+
+    #[test]
+    fn mock_commands() {
+        let cluster = MockCluster::builder()
+            .command(
+                "sh/wwv",
+                "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms",
+            )
+            .command("SH/DX  20", &format!("{}\n{}", SPOT2, SPOT2))
+            .bind("127.0.0.1:0")
+            .unwrap();
+
+        let mut client = ClusterClient::builder("DL1ABC")
+            .read_timeout(Duration::from_secs(5))
+            .connect(cluster.addr())
+            .unwrap();
+        client.send("SH/WWV").unwrap();
+        assert!(matches!(client.next(), Some(Ok(Spot::WWV(_)))));
+        client.send("sh/dx 20").unwrap();
+        assert_eq!(client.next(), Some(crate::parse(SPOT2)));
+        assert_eq!(client.next(), Some(crate::parse(SPOT2)));
+
+        // The connection is closed after bye
+        client.send("bye").unwrap();
+        while client.read_line().unwrap().is_some() {}
+        assert_eq!(cluster.received(), vec!["SH/WWV", "sh/dx 20", "bye"]);
+    }
+
+    #[test]
+    fn mock_invalid_speed() {
+        let res = MockCluster::builder().speed(0.0).bind("127.0.0.1:0");
+        assert_eq!(res.err().map(|e| e.kind()), Some(ErrorKind::InvalidInput));
+    }
+}
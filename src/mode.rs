@@ -0,0 +1,229 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::types::DX;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Dial frequencies of FT8 (in kHz), the signals lie within 3 kHz above
+const FT8_FREQS: &[u64] = &[
+    1_840, 3_573, 5_357, 7_074, 10_136, 14_074, 18_100, 21_074, 24_915, 28_074, 50_313, 144_174,
+];
+
+/// Segments of the bands (in kHz, upper edge excluded) with the mode mainly used within, following the IARU Region 1 band plans
+const SEGMENTS: &[(u64, u64, Mode)] = &[
+    (1_800, 1_838, Mode::Cw),
+    (1_838, 1_843, Mode::Digital),
+    (1_843, 2_000, Mode::Ssb),
+    (3_500, 3_570, Mode::Cw),
+    (3_570, 3_600, Mode::Digital),
+    (3_600, 4_000, Mode::Ssb),
+    (5_250, 5_354, Mode::Cw),
+    (5_354, 5_450, Mode::Ssb),
+    (7_000, 7_040, Mode::Cw),
+    (7_040, 7_060, Mode::Digital),
+    (7_060, 7_300, Mode::Ssb),
+    (10_100, 10_130, Mode::Cw),
+    (10_130, 10_150, Mode::Digital),
+    (14_000, 14_070, Mode::Cw),
+    (14_070, 14_101, Mode::Digital),
+    (14_101, 14_350, Mode::Ssb),
+    (18_068, 18_095, Mode::Cw),
+    (18_095, 18_111, Mode::Digital),
+    (18_111, 18_168, Mode::Ssb),
+    (21_000, 21_070, Mode::Cw),
+    (21_070, 21_151, Mode::Digital),
+    (21_151, 21_450, Mode::Ssb),
+    (24_890, 24_915, Mode::Cw),
+    (24_915, 24_931, Mode::Digital),
+    (24_931, 24_990, Mode::Ssb),
+    (28_000, 28_070, Mode::Cw),
+    (28_070, 28_300, Mode::Digital),
+    (28_300, 29_500, Mode::Ssb),
+    (29_500, 29_700, Mode::Fm),
+    (50_000, 50_100, Mode::Cw),
+    (50_100, 50_500, Mode::Ssb),
+    (50_500, 54_000, Mode::Fm),
+    (144_000, 144_150, Mode::Cw),
+    (144_150, 144_400, Mode::Ssb),
+    (144_400, 148_000, Mode::Fm),
+    (430_000, 432_150, Mode::Cw),
+    (432_150, 432_500, Mode::Ssb),
+    (432_500, 440_000, Mode::Fm),
+];
+
+/// Mode of operation of a DX station
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Mode {
+    /// Morse code
+    Cw,
+
+    /// Single sideband
+    Ssb,
+
+    /// Amplitude modulation
+    Am,
+
+    /// Frequency modulation
+    Fm,
+
+    /// Radioteletype
+    Rtty,
+
+    /// PSK31 and similar
+    Psk,
+
+    /// FT8
+    Ft8,
+
+    /// FT4
+    Ft4,
+
+    /// Other digital modes, e.g. JS8 or Olivia
+    Digital,
+}
+
+impl Mode {
+    /// All modes
+    pub const ALL: [Mode; 9] = [
+        Mode::Cw,
+        Mode::Ssb,
+        Mode::Am,
+        Mode::Fm,
+        Mode::Rtty,
+        Mode::Psk,
+        Mode::Ft8,
+        Mode::Ft4,
+        Mode::Digital,
+    ];
+
+    /// Guess the mode of a DX spot.
+    ///
+    /// The mode is taken from the comment in case it names one, e.g. `CW` or `FT8 -12dB`.
+    /// Otherwise the mode mainly used at the frequency is taken from the band plan.
+    ///
+    /// ## Arguments
+    ///
+    /// * `dx`: Spot of the DX station
+    ///
+    /// ## Result
+    ///
+    /// The guessed mode shall be returned, `None` in case the frequency lies outside of the known bands and the comment names no mode.
+    pub fn of(dx: &DX) -> Option<Mode> {
+        let comment = dx.comment.as_deref().unwrap_or_default();
+        comment
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .find_map(Mode::from_word)
+            .or_else(|| Mode::at(dx.freq))
+    }
+
+    /// Get the mode mainly used at a frequency (in Hz) according to the band plan.
+    pub fn at(freq: u64) -> Option<Mode> {
+        let khz = freq / 1000;
+        if FT8_FREQS.iter().any(|f| (*f..*f + 3).contains(&khz)) {
+            return Some(Mode::Ft8);
+        }
+        SEGMENTS
+            .iter()
+            .find(|(low, high, _)| (*low..*high).contains(&khz))
+            .map(|(_, _, mode)| *mode)
+    }
+
+    /// Get the name of the mode, e.g. `CW`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Cw => "CW",
+            Mode::Ssb => "SSB",
+            Mode::Am => "AM",
+            Mode::Fm => "FM",
+            Mode::Rtty => "RTTY",
+            Mode::Psk => "PSK",
+            Mode::Ft8 => "FT8",
+            Mode::Ft4 => "FT4",
+            Mode::Digital => "DIGI",
+        }
+    }
+
+    /// Check whether the mode is a digital mode.
+    pub fn is_digital(&self) -> bool {
+        matches!(
+            self,
+            Mode::Rtty | Mode::Psk | Mode::Ft8 | Mode::Ft4 | Mode::Digital
+        )
+    }
+
+    /// Get the mode named by a word of a comment.
+    fn from_word(word: &str) -> Option<Mode> {
+        let mode = match word.to_ascii_uppercase().as_str() {
+            "CW" => Mode::Cw,
+            "SSB" | "USB" | "LSB" => Mode::Ssb,
+            "AM" => Mode::Am,
+            "FM" => Mode::Fm,
+            "RTTY" => Mode::Rtty,
+            "PSK" | "PSK31" | "PSK63" | "BPSK" | "BPSK31" => Mode::Psk,
+            "FT8" => Mode::Ft8,
+            "FT4" => Mode::Ft4,
+            "DIGI" | "DATA" | "JS8" | "JT65" | "JT9" | "MSK144" | "Q65" | "OLIVIA" | "SSTV" => {
+                Mode::Digital
+            }
+            _ => return None,
+        };
+        Some(mode)
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Mode {
+    type Err = ();
+
+    /// Parse the name of a mode, e.g. `CW`, `ssb` or `USB`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::from_word(s).ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn dx(khz: u64, comment: &str) -> DX {
+        DX {
+            freq: khz * 1000,
+            comment: Some(String::from(comment)),
+            ..DX::new()
+        }
+    }
+
+    #[test]
+    fn mode_of() {
+        assert_eq!(Mode::of(&dx(14025, "")), Some(Mode::Cw));
+        assert_eq!(Mode::of(&dx(14205, "up 5")), Some(Mode::Ssb));
+        assert_eq!(Mode::of(&dx(14075, "")), Some(Mode::Ft8));
+        assert_eq!(Mode::of(&dx(14080, "")), Some(Mode::Digital));
+        assert_eq!(Mode::of(&dx(14025, "RTTY up 1")), Some(Mode::Rtty));
+        assert_eq!(Mode::of(&dx(7074, "ft4 -12dB")), Some(Mode::Ft4));
+        assert_eq!(Mode::of(&dx(3780, "LSB")), Some(Mode::Ssb));
+        assert_eq!(Mode::of(&dx(144300, "")), Some(Mode::Ssb));
+        assert_eq!(Mode::of(&dx(15000, "")), None);
+        assert_eq!(Mode::of(&dx(15000, "AM broadcast")), Some(Mode::Am));
+    }
+
+    #[test]
+    fn mode_names() {
+        assert_eq!("cw".parse(), Ok(Mode::Cw));
+        assert_eq!("USB".parse(), Ok(Mode::Ssb));
+        assert_eq!("digi".parse(), Ok(Mode::Digital));
+        assert_eq!("morse".parse::<Mode>(), Err(()));
+        assert_eq!(Mode::Ft8.to_string(), "FT8");
+        assert!(Mode::Ft8.is_digital());
+        assert!(!Mode::Cw.is_digital());
+    }
+}
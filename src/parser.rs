@@ -2,12 +2,19 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::borrowed::*;
+use crate::columns::ColumnLayout;
+use crate::custom::{DynSpotParser, RegexSpotParser, SpotParser};
+use crate::definition::{DefinitionParser, Definitions};
+use crate::encoding::{DecodedSpot, Detection};
+use crate::error::{BuildError, ParseError, ParseErrorKind};
+use crate::tokenizer::{self, Engine};
 use crate::types::*;
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
-use thiserror::Error;
+use std::borrow::Cow;
 
-const REGEX_PATTERN_DX: &str = r#"^DX de +([A-Z0-9/\-#]{3,}):? *(\d*.\d{1,2}) *([A-Z0-9/\-#]{3,}) +(.*\S)? +(\d{4}){1}Z *(\w{2}\d{2})?$"#;
+const REGEX_PATTERN_DX: &str = r#"^DX de +([A-Z0-9/\-#]{3,})[: ] *(\d*.\d{1,2}) *([A-Z0-9/\-#]{3,}) +(.*\S)? +(\d{4}){1}Z *(\w{2}\d{2})?$"#;
 
 enum RegexDxCaptureIds {
     CallDe = 1,
@@ -18,6 +25,33 @@ enum RegexDxCaptureIds {
     Loc = 6,
 }
 
+/// Steps to locate the first failing field of a DX spot, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_DX`.
+const DIAGNOSIS_DX: &[(&str, &str, &str)] = &[
+    (
+        "call_de",
+        r#"DX de +[A-Z0-9/\-#]{3,}"#,
+        "callsign of the spotter",
+    ),
+    (
+        "freq",
+        r#"[: ] *\d*.\d{1,2}"#,
+        "frequency in kHz, e.g. 14025.0",
+    ),
+    (
+        "call_dx",
+        r#" *[A-Z0-9/\-#]{3,}"#,
+        "callsign of the DX station",
+    ),
+    ("comment", r#" +(?:.*\S)?"#, "comment"),
+    ("utc", r#" +\d{4}Z"#, "time as HHMM followed by Z"),
+    (
+        "loc",
+        r#" *(?:\w{2}\d{2})?$"#,
+        "locator, e.g. JO62, at the end of the line",
+    ),
+];
+
 const REGEX_PATTERN_RBN1: &str =
     r#"^([a-zA-z0-9]{2,}) +([0-9\-]{1,4}) +dB +([0-9]{1,3}) +((?:WPM|BPS)) +([a-zA-Z ]+)$"#;
 
@@ -29,6 +63,28 @@ enum RegexRbn1CaptureIds {
     Info = 5,
 }
 
+/// Steps to locate the first failing field of the comment of a RBN spot with speed information, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_RBN1`.
+const DIAGNOSIS_RBN1: &[(&str, &str, &str)] = &[
+    ("mode", r#"[a-zA-z0-9]{2,}"#, "mode, e.g. CW"),
+    (
+        "db",
+        r#" +[0-9\-]{1,4} +dB"#,
+        "signal strength followed by dB",
+    ),
+    ("speed", r#" +[0-9]{1,3}"#, "speed"),
+    (
+        "speed_unit",
+        r#" +(?:WPM|BPS)"#,
+        "unit of speed, either WPM or BPS",
+    ),
+    (
+        "info",
+        r#" +[a-zA-Z ]+$"#,
+        "additional information, e.g. CQ",
+    ),
+];
+
 const REGEX_PATTERN_RBN2: &str =
     r#"^([a-zA-z0-9]{2,}) +([0-9\-]{1,4}) +dB +([A-Z]{2}[0-9]{2})? +([a-zA-Z ]+)$"#;
 
@@ -39,6 +95,23 @@ enum RegexRbn2CaptureIds {
     Info = 4,
 }
 
+/// Steps to locate the first failing field of the comment of a RBN spot with locator, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_RBN2`.
+const DIAGNOSIS_RBN2: &[(&str, &str, &str)] = &[
+    ("mode", r#"[a-zA-z0-9]{2,}"#, "mode, e.g. FT8"),
+    (
+        "db",
+        r#" +[0-9\-]{1,4} +dB"#,
+        "signal strength followed by dB",
+    ),
+    ("loc", r#" +(?:[A-Z]{2}[0-9]{2})?"#, "locator, e.g. FK68"),
+    (
+        "info",
+        r#" +[a-zA-Z ]+$"#,
+        "additional information, e.g. CQ",
+    ),
+];
+
 const REGEX_PATTERN_WWV: &str = r#"^WWV de +([A-Z0-9/\-#]*) +<(\d{2})Z?> *: *SFI=(\d{1,3}), A=(\d{1,3}), K=(\d{1,3}), (.*\b) *-> *(.*\b) *$"#;
 
 enum RegexWwvCaptureIds {
@@ -51,6 +124,26 @@ enum RegexWwvCaptureIds {
     Info2 = 7,
 }
 
+/// Steps to locate the first failing field of a WWV spot, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_WWV`.
+const DIAGNOSIS_WWV: &[(&str, &str, &str)] = &[
+    (
+        "call_de",
+        r#"WWV de +[A-Z0-9/\-#]*"#,
+        "callsign of the spotter",
+    ),
+    ("utc", r#" +<\d{2}Z?>"#, "hour in angle brackets, e.g. <21>"),
+    (
+        "sfi",
+        r#" *: *SFI=\d{1,3}"#,
+        "colon followed by SFI=<index>",
+    ),
+    ("a", r#", A=\d{1,3}"#, "A=<index>"),
+    ("k", r#", K=\d{1,3}"#, "K=<index>"),
+    ("info1", r#", .*\b *->"#, "information followed by ->"),
+    ("info2", r#" *.*\b *$"#, "information"),
+];
+
 const REGEX_PATTERN_WCY: &str = r#"^WCY de +([A-Z0-9/\-#]*) +<(\d{2})> *: +K=(\d{1,3}) expK=(\d{1,3}) A=(\d{1,3}) R=(\d{1,3}) SFI=(\d{1,3}) SA=([a-zA-Z]{1,3}) GMF=([a-zA-Z]{1,3}) Au=([a-zA-Z]{2,6}) *$"#;
 
 enum RegexWcyCaptureIds {
@@ -66,6 +159,29 @@ enum RegexWcyCaptureIds {
     Au = 10,
 }
 
+/// Steps to locate the first failing field of a WCY spot, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_WCY`.
+const DIAGNOSIS_WCY: &[(&str, &str, &str)] = &[
+    (
+        "call_de",
+        r#"WCY de +[A-Z0-9/\-#]*"#,
+        "callsign of the spotter",
+    ),
+    ("utc", r#" +<\d{2}>"#, "hour in angle brackets, e.g. <22>"),
+    ("k", r#" *: +K=\d{1,3}"#, "colon followed by K=<index>"),
+    ("expk", r#" expK=\d{1,3}"#, "expK=<index>"),
+    ("a", r#" A=\d{1,3}"#, "A=<index>"),
+    ("r", r#" R=\d{1,3}"#, "R=<index>"),
+    ("sfi", r#" SFI=\d{1,3}"#, "SFI=<index>"),
+    ("sa", r#" SA=[a-zA-Z]{1,3}"#, "SA=<level>"),
+    ("gmf", r#" GMF=[a-zA-Z]{1,3}"#, "GMF=<level>"),
+    (
+        "au",
+        r#" Au=[a-zA-Z]{2,6} *$"#,
+        "Au=<level> at the end of the line",
+    ),
+];
+
 const REGEX_PATTERN_WX: &str = r#"^WX de +([A-Z0-9/\-#]*)\s?(?:<(\d{4})Z>)?[ :]+(.*)?$"#;
 
 enum RegexWxCaptureIds {
@@ -74,6 +190,22 @@ enum RegexWxCaptureIds {
     Msg = 3,
 }
 
+/// Steps to locate the first failing field of a WX spot, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_WX`.
+const DIAGNOSIS_WX: &[(&str, &str, &str)] = &[
+    (
+        "call_de",
+        r#"WX de +[A-Z0-9/\-#]*"#,
+        "callsign of the spotter",
+    ),
+    (
+        "utc",
+        r#"\s?(?:<\d{4}Z>)?"#,
+        "time in angle brackets, e.g. <1001Z>",
+    ),
+    ("msg", r#"[ :]+(?:.*)?$"#, "colon followed by the message"),
+];
+
 const REGEX_PATTERN_TOALL: &str = r#"^To ALL de +([A-Z0-9/\-#]*)\s?(?:<(\d{4})Z>)?[ :]+(.*)?$"#;
 
 enum RegexToAllCaptureIds {
@@ -82,6 +214,22 @@ enum RegexToAllCaptureIds {
     Msg = 3,
 }
 
+/// Steps to locate the first failing field of a ToAll spot, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_TOALL`.
+const DIAGNOSIS_TOALL: &[(&str, &str, &str)] = &[
+    (
+        "call_de",
+        r#"To ALL de +[A-Z0-9/\-#]*"#,
+        "callsign of the spotter",
+    ),
+    (
+        "utc",
+        r#"\s?(?:<\d{4}Z>)?"#,
+        "time in angle brackets, e.g. <1044Z>",
+    ),
+    ("msg", r#"[ :]+(?:.*)?$"#, "colon followed by the message"),
+];
+
 const REGEX_PATTERN_TOLOCAL: &str =
     r#"^To (?:LOCAL|Local) de +([A-Z0-9/\-#]*)(?: +<(\d{4})Z>)?[ :]+(.*)?$"#;
 
@@ -91,24 +239,30 @@ enum RegexToLocalCaptureIds {
     Msg = 3,
 }
 
-/// Possible errors while parsing spot
-#[derive(Error, Debug, PartialEq, Eq)]
-pub enum ParseError {
-    #[error("Unknown type of spot")]
-    UnknownType,
-
-    #[error("The content of the spot does not match the detected type")]
-    InvalidContent,
-
-    #[error("Required field of the spot is missing")]
-    MissingField,
-
-    #[error("Internal error occurred while parsing")]
-    InternalError,
+/// Steps to locate the first failing field of a ToLocal spot, each given as field, pattern and expected shape.
+/// Concatenated, the patterns correspond to `REGEX_PATTERN_TOLOCAL`.
+const DIAGNOSIS_TOLOCAL: &[(&str, &str, &str)] = &[
+    (
+        "call_de",
+        r#"To (?:LOCAL|Local) de +[A-Z0-9/\-#]*"#,
+        "callsign of the spotter",
+    ),
+    (
+        "utc",
+        r#"(?: +<\d{4}Z>)?"#,
+        "time in angle brackets, e.g. <1405Z>",
+    ),
+    ("msg", r#"[ :]+(?:.*)?$"#, "colon followed by the message"),
+];
+
+lazy_static! {
+    static ref DEFAULT_PARSER: Parser = Parser::new();
 }
 
 /// Parse a spot received from a DX Cluster into a struct.
 ///
+/// Uses a parser with the default configuration, see [`Parser`] for a configurable one.
+///
 /// ## Arguments
 ///
 /// * `raw`: A raw spot that is already cleaned from newline or bell characters etc.
@@ -118,18 +272,13 @@ pub enum ParseError {
 /// In case the spot was parsed successfully, the structure containing the spot shall be returned.
 /// In case of an error the occurred error shall be returned.
 pub fn parse(raw: &str) -> Result<Spot, ParseError> {
-    match ident_type(raw)? {
-        Spot::DX(dx) => parse_dx(raw, dx),
-        Spot::WWV(wwv) => parse_wwv(raw, wwv),
-        Spot::WCY(wcy) => parse_wcy(raw, wcy),
-        Spot::WX(wx) => parse_wx(raw, wx),
-        Spot::ToAll(ta) => parse_toall(raw, ta),
-        Spot::ToLocal(tl) => parse_tolocal(raw, tl),
-    }
+    DEFAULT_PARSER.parse(raw)
 }
 
 /// Parse the comment section of a RBN spot into a struct.
 ///
+/// Uses a parser with the default configuration, see [`Parser`] for a configurable one.
+///
 /// ## Arguments
 ///
 /// * `raw`: A raw comment section of a parsed DX spot. A RBN spot is basically a DX spot with additional data in the comment section)
@@ -139,209 +288,791 @@ pub fn parse(raw: &str) -> Result<Spot, ParseError> {
 /// In case the comment was parsed successfully, the structure containing the information shall be returned.
 /// In case of an error the occurred error shall be returned.
 pub fn parse_rbn(raw: &str) -> Result<RBN, ParseError> {
-    lazy_static! {
-        static ref RE_RBN1: Regex = Regex::new(REGEX_PATTERN_RBN1).unwrap();
-        static ref RE_RBN2: Regex = Regex::new(REGEX_PATTERN_RBN2).unwrap();
+    DEFAULT_PARSER.parse_rbn(raw)
+}
+
+/// Parse a spot received from a DX Cluster as raw bytes into a struct.
+///
+/// Uses a parser with the default configuration, see [`Parser`] for a configurable one.
+///
+/// ## Arguments
+///
+/// * `raw`: Raw bytes of a spot that are already cleaned from newline or bell characters etc.
+///
+/// ## Result
+///
+/// In case the spot was parsed successfully, the structure containing the spot together with the used encoding shall be returned.
+/// In case of an error the occurred error shall be returned.
+pub fn parse_bytes(raw: &[u8]) -> Result<DecodedSpot, ParseError> {
+    DEFAULT_PARSER.parse_bytes(raw)
+}
+
+/// Configurable parser for spots received from a DX Cluster.
+///
+/// A parser holds its compiled regular expressions and can be shared between threads.
+/// Use [`ParserBuilder`] to create a parser with a non-default configuration.
+pub struct Parser {
+    strict: bool,
+    types: Vec<SpotType>,
+    flavor: ClusterFlavor,
+    date: Option<Date>,
+    engine: Engine,
+    detection: Detection,
+    custom: Vec<Box<dyn DynSpotParser>>,
+    re_dx: Regex,
+    re_rbn1: Regex,
+    re_rbn2: Regex,
+    re_wwv: Regex,
+    re_wcy: Regex,
+    re_wx: Regex,
+    re_toall: Regex,
+    re_tolocal: Regex,
+    diagnosis: Diagnosis,
+}
+
+impl Parser {
+    /// Create a parser with the default configuration.
+    ///
+    /// The parser is lenient, has all built-in types of spots enabled and no custom types registered.
+    pub fn new() -> Self {
+        Parser {
+            strict: false,
+            types: SpotType::ALL.to_vec(),
+            flavor: ClusterFlavor::Unknown,
+            date: None,
+            engine: Engine::Tokenizer,
+            detection: Detection::Auto,
+            custom: Vec::new(),
+            re_dx: Regex::new(REGEX_PATTERN_DX).unwrap(),
+            re_rbn1: Regex::new(REGEX_PATTERN_RBN1).unwrap(),
+            re_rbn2: Regex::new(REGEX_PATTERN_RBN2).unwrap(),
+            re_wwv: Regex::new(REGEX_PATTERN_WWV).unwrap(),
+            re_wcy: Regex::new(REGEX_PATTERN_WCY).unwrap(),
+            re_wx: Regex::new(REGEX_PATTERN_WX).unwrap(),
+            re_toall: Regex::new(REGEX_PATTERN_TOALL).unwrap(),
+            re_tolocal: Regex::new(REGEX_PATTERN_TOLOCAL).unwrap(),
+            diagnosis: Diagnosis::new(),
+        }
     }
 
-    if let Some(c) = RE_RBN1.captures(raw) {
-        let mut rbn = RBN::new();
+    /// Create a builder to configure a new parser.
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::new()
+    }
 
-        rbn.mode = check_existence_str(&c, RegexRbn1CaptureIds::Mode as u32)?;
-        rbn.db = check_existence_num(&c, RegexRbn1CaptureIds::Db as u32)?;
-        rbn.speed = check_existence_num_opt(&c, RegexRbn1CaptureIds::Speed as u32)?;
-        rbn.speed_unit = check_existence_str_opt(&c, RegexRbn1CaptureIds::SpeedUnit as u32);
-        rbn.info = check_existence_str(&c, RegexRbn1CaptureIds::Info as u32)?;
+    /// Check whether the parser validates the values of the parsed fields.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
 
-        return Ok(rbn);
+    /// Check whether a built-in type of spot is enabled.
+    pub fn is_enabled(&self, spot_type: SpotType) -> bool {
+        self.types.contains(&spot_type)
     }
 
-    if let Some(c) = RE_RBN2.captures(raw) {
-        let mut rbn = RBN::new();
+    /// Get the hint about the cluster software the spots originate from.
+    pub fn flavor(&self) -> ClusterFlavor {
+        self.flavor
+    }
 
-        rbn.mode = check_existence_str(&c, RegexRbn2CaptureIds::Mode as u32)?;
-        rbn.db = check_existence_num(&c, RegexRbn2CaptureIds::Db as u32)?;
-        rbn.info = check_existence_str(&c, RegexRbn2CaptureIds::Info as u32)?;
-        rbn.loc = check_existence_str_opt(&c, RegexRbn2CaptureIds::Loc as u32);
+    /// Get the date the spots are put into context with.
+    pub fn date(&self) -> Option<Date> {
+        self.date
+    }
 
-        return Ok(rbn);
+    /// Get the engine used to parse the built-in types of spots.
+    pub fn engine(&self) -> Engine {
+        self.engine
     }
 
-    Err(ParseError::InvalidContent)
-}
+    /// Get the detection of the encoding of raw bytes, see [`Parser::parse_bytes`].
+    pub fn detection(&self) -> Detection {
+        self.detection
+    }
 
-fn ident_type(input: &str) -> Result<Spot, ParseError> {
-    if input.starts_with("DX de") {
-        Ok(Spot::DX(DX::new()))
-    } else if input.starts_with("WWV de") {
-        Ok(Spot::WWV(WWV::new()))
-    } else if input.starts_with("WCY de") {
-        Ok(Spot::WCY(WCY::new()))
-    } else if input.starts_with("WX de") {
-        Ok(Spot::WX(WX::new()))
-    } else if input.starts_with("To ALL de") {
-        Ok(Spot::ToAll(ToAll::new()))
-    } else if input.starts_with("To LOCAL de") || input.starts_with("To Local de") {
-        Ok(Spot::ToLocal(ToLocal::new()))
-    } else {
-        Err(ParseError::UnknownType)
+    /// Parse a spot received from a DX Cluster into a struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw spot that is already cleaned from newline or bell characters etc.
+    ///
+    /// ## Result
+    ///
+    /// In case the spot was parsed successfully, the structure containing the spot shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn parse(&self, raw: &str) -> Result<Spot, ParseError> {
+        self.parse_ref(raw).map(SpotRef::into_owned)
     }
-}
 
-fn parse_dx(raw: &str, mut dx: DX) -> Result<Spot, ParseError> {
-    lazy_static! {
-        static ref RE_DX: Regex = Regex::new(REGEX_PATTERN_DX).unwrap();
+    /// Parse a spot received from a DX Cluster into a struct borrowing its text fields from the raw spot.
+    ///
+    /// Other than [`Parser::parse`] no text field is copied, use [`SpotRef::into_owned`] to get a [`Spot`] later on.
+    /// Spots of custom types and spots parsed by the columns of their line are always owned.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw spot that is already cleaned from newline or bell characters etc.
+    ///
+    /// ## Result
+    ///
+    /// In case the spot was parsed successfully, the structure containing the spot shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn parse_ref<'a>(&self, raw: &'a str) -> Result<SpotRef<'a>, ParseError> {
+        let spot = match self.custom.iter().find(|c| c.recognize(raw)) {
+            Some(custom) => SpotRef::from(custom.parse_spot(raw)?),
+            None => self.check_loc(raw, self.parse_builtin(raw)?)?,
+        };
+
+        if self.strict {
+            validate(&spot).map_err(|e| match spot.spot_type() {
+                Some(t) => e.with_spot_type(t),
+                None => e,
+            })?;
+            if let (ClusterFlavor::RBN, SpotRef::DX(dx)) = (self.flavor, &spot) {
+                self.validate_rbn(raw, dx.comment.as_deref())
+                    .map_err(|e| e.with_spot_type(SpotType::DX))?;
+            }
+        }
+
+        Ok(spot)
+    }
+
+    /// Parse the comment section of a RBN spot into a struct.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw comment section of a parsed DX spot. A RBN spot is basically a DX spot with additional data in the comment section)
+    ///
+    /// ## Result
+    ///
+    /// In case the comment was parsed successfully, the structure containing the information shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn parse_rbn(&self, raw: &str) -> Result<RBN, ParseError> {
+        self.parse_rbn_ref(raw).map(RBNRef::into_owned)
+    }
+
+    /// Parse the comment section of a RBN spot into a struct borrowing its text fields from the raw comment.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw comment section of a parsed DX spot.
+    ///
+    /// ## Result
+    ///
+    /// In case the comment was parsed successfully, the structure containing the information shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn parse_rbn_ref<'a>(&self, raw: &'a str) -> Result<RBNRef<'a>, ParseError> {
+        if self.engine == Engine::Tokenizer {
+            if let Some(rbn) = tokenizer::scan_rbn(raw) {
+                if self.strict {
+                    validate_loc(rbn.loc.as_deref())?;
+                }
+                return Ok(rbn);
+            }
+        }
+
+        if let Some(c) = self.re_rbn1.captures(raw) {
+            let mut rbn = RBNRef::new();
+
+            rbn.mode = check_existence_str(&c, RegexRbn1CaptureIds::Mode as u32, "mode")?;
+            rbn.db = check_existence_num(&c, RegexRbn1CaptureIds::Db as u32, "db")?;
+            rbn.speed = check_existence_num_opt(&c, RegexRbn1CaptureIds::Speed as u32, "speed")?;
+            rbn.speed_unit = check_existence_str_opt(&c, RegexRbn1CaptureIds::SpeedUnit as u32);
+            rbn.info = check_existence_str(&c, RegexRbn1CaptureIds::Info as u32, "info")?;
+
+            return Ok(rbn);
+        }
+
+        if let Some(c) = self.re_rbn2.captures(raw) {
+            let mut rbn = RBNRef::new();
+
+            rbn.mode = check_existence_str(&c, RegexRbn2CaptureIds::Mode as u32, "mode")?;
+            rbn.db = check_existence_num(&c, RegexRbn2CaptureIds::Db as u32, "db")?;
+            rbn.info = check_existence_str(&c, RegexRbn2CaptureIds::Info as u32, "info")?;
+            rbn.loc = check_existence_str_opt(&c, RegexRbn2CaptureIds::Loc as u32);
+
+            if self.strict {
+                validate_loc(rbn.loc.as_deref())?;
+            }
+
+            return Ok(rbn);
+        }
+
+        let err1 = diagnose(&self.diagnosis.rbn1, raw);
+        let err2 = diagnose(&self.diagnosis.rbn2, raw);
+        if err2.span().map(|s| s.start) > err1.span().map(|s| s.start) {
+            Err(err2)
+        } else {
+            Err(err1)
+        }
+    }
+
+    /// Get the seconds since the unix epoch for the time of a spot.
+    ///
+    /// The time of the spot is put into context with the date configured for the parser.
+    /// In case no date is configured or the spot has no time information, `None` shall be returned.
+    pub fn timestamp(&self, spot: &Spot) -> Option<u64> {
+        let utc = match spot {
+            Spot::DX(dx) => Some(dx.utc),
+            Spot::WWV(wwv) => Some(u16::from(wwv.utc) * 100),
+            Spot::WCY(wcy) => Some(u16::from(wcy.utc) * 100),
+            Spot::WX(wx) => wx.utc,
+            Spot::ToAll(ta) => ta.utc,
+            Spot::ToLocal(tl) => tl.utc,
+            Spot::Custom(_) => None,
+        }?;
+
+        self.date.map(|d| d.timestamp(utc))
     }
 
-    match RE_DX.captures(raw) {
-        Some(c) => {
-            dx.call_de = check_existence_str(&c, RegexDxCaptureIds::CallDe as u32)?;
-            dx.call_dx = check_existence_str(&c, RegexDxCaptureIds::CallDx as u32)?;
-            dx.freq =
-                (check_existence_num::<f64>(&c, RegexDxCaptureIds::Freq as u32)? * 1000.0) as u64;
-            dx.utc = check_existence_num(&c, RegexDxCaptureIds::Utc as u32)?;
-            dx.loc = check_existence_str_opt(&c, RegexDxCaptureIds::Loc as u32);
-            dx.comment = check_existence_str_opt(&c, RegexDxCaptureIds::Comment as u32);
+    /// Validate the comment of a DX spot received from the Reverse Beacon Network.
+    pub(crate) fn validate_rbn(&self, raw: &str, comment: Option<&str>) -> Result<(), ParseError> {
+        let comment = match comment {
+            Some(comment) => comment,
+            None => {
+                return Err(ParseError::new(ParseErrorKind::MissingField)
+                    .with_field("comment")
+                    .with_expected("comment of a RBN spot, e.g. CW 12 dB 22 WPM CQ"))
+            }
+        };
+
+        // Positions within the comment are shifted to positions within the line
+        let offset = raw.find(comment).unwrap_or(0);
+        self.parse_rbn_ref(comment)
+            .map(|_| ())
+            .map_err(|e| e.with_offset(offset))
+    }
 
-            Ok(Spot::DX(dx))
+    fn parse_builtin<'a>(&self, raw: &'a str) -> Result<SpotRef<'a>, ParseError> {
+        // Lines the tokenizer can not handle as well as disabled types take the path via the regular expressions
+        if self.engine == Engine::Tokenizer {
+            if let Some(mut spot) = tokenizer::scan(raw) {
+                if spot.spot_type().is_some_and(|t| self.is_enabled(t)) {
+                    if let SpotRef::DX(dx) = &mut spot {
+                        dx.truncated = self.truncates(raw, dx);
+                    }
+                    return Ok(spot);
+                }
+            }
         }
-        None => Err(ParseError::InvalidContent),
+
+        let spot = self.ident_type(raw)?;
+        let spot_type = spot.spot_type();
+
+        match spot {
+            SpotRef::DX(dx) => self.parse_dx(raw, dx),
+            SpotRef::WWV(wwv) => self.parse_wwv(raw, wwv),
+            SpotRef::WCY(wcy) => self.parse_wcy(raw, wcy),
+            SpotRef::WX(wx) => self.parse_wx(raw, wx),
+            SpotRef::ToAll(ta) => self.parse_toall(raw, ta),
+            SpotRef::ToLocal(tl) => self.parse_tolocal(raw, tl),
+            SpotRef::Custom(_) => Err(ParseErrorKind::UnknownType.into()),
+        }
+        .map_err(|e| match spot_type {
+            Some(t) => e.with_spot_type(t),
+            None => e,
+        })
     }
-}
 
-fn parse_wwv(raw: &str, mut wwv: WWV) -> Result<Spot, ParseError> {
-    lazy_static! {
-        static ref RE_WWV: Regex = Regex::new(REGEX_PATTERN_WWV).unwrap();
+    fn ident_type<'a>(&self, input: &str) -> Result<SpotRef<'a>, ParseError> {
+        let spot = if input.starts_with("DX de") {
+            SpotRef::DX(DXRef::new())
+        } else if input.starts_with("WWV de") {
+            SpotRef::WWV(WWVRef::new())
+        } else if input.starts_with("WCY de") {
+            SpotRef::WCY(WCYRef::new())
+        } else if input.starts_with("WX de") {
+            SpotRef::WX(WXRef::new())
+        } else if input.starts_with("To ALL de") {
+            SpotRef::ToAll(ToAllRef::new())
+        } else if input.starts_with("To LOCAL de") || input.starts_with("To Local de") {
+            SpotRef::ToLocal(ToLocalRef::new())
+        } else {
+            let end = input.find(char::is_whitespace).unwrap_or(input.len());
+            return Err(ParseError::new(ParseErrorKind::UnknownType)
+                .with_span(0..end, &input[..end])
+                .with_expected("one of DX, WWV, WCY, WX, To ALL or To LOCAL"));
+        };
+
+        match spot.spot_type() {
+            Some(t) if !self.is_enabled(t) => Err(ParseError::new(ParseErrorKind::UnknownType)
+                .with_spot_type(t)
+                .with_expected("enabled type of spot")),
+            _ => Ok(spot),
+        }
     }
 
-    match RE_WWV.captures(raw) {
-        Some(c) => {
-            wwv.call_de = check_existence_str(&c, RegexWwvCaptureIds::CallDe as u32)?;
-            wwv.utc = check_existence_num(&c, RegexWwvCaptureIds::Utc as u32)?;
-            wwv.sfi = check_existence_num(&c, RegexWwvCaptureIds::Sfi as u32)?;
-            wwv.a = check_existence_num(&c, RegexWwvCaptureIds::A as u32)?;
-            wwv.k = check_existence_num(&c, RegexWwvCaptureIds::K as u32)?;
-            wwv.info1 = check_existence_str(&c, RegexWwvCaptureIds::Info1 as u32)?;
-            wwv.info2 = check_existence_str(&c, RegexWwvCaptureIds::Info2 as u32)?;
+    /// Reject the locator of a DX spot in case the configured cluster software appends none, see [`ColumnLayout::of`].
+    fn check_loc<'a>(&self, raw: &str, spot: SpotRef<'a>) -> Result<SpotRef<'a>, ParseError> {
+        let loc = match &spot {
+            SpotRef::DX(dx) if !ColumnLayout::of(self.flavor).loc => dx.loc.as_deref(),
+            _ => None,
+        };
+        match loc {
+            Some(loc) => {
+                let pos = raw.rfind(loc).unwrap_or(0);
+                Err(ParseError::new(ParseErrorKind::InvalidContent)
+                    .with_spot_type(SpotType::DX)
+                    .with_field("loc")
+                    .with_span(pos..pos + loc.len(), loc)
+                    .with_expected("end of the line after the time"))
+            }
+            None => Ok(spot),
+        }
+    }
 
-            Ok(Spot::WWV(wwv))
+    /// Check whether the comment of a DX spot was probably truncated by the cluster, see [`ColumnLayout`].
+    fn truncates(&self, raw: &str, dx: &DXRef) -> bool {
+        dx.comment.is_some() && ColumnLayout::of(self.flavor).truncates(raw)
+    }
+
+    fn parse_dx<'a>(&self, raw: &'a str, mut dx: DXRef<'a>) -> Result<SpotRef<'a>, ParseError> {
+        match self.re_dx.captures(raw) {
+            Some(c) => {
+                dx.call_de = check_existence_str(&c, RegexDxCaptureIds::CallDe as u32, "call_de")?;
+                dx.call_dx = check_existence_str(&c, RegexDxCaptureIds::CallDx as u32, "call_dx")?;
+                dx.freq = (check_existence_num::<f64>(&c, RegexDxCaptureIds::Freq as u32, "freq")?
+                    * 1000.0)
+                    .round() as u64;
+                dx.utc = check_existence_num(&c, RegexDxCaptureIds::Utc as u32, "utc")?;
+                dx.loc = check_existence_str_opt(&c, RegexDxCaptureIds::Loc as u32);
+                dx.comment = check_existence_str_opt(&c, RegexDxCaptureIds::Comment as u32);
+                dx.truncated = self.truncates(raw, &dx);
+
+                Ok(SpotRef::DX(dx))
+            }
+            None => match self.parse_columns(raw) {
+                Ok(dx) => Ok(SpotRef::from(Spot::DX(dx))),
+                Err(_) => Err(diagnose(&self.diagnosis.dx, raw)),
+            },
+        }
+    }
+
+    fn parse_wwv<'a>(&self, raw: &'a str, mut wwv: WWVRef<'a>) -> Result<SpotRef<'a>, ParseError> {
+        match self.re_wwv.captures(raw) {
+            Some(c) => {
+                wwv.call_de =
+                    check_existence_str(&c, RegexWwvCaptureIds::CallDe as u32, "call_de")?;
+                wwv.utc = check_existence_num(&c, RegexWwvCaptureIds::Utc as u32, "utc")?;
+                wwv.sfi = check_existence_num(&c, RegexWwvCaptureIds::Sfi as u32, "sfi")?;
+                wwv.a = check_existence_num(&c, RegexWwvCaptureIds::A as u32, "a")?;
+                wwv.k = check_existence_num(&c, RegexWwvCaptureIds::K as u32, "k")?;
+                wwv.info1 = check_existence_str(&c, RegexWwvCaptureIds::Info1 as u32, "info1")?;
+                wwv.info2 = check_existence_str(&c, RegexWwvCaptureIds::Info2 as u32, "info2")?;
+
+                Ok(SpotRef::WWV(wwv))
+            }
+            None => Err(diagnose(&self.diagnosis.wwv, raw)),
+        }
+    }
+
+    fn parse_wcy<'a>(&self, raw: &'a str, mut wcy: WCYRef<'a>) -> Result<SpotRef<'a>, ParseError> {
+        match self.re_wcy.captures(raw) {
+            Some(c) => {
+                wcy.call_de =
+                    check_existence_str(&c, RegexWcyCaptureIds::CallDe as u32, "call_de")?;
+                wcy.utc = check_existence_num(&c, RegexWcyCaptureIds::Utc as u32, "utc")?;
+                wcy.k = check_existence_num(&c, RegexWcyCaptureIds::K as u32, "k")?;
+                wcy.expk = check_existence_num(&c, RegexWcyCaptureIds::Expk as u32, "expk")?;
+                wcy.a = check_existence_num(&c, RegexWcyCaptureIds::A as u32, "a")?;
+                wcy.r = check_existence_num(&c, RegexWcyCaptureIds::R as u32, "r")?;
+                wcy.sfi = check_existence_num(&c, RegexWcyCaptureIds::Sfi as u32, "sfi")?;
+                wcy.sa = check_existence_str(&c, RegexWcyCaptureIds::Sa as u32, "sa")?;
+                wcy.gmf = check_existence_str(&c, RegexWcyCaptureIds::Gmf as u32, "gmf")?;
+                wcy.au = check_existence_str(&c, RegexWcyCaptureIds::Au as u32, "au")?;
+
+                Ok(SpotRef::WCY(wcy))
+            }
+            None => Err(diagnose(&self.diagnosis.wcy, raw)),
+        }
+    }
+
+    fn parse_wx<'a>(&self, raw: &'a str, mut wx: WXRef<'a>) -> Result<SpotRef<'a>, ParseError> {
+        match self.re_wx.captures(raw) {
+            Some(c) => {
+                wx.call_de = check_existence_str(&c, RegexWxCaptureIds::CallDe as u32, "call_de")?;
+                wx.utc = check_existence_num_opt(&c, RegexWxCaptureIds::Utc as u32, "utc")?;
+                wx.msg = check_existence_str_opt(&c, RegexWxCaptureIds::Msg as u32);
+
+                Ok(SpotRef::WX(wx))
+            }
+            None => Err(diagnose(&self.diagnosis.wx, raw)),
         }
-        None => Err(ParseError::InvalidContent),
     }
+
+    fn parse_toall<'a>(
+        &self,
+        raw: &'a str,
+        mut ta: ToAllRef<'a>,
+    ) -> Result<SpotRef<'a>, ParseError> {
+        match self.re_toall.captures(raw) {
+            Some(c) => {
+                ta.call_de =
+                    check_existence_str(&c, RegexToAllCaptureIds::CallDe as u32, "call_de")?;
+                ta.utc = check_existence_num_opt(&c, RegexToAllCaptureIds::Utc as u32, "utc")?;
+                ta.msg = check_existence_str_opt(&c, RegexToAllCaptureIds::Msg as u32);
+
+                Ok(SpotRef::ToAll(ta))
+            }
+            None => Err(diagnose(&self.diagnosis.toall, raw)),
+        }
+    }
+
+    fn parse_tolocal<'a>(
+        &self,
+        raw: &'a str,
+        mut tl: ToLocalRef<'a>,
+    ) -> Result<SpotRef<'a>, ParseError> {
+        match self.re_tolocal.captures(raw) {
+            Some(c) => {
+                tl.call_de =
+                    check_existence_str(&c, RegexToLocalCaptureIds::CallDe as u32, "call_de")?;
+                tl.msg = check_existence_str_opt(&c, RegexToLocalCaptureIds::Msg as u32);
+                tl.utc = check_existence_num_opt(&c, RegexToLocalCaptureIds::Utc as u32, "utc")?;
+
+                Ok(SpotRef::ToLocal(tl))
+            }
+            None => Err(diagnose(&self.diagnosis.tolocal, raw)),
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder to configure a [`Parser`].
+pub struct ParserBuilder {
+    strict: bool,
+    types: Vec<SpotType>,
+    flavor: ClusterFlavor,
+    date: Option<Date>,
+    engine: Engine,
+    detection: Detection,
+    custom: Vec<Box<dyn DynSpotParser>>,
+    error: Option<BuildError>,
 }
 
-fn parse_wcy(raw: &str, mut wcy: WCY) -> Result<Spot, ParseError> {
-    lazy_static! {
-        static ref RE_WCY: Regex = Regex::new(REGEX_PATTERN_WCY).unwrap();
+impl ParserBuilder {
+    /// Create a builder with the default configuration, see [`Parser::new`].
+    pub fn new() -> Self {
+        ParserBuilder {
+            strict: false,
+            types: SpotType::ALL.to_vec(),
+            flavor: ClusterFlavor::Unknown,
+            date: None,
+            engine: Engine::Tokenizer,
+            detection: Detection::Auto,
+            custom: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Enable the strict mode.
+    ///
+    /// A strict parser additionally validates the values of the parsed fields, e.g. the time or the locator.
+    /// A lenient parser accepts everything matching the layout of the type of spot.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the built-in types of spots to parse.
+    /// Lines of any other built-in type are rejected as unknown.
+    pub fn spot_types(mut self, types: &[SpotType]) -> Self {
+        self.types = types.to_vec();
+        self
+    }
+
+    /// Set a hint about the cluster software the spots originate from.
+    ///
+    /// In strict mode, the comment of a DX spot from the Reverse Beacon Network is additionally validated by [`Parser::parse_rbn`].
+    pub fn flavor(mut self, flavor: ClusterFlavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Set the date the time of the parsed spots is put into context with.
+    pub fn date(mut self, date: Date) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// Set the engine used to parse the built-in types of spots.
+    ///
+    /// Both engines give the same results, the tokenizer is faster in the common case of well-formed lines.
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Set the detection of the encoding of raw bytes, see [`Parser::parse_bytes`].
+    pub fn detection(mut self, detection: Detection) -> Self {
+        self.detection = detection;
+        self
+    }
+
+    /// Register a custom type of line recognized by its prefix and parsed by a regular expression.
+    /// See [`RegexSpotParser`] for details.
+    ///
+    /// ## Arguments
+    ///
+    /// * `name`: Name of the custom type, used as kind of the resulting spot
+    /// * `prefix`: Prefix a line must start with to be considered of the custom type
+    /// * `pattern`: Regular expression the line must match, the named capture groups form the payload
+    pub fn custom_type(self, name: &str, prefix: &str, pattern: &str) -> Self {
+        match RegexSpotParser::new(name, prefix, pattern) {
+            Ok(parser) => self.spot_parser(parser),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Register a parser for a custom type of line.
+    /// Registered parsers are tried in order of registration before the built-in types.
+    pub fn spot_parser<P: SpotParser + 'static>(mut self, parser: P) -> Self {
+        self.custom.push(Box::new(parser));
+        self
     }
 
-    match RE_WCY.captures(raw) {
-        Some(c) => {
-            wcy.call_de = check_existence_str(&c, RegexWcyCaptureIds::CallDe as u32)?;
-            wcy.utc = check_existence_num(&c, RegexWcyCaptureIds::Utc as u32)?;
-            wcy.k = check_existence_num(&c, RegexWcyCaptureIds::K as u32)?;
-            wcy.expk = check_existence_num(&c, RegexWcyCaptureIds::Expk as u32)?;
-            wcy.a = check_existence_num(&c, RegexWcyCaptureIds::A as u32)?;
-            wcy.r = check_existence_num(&c, RegexWcyCaptureIds::R as u32)?;
-            wcy.sfi = check_existence_num(&c, RegexWcyCaptureIds::Sfi as u32)?;
-            wcy.sa = check_existence_str(&c, RegexWcyCaptureIds::Sa as u32)?;
-            wcy.gmf = check_existence_str(&c, RegexWcyCaptureIds::Gmf as u32)?;
-            wcy.au = check_existence_str(&c, RegexWcyCaptureIds::Au as u32)?;
+    /// Register types of lines given by definitions, e.g. loaded from a config file.
+    /// See [`Definitions`] for details.
+    pub fn definitions(mut self, definitions: Definitions) -> Self {
+        for def in definitions.lines {
+            match DefinitionParser::new(def) {
+                Ok(parser) => self.custom.push(Box::new(parser)),
+                Err(e) => return self.fail(e),
+            }
+        }
+        self
+    }
 
-            Ok(Spot::WCY(wcy))
+    /// Build the parser.
+    ///
+    /// ## Result
+    ///
+    /// In case the configuration is invalid, e.g. a pattern of a custom type, an error shall be returned.
+    pub fn build(self) -> Result<Parser, BuildError> {
+        if let Some(e) = self.error {
+            return Err(e);
         }
-        None => Err(ParseError::InvalidContent),
+
+        Ok(Parser {
+            strict: self.strict,
+            types: self.types,
+            flavor: self.flavor,
+            date: self.date,
+            engine: self.engine,
+            detection: self.detection,
+            custom: self.custom,
+            ..Parser::new()
+        })
+    }
+
+    fn fail(mut self, error: BuildError) -> Self {
+        self.error.get_or_insert(error);
+        self
     }
 }
 
-fn parse_wx(raw: &str, mut wx: WX) -> Result<Spot, ParseError> {
-    lazy_static! {
-        static ref RE_WX: Regex = Regex::new(REGEX_PATTERN_WX).unwrap();
+impl Default for ParserBuilder {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    match RE_WX.captures(raw) {
-        Some(c) => {
-            wx.call_de = check_existence_str(&c, RegexWxCaptureIds::CallDe as u32)?;
-            wx.utc = check_existence_num_opt(&c, RegexWxCaptureIds::Utc as u32)?;
-            wx.msg = check_existence_str_opt(&c, RegexWxCaptureIds::Msg as u32);
+/// Compiled steps to locate the first failing field of each type of spot
+struct Diagnosis {
+    dx: Vec<DiagnosisStep>,
+    rbn1: Vec<DiagnosisStep>,
+    rbn2: Vec<DiagnosisStep>,
+    wwv: Vec<DiagnosisStep>,
+    wcy: Vec<DiagnosisStep>,
+    wx: Vec<DiagnosisStep>,
+    toall: Vec<DiagnosisStep>,
+    tolocal: Vec<DiagnosisStep>,
+}
 
-            Ok(Spot::WX(wx))
+impl Diagnosis {
+    fn new() -> Self {
+        Diagnosis {
+            dx: compile_diagnosis(DIAGNOSIS_DX),
+            rbn1: compile_diagnosis(DIAGNOSIS_RBN1),
+            rbn2: compile_diagnosis(DIAGNOSIS_RBN2),
+            wwv: compile_diagnosis(DIAGNOSIS_WWV),
+            wcy: compile_diagnosis(DIAGNOSIS_WCY),
+            wx: compile_diagnosis(DIAGNOSIS_WX),
+            toall: compile_diagnosis(DIAGNOSIS_TOALL),
+            tolocal: compile_diagnosis(DIAGNOSIS_TOLOCAL),
         }
-        None => Err(ParseError::InvalidContent),
     }
 }
 
-fn parse_toall(raw: &str, mut ta: ToAll) -> Result<Spot, ParseError> {
-    lazy_static! {
-        static ref RE_TOALL: Regex = Regex::new(REGEX_PATTERN_TOALL).unwrap();
+/// Single step of a diagnosis, the expression matches all fields up to and including the field of the step
+struct DiagnosisStep {
+    field: &'static str,
+    re: Regex,
+    expected: &'static str,
+}
+
+fn compile_diagnosis(steps: &[(&'static str, &str, &'static str)]) -> Vec<DiagnosisStep> {
+    let mut pattern = String::from("^");
+
+    steps
+        .iter()
+        .map(|(field, fragment, expected)| {
+            pattern.push_str(fragment);
+            DiagnosisStep {
+                field,
+                re: Regex::new(&pattern).unwrap(),
+                expected,
+            }
+        })
+        .collect()
+}
+
+/// Locate the first field of a spot not matching its expected shape.
+/// The offending text is the word following the last field that matched.
+fn diagnose(steps: &[DiagnosisStep], raw: &str) -> ParseError {
+    let mut pos = 0;
+
+    for step in steps {
+        match step.re.find(raw) {
+            Some(m) => pos = m.end(),
+            None => {
+                let rest = &raw[pos..];
+                let start = pos + (rest.len() - rest.trim_start().len());
+                let end = raw[start..]
+                    .find(char::is_whitespace)
+                    .map_or(raw.len(), |i| start + i);
+
+                return ParseError::new(ParseErrorKind::InvalidContent)
+                    .with_field(step.field)
+                    .with_span(start..end, &raw[start..end])
+                    .with_expected(step.expected);
+            }
+        }
     }
 
-    match RE_TOALL.captures(raw) {
-        Some(c) => {
-            ta.call_de = check_existence_str(&c, RegexToAllCaptureIds::CallDe as u32)?;
-            ta.utc = check_existence_num_opt(&c, RegexToAllCaptureIds::Utc as u32)?;
-            ta.msg = check_existence_str_opt(&c, RegexToAllCaptureIds::Msg as u32);
+    ParseErrorKind::InvalidContent.into()
+}
 
-            Ok(Spot::ToAll(ta))
+fn validate(spot: &SpotRef) -> Result<(), ParseError> {
+    match spot {
+        SpotRef::DX(dx) => {
+            validate_time(Some(dx.utc))?;
+            validate_loc(dx.loc.as_deref())?;
+            if dx.freq == 0 {
+                return Err(ParseError::new(ParseErrorKind::InvalidContent)
+                    .with_field("freq")
+                    .with_text("0")
+                    .with_expected("non-zero frequency"));
+            }
         }
-        None => Err(ParseError::InvalidContent),
+        SpotRef::WWV(wwv) => validate_hour(wwv.utc)?,
+        SpotRef::WCY(wcy) => validate_hour(wcy.utc)?,
+        SpotRef::WX(wx) => validate_time(wx.utc)?,
+        SpotRef::ToAll(ta) => validate_time(ta.utc)?,
+        SpotRef::ToLocal(tl) => validate_time(tl.utc)?,
+        SpotRef::Custom(_) => {}
     }
+
+    Ok(())
 }
 
-fn parse_tolocal(raw: &str, mut tl: ToLocal) -> Result<Spot, ParseError> {
-    lazy_static! {
-        static ref RE_TOLOCAL: Regex = Regex::new(REGEX_PATTERN_TOLOCAL).unwrap();
+pub(crate) fn validate_time(utc: Option<u16>) -> Result<(), ParseError> {
+    match utc {
+        Some(utc) if utc / 100 > 23 || utc % 100 > 59 => {
+            Err(ParseError::new(ParseErrorKind::InvalidContent)
+                .with_field("utc")
+                .with_text(&format!("{:04}", utc))
+                .with_expected("valid time as HHMM"))
+        }
+        _ => Ok(()),
     }
+}
 
-    match RE_TOLOCAL.captures(raw) {
-        Some(c) => {
-            tl.call_de = check_existence_str(&c, RegexToLocalCaptureIds::CallDe as u32)?;
-            tl.msg = check_existence_str_opt(&c, RegexToLocalCaptureIds::Msg as u32);
-            tl.utc = check_existence_num_opt(&c, RegexToLocalCaptureIds::Utc as u32)?;
+fn validate_hour(utc: u8) -> Result<(), ParseError> {
+    if utc > 23 {
+        Err(ParseError::new(ParseErrorKind::InvalidContent)
+            .with_field("utc")
+            .with_text(&format!("{:02}", utc))
+            .with_expected("valid hour"))
+    } else {
+        Ok(())
+    }
+}
 
-            Ok(Spot::ToLocal(tl))
+pub(crate) fn validate_loc(loc: Option<&str>) -> Result<(), ParseError> {
+    match loc {
+        Some(loc) => {
+            let b = loc.to_ascii_uppercase().into_bytes();
+            if b.len() == 4
+                && (b'A'..=b'R').contains(&b[0])
+                && (b'A'..=b'R').contains(&b[1])
+                && b[2].is_ascii_digit()
+                && b[3].is_ascii_digit()
+            {
+                Ok(())
+            } else {
+                Err(ParseError::new(ParseErrorKind::InvalidContent)
+                    .with_field("loc")
+                    .with_text(loc)
+                    .with_expected("locator of two letters A to R followed by two digits"))
+            }
         }
-        None => Err(ParseError::InvalidContent),
+        None => Ok(()),
     }
 }
 
-fn check_existence_num<T>(cap: &Captures, id: u32) -> Result<T, ParseError>
+fn check_existence_num<T>(cap: &Captures, id: u32, field: &str) -> Result<T, ParseError>
 where
     T: std::str::FromStr,
 {
     match cap.get(id.try_into().unwrap()) {
         Some(val) => match val.as_str().parse::<T>() {
             Ok(v) => Ok(v),
-            Err(_) => Err(ParseError::InternalError),
+            Err(_) => Err(ParseError::new(ParseErrorKind::InvalidContent)
+                .with_field(field)
+                .with_span(val.range(), val.as_str())
+                .with_expected("number")),
         },
-        None => Err(ParseError::MissingField),
+        None => Err(ParseError::new(ParseErrorKind::MissingField).with_field(field)),
     }
 }
 
-fn check_existence_num_opt<T>(cap: &Captures, id: u32) -> Result<Option<T>, ParseError>
+fn check_existence_num_opt<T>(cap: &Captures, id: u32, field: &str) -> Result<Option<T>, ParseError>
 where
     T: std::str::FromStr,
 {
     if let Some(val) = cap.get(id.try_into().unwrap()) {
         match val.as_str().parse::<T>() {
             Ok(v) => Ok(Some(v)),
-            Err(_) => Err(ParseError::InternalError),
+            Err(_) => Err(ParseError::new(ParseErrorKind::InvalidContent)
+                .with_field(field)
+                .with_span(val.range(), val.as_str())
+                .with_expected("number")),
         }
     } else {
         Ok(None)
     }
 }
 
-fn check_existence_str(cap: &Captures, id: u32) -> Result<String, ParseError> {
+fn check_existence_str<'a>(
+    cap: &Captures<'a>,
+    id: u32,
+    field: &str,
+) -> Result<Cow<'a, str>, ParseError> {
     match cap.get(id.try_into().unwrap()) {
-        Some(val) => Ok(String::from(val.as_str())),
-        None => Err(ParseError::MissingField),
+        Some(val) => Ok(Cow::Borrowed(val.as_str())),
+        None => Err(ParseError::new(ParseErrorKind::MissingField).with_field(field)),
     }
 }
 
-fn check_existence_str_opt(cap: &Captures, id: u32) -> Option<String> {
+fn check_existence_str_opt<'a>(cap: &Captures<'a>, id: u32) -> Option<Cow<'a, str>> {
     cap.get(id.try_into().unwrap())
-        .map(|val| String::from(val.as_str()))
+        .map(|val| Cow::Borrowed(val.as_str()))
 }
 
 #[cfg(test)]
@@ -361,6 +1092,7 @@ mod tests {
             utc: 2200,
             loc: Some("JO62".into()),
             comment: Some("LSB".into()),
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -376,6 +1108,7 @@ mod tests {
             utc: 1624,
             loc: None,
             comment: Some("OK QSO Party: Major".into()),
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -391,6 +1124,7 @@ mod tests {
             utc: 1625,
             loc: None,
             comment: Some("FT8".into()),
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -399,7 +1133,10 @@ mod tests {
     fn dx_only_type() {
         let spot = "DX de DF2MX";
         let res = parse(spot);
-        assert_eq!(res, Err(ParseError::InvalidContent));
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
     }
 
     #[test]
@@ -413,6 +1150,7 @@ mod tests {
             utc: 1812,
             loc: None,
             comment: Some("599 into N. MI".into()),
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -429,6 +1167,7 @@ mod tests {
             utc: 1815,
             loc: Some("JO55".into()),
             comment: None,
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -444,6 +1183,7 @@ mod tests {
             utc: 1629,
             loc: None,
             comment: None,
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -459,6 +1199,7 @@ mod tests {
             utc: 1625,
             loc: None,
             comment: None,
+            truncated: false,
         });
         assert_eq!(res, Ok(exp));
     }
@@ -515,7 +1256,10 @@ mod tests {
     fn wwv_only_type() {
         let spot = "WWV de VE7CC";
         let res = parse(spot);
-        assert_eq!(res, Err(ParseError::InvalidContent));
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
     }
 
     #[test]
@@ -560,7 +1304,10 @@ mod tests {
     fn wcy_only_type() {
         let spot = "WCY de DK0WCY-1";
         let res = parse(spot);
-        assert_eq!(res, Err(ParseError::InvalidContent))
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        )
     }
 
     #[test]
@@ -594,7 +1341,10 @@ mod tests {
     fn wx_only_type() {
         let spot = "WX de OZ4AEC";
         let res = parse(spot);
-        assert_eq!(res, Err(ParseError::InvalidContent));
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
     }
 
     #[test]
@@ -625,7 +1375,10 @@ mod tests {
     fn toall_only_type() {
         let spot = "To ALL de SV5FRI-1";
         let res = parse(spot);
-        assert_eq!(res, Err(ParseError::InvalidContent));
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
     }
 
     #[test]
@@ -656,7 +1409,10 @@ mod tests {
     fn tolocal_only_type() {
         let spot = "To Local de N5UXT";
         let res = parse(spot);
-        assert_eq!(res, Err(ParseError::InvalidContent));
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
     }
 
     #[test]
@@ -718,4 +1474,191 @@ mod tests {
         };
         assert_eq!(res, Ok(exp));
     }
+
+    #[test]
+    fn parser_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Parser>();
+    }
+
+    #[test]
+    fn parser_strict_invalid_time() {
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI                 2512Z";
+        let parser = Parser::builder().strict(true).build().unwrap();
+        assert_eq!(
+            parser.parse(spot).map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
+        assert!(Parser::new().parse(spot).is_ok());
+    }
+
+    #[test]
+    fn parser_strict_invalid_loc() {
+        let spot =
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z ZZ62";
+        let parser = Parser::builder().strict(true).build().unwrap();
+        assert_eq!(
+            parser.parse(spot).map_err(|e| e.kind()),
+            Err(ParseErrorKind::InvalidContent)
+        );
+    }
+
+    #[test]
+    fn parser_strict_rbn_comment() {
+        let parser = Parser::builder()
+            .strict(true)
+            .flavor(ClusterFlavor::RBN)
+            .build()
+            .unwrap();
+        let spot = "DX de W3LPL-#:   14025.0  DL1ABC       CW    12 dB  22 WPM  CQ      1234Z";
+        assert!(parser.parse(spot).is_ok());
+
+        let spot = "DX de W3LPL-#:   14025.0  DL1ABC       CW    loud   22 WPM  CQ      1234Z";
+        let err = parser.parse(spot).unwrap_err();
+        assert_eq!(err.spot_type(), Some(SpotType::DX));
+        assert_eq!(err.field(), Some("db"));
+        assert_eq!(err.span().map(|s| &spot[s]), Some("loud"));
+        assert!(Parser::builder()
+            .strict(true)
+            .build()
+            .unwrap()
+            .parse(spot)
+            .is_ok());
+    }
+
+    #[test]
+    fn parser_flavor_without_loc() {
+        let spot =
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62";
+        for flavor in [ClusterFlavor::ARCluster, ClusterFlavor::RBN] {
+            let parser = Parser::builder().flavor(flavor).build().unwrap();
+            let err = parser.parse(spot).unwrap_err();
+            assert_eq!(err.kind(), ParseErrorKind::InvalidContent);
+            assert_eq!(err.field(), Some("loc"));
+            assert_eq!(err.span().map(|s| &spot[s]), Some("JO62"));
+            assert!(parser.parse(spot.trim_end_matches(" JO62")).is_ok());
+        }
+        let parser = Parser::builder()
+            .flavor(ClusterFlavor::CCCluster)
+            .build()
+            .unwrap();
+        assert!(parser.parse(spot).is_ok());
+    }
+
+    #[test]
+    fn parser_disabled_type() {
+        let parser = Parser::builder()
+            .spot_types(&[SpotType::WWV, SpotType::WCY])
+            .build()
+            .unwrap();
+        let spot = "DX de ZS6WN:     21075.4  CX2DAJ       FT8                            1625Z";
+        assert_eq!(
+            parser.parse(spot).map_err(|e| e.kind()),
+            Err(ParseErrorKind::UnknownType)
+        );
+        let spot = "WWV de AE5E <00>:   SFI=69, A=15, K=4, No Storms -> No Storms";
+        assert!(parser.parse(spot).is_ok());
+    }
+
+    #[test]
+    fn parser_custom_type() {
+        let parser = Parser::builder()
+            .custom_type(
+                "contest",
+                "CONTEST de",
+                r#"^CONTEST de (?P<call_de>[A-Z0-9/\-#]+): (?P<name>.*)$"#,
+            )
+            .build()
+            .unwrap();
+        let res = parser.parse("CONTEST de DL1ABC: CQ WW DX CW");
+        let exp = Spot::Custom(Custom {
+            kind: "contest".into(),
+            payload: serde_json::json!({"call_de": "DL1ABC", "name": "CQ WW DX CW"}),
+        });
+        assert_eq!(res, Ok(exp));
+    }
+
+    #[test]
+    fn parser_custom_type_invalid_pattern() {
+        let res = Parser::builder()
+            .custom_type("broken", "BROKEN", "(")
+            .build();
+        assert!(matches!(res, Err(BuildError::InvalidPattern(name, _)) if name == "broken"));
+    }
+
+    #[test]
+    fn parser_timestamp() {
+        let parser = Parser::builder()
+            .date(Date::new(2022, 9, 10))
+            .build()
+            .unwrap();
+        let spot = parser
+            .parse("DX de W9KXQ:     14076.0  HB9AOF                                      1629Z")
+            .unwrap();
+        assert_eq!(parser.timestamp(&spot), Some(1662827340));
+        assert_eq!(Parser::new().timestamp(&spot), None);
+    }
+
+    #[test]
+    fn error_unknown_type() {
+        let err = parse("SH/DX 20").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnknownType);
+        assert_eq!(err.span(), Some(0..5));
+        assert_eq!(err.text(), Some("SH/DX"));
+    }
+
+    #[test]
+    fn error_dx_invalid_freq() {
+        let spot = "DX de KE8GX:     14025,0  3B9FR        599 into N. MI                 1812Z";
+        let err = parse(spot).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidContent);
+        assert_eq!(err.spot_type(), Some(SpotType::DX));
+        assert_eq!(err.field(), Some("freq"));
+        assert_eq!(err.span(), Some(17..24));
+        assert_eq!(err.text(), Some("14025,0"));
+        assert_eq!(err.expected(), Some("number"));
+    }
+
+    #[test]
+    fn error_dx_invalid_loc() {
+        let spot =
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO6";
+        let err = parse(spot).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidContent);
+        assert_eq!(err.field(), Some("loc"));
+        assert_eq!(err.text(), Some("JO6"));
+    }
+
+    #[test]
+    fn error_dx_only_type() {
+        let err = parse("DX de DF2MX").unwrap_err();
+        assert_eq!(err.field(), Some("freq"));
+        assert_eq!(err.span(), Some(11..11));
+    }
+
+    #[test]
+    fn error_wcy_invalid_index() {
+        let spot = "WCY de DK0WCY-1 <17> : K=2 expK=3 A=15 R=x SFI=68 SA=qui GMF=min Au=no";
+        let err = parse(spot).unwrap_err();
+        assert_eq!(err.spot_type(), Some(SpotType::WCY));
+        assert_eq!(err.field(), Some("r"));
+        assert_eq!(err.text(), Some("R=x"));
+    }
+
+    #[test]
+    fn error_rbn() {
+        let err = parse_rbn("CW     9 dB  21 KPH  NCDXF B").unwrap_err();
+        assert_eq!(err.field(), Some("speed_unit"));
+        assert_eq!(err.text(), Some("KPH"));
+    }
+
+    #[test]
+    fn error_strict_time() {
+        let spot = "To ALL de CT2IDL <1075Z> : TNX qso..";
+        let parser = Parser::builder().strict(true).build().unwrap();
+        let err = parser.parse(spot).unwrap_err();
+        assert_eq!(err.spot_type(), Some(SpotType::ToAll));
+        assert_eq!(err.field(), Some("utc"));
+        assert_eq!(err.text(), Some("1075"));
+    }
 }
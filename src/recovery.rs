@@ -0,0 +1,406 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::columns::ColumnLayout;
+use crate::error::{ParseError, ParseErrorKind};
+use crate::parser::{validate_loc, validate_time, Parser};
+use crate::types::*;
+use std::ops::Range;
+
+/// Result of a best-effort parse
+#[derive(Debug, PartialEq, Eq)]
+pub struct Recovered {
+    /// Spot holding all fields that could be parsed, fields that could not be parsed are left at their default.
+    /// `None` in case nothing could be recovered.
+    pub spot: Option<Spot>,
+
+    /// Problems with the fields that could not be parsed
+    pub problems: Vec<ParseError>,
+}
+
+impl Recovered {
+    /// Check whether the spot was parsed without any problem.
+    pub fn is_complete(&self) -> bool {
+        self.spot.is_some() && self.problems.is_empty()
+    }
+}
+
+impl Parser {
+    /// Parse a spot received from a DX Cluster on a best-effort basis.
+    ///
+    /// In case the spot can not be parsed as a whole, a spot of the type DX is recovered field by field.
+    /// Any other type of spot is either parsed completely or not at all.
+    ///
+    /// ## Arguments
+    ///
+    /// * `raw`: A raw spot that is already cleaned from newline or bell characters etc.
+    ///
+    /// ## Result
+    ///
+    /// The recovered spot together with the problems of all fields that could not be parsed.
+    pub fn parse_recover(&self, raw: &str) -> Recovered {
+        match self.parse(raw) {
+            Ok(spot) => Recovered {
+                spot: Some(spot),
+                problems: Vec::new(),
+            },
+            // Disabled types are rejected as unknown, they are not recovered either
+            Err(e)
+                if e.kind() != ParseErrorKind::UnknownType
+                    && e.spot_type() == Some(SpotType::DX)
+                    && self.is_enabled(SpotType::DX) =>
+            {
+                let (dx, problems) = self.recover_dx(raw);
+                Recovered {
+                    spot: (dx != DX::new()).then_some(Spot::DX(dx)),
+                    problems,
+                }
+            }
+            Err(e) => Recovered {
+                spot: None,
+                problems: vec![e],
+            },
+        }
+    }
+
+    fn recover_dx(&self, raw: &str) -> (DX, Vec<ParseError>) {
+        let mut dx = DX::new();
+        let mut problems = Vec::new();
+        let toks = tokens(raw);
+
+        // Skip the leading `DX de`
+        let mut idx = 2;
+
+        // Call of the spotter, with the frequency glued to it in case the columns overflowed
+        let mut glued = None;
+        match toks.get(idx) {
+            Some(&(pos, tok)) => {
+                idx += 1;
+                let call = match tok.find(':') {
+                    Some(i) => {
+                        if i + 1 < tok.len() {
+                            glued = Some((pos + i + 1, &tok[i + 1..]));
+                        }
+                        &tok[..i]
+                    }
+                    None => tok,
+                };
+                if is_call(call) {
+                    dx.call_de = String::from(call);
+                } else {
+                    problems.push(invalid(
+                        "call_de",
+                        pos..pos + call.len(),
+                        call,
+                        EXPECTED_CALL,
+                    ));
+                }
+            }
+            None => problems.push(missing("call_de")),
+        }
+
+        // Frequency
+        let freq = match glued {
+            Some(tok) => Some(tok),
+            None => {
+                let tok = toks.get(idx).copied();
+                if tok.is_some() {
+                    idx += 1;
+                }
+                tok
+            }
+        };
+        match freq {
+            Some((pos, tok)) => match tok.parse::<f64>() {
+                Ok(f) if f > 0.0 => dx.freq = (f * 1000.0).round() as u64,
+                _ => problems.push(invalid("freq", pos..pos + tok.len(), tok, EXPECTED_FREQ)),
+            },
+            None => problems.push(missing("freq")),
+        }
+
+        // Time and locator at the end of the line
+        let lower = idx.max(toks.len().saturating_sub(2));
+        let time = (lower..toks.len())
+            .rev()
+            .find(|&i| looks_like_time(toks[i].1));
+        let tail = match time {
+            Some(ti) => {
+                let (pos, tok) = toks[ti];
+                match tok.strip_suffix('Z').and_then(parse_time) {
+                    Some(utc) => match self.check(validate_time(Some(utc))) {
+                        Ok(()) => dx.utc = utc,
+                        Err(e) => problems.push(e.with_span(pos..pos + tok.len(), tok)),
+                    },
+                    None => problems.push(invalid("utc", pos..pos + tok.len(), tok, EXPECTED_TIME)),
+                }
+
+                if let Some(&(pos, tok)) = toks.get(ti + 1) {
+                    let layout = ColumnLayout::of(self.flavor());
+                    if layout.loc && is_loc(tok) && self.check(validate_loc(Some(tok))).is_ok() {
+                        dx.loc = Some(String::from(tok));
+                    } else {
+                        problems.push(invalid("loc", pos..pos + tok.len(), tok, EXPECTED_LOC));
+                    }
+                }
+
+                ti
+            }
+            None => {
+                problems.push(missing("utc"));
+                toks.len()
+            }
+        };
+
+        // Call of the DX station followed by the comment
+        if idx < tail {
+            let (pos, tok) = toks[idx];
+            if is_call(tok) {
+                dx.call_dx = String::from(tok);
+            } else {
+                problems.push(invalid("call_dx", pos..pos + tok.len(), tok, EXPECTED_CALL));
+            }
+            idx += 1;
+        } else {
+            problems.push(missing("call_dx"));
+        }
+
+        if idx < tail {
+            let (last_pos, last) = toks[tail - 1];
+            dx.comment = Some(String::from(&raw[toks[idx].0..last_pos + last.len()]));
+        }
+
+        // The comment of a RBN spot is validated just like by a complete parse
+        if self.flavor() == ClusterFlavor::RBN {
+            if let Err(e) = self.check(self.validate_rbn(raw, dx.comment.as_deref())) {
+                problems.push(e);
+            }
+        }
+
+        let problems = problems
+            .into_iter()
+            .map(|e| e.with_spot_type(SpotType::DX))
+            .collect();
+
+        (dx, problems)
+    }
+
+    /// Apply a validation only in strict mode.
+    fn check(&self, res: Result<(), ParseError>) -> Result<(), ParseError> {
+        if self.is_strict() {
+            res
+        } else {
+            Ok(())
+        }
+    }
+}
+
+const EXPECTED_CALL: &str = "callsign of at least three characters A to Z, 0 to 9, /, - or #";
+const EXPECTED_FREQ: &str = "frequency in kHz, e.g. 14025.0";
+const EXPECTED_TIME: &str = "time as HHMM followed by Z";
+const EXPECTED_LOC: &str = "locator, e.g. JO62";
+
+/// Split a line into its whitespace separated words together with their positions.
+fn tokens(raw: &str) -> Vec<(usize, &str)> {
+    let mut toks = Vec::new();
+    let mut start = None;
+
+    for (i, ch) in raw.char_indices() {
+        match (ch.is_whitespace(), start) {
+            (true, Some(s)) => {
+                toks.push((s, &raw[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        toks.push((s, &raw[s..]));
+    }
+
+    toks
+}
+
+fn is_call(val: &str) -> bool {
+    val.len() >= 3
+        && val
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "/-#".contains(c))
+}
+
+fn is_loc(val: &str) -> bool {
+    let b = val.as_bytes();
+    b.len() == 4
+        && b[0].is_ascii_alphanumeric()
+        && b[1].is_ascii_alphanumeric()
+        && b[2].is_ascii_digit()
+        && b[3].is_ascii_digit()
+}
+
+fn looks_like_time(val: &str) -> bool {
+    val.starts_with(|c: char| c.is_ascii_digit())
+        && (val.ends_with('Z') || (val.len() == 4 && val.bytes().all(|b| b.is_ascii_digit())))
+}
+
+fn parse_time(val: &str) -> Option<u16> {
+    if val.len() == 4 && val.bytes().all(|b| b.is_ascii_digit()) {
+        val.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn invalid(field: &str, span: Range<usize>, text: &str, expected: &str) -> ParseError {
+    ParseError::new(ParseErrorKind::InvalidContent)
+        .with_field(field)
+        .with_span(span, text)
+        .with_expected(expected)
+}
+
+fn missing(field: &str) -> ParseError {
+    ParseError::new(ParseErrorKind::MissingField).with_field(field)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn recover_complete() {
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI                 1812Z";
+        let res = Parser::new().parse_recover(spot);
+        assert!(res.is_complete());
+        assert_eq!(res.spot, crate::parse(spot).ok());
+    }
+
+    #[test]
+    fn recover_malformed_loc() {
+        let spot =
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO6";
+        let res = Parser::new().parse_recover(spot);
+        let exp = Spot::DX(DX {
+            call_de: "DJ1TO".into(),
+            call_dx: "OH5Z".into(),
+            freq: 3780000,
+            utc: 2200,
+            loc: None,
+            comment: Some("LSB".into()),
+            truncated: false,
+        });
+        assert_eq!(res.spot, Some(exp));
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].field(), Some("loc"));
+        assert_eq!(res.problems[0].text(), Some("JO6"));
+    }
+
+    #[test]
+    fn recover_invalid_spotter() {
+        let spot = "DX de dl1abc-@:  14025.0  3B9FR        599                            1812Z";
+        let res = Parser::new().parse_recover(spot);
+        let exp = Spot::DX(DX {
+            call_de: "".into(),
+            call_dx: "3B9FR".into(),
+            freq: 14025000,
+            utc: 1812,
+            loc: None,
+            comment: Some("599".into()),
+            truncated: false,
+        });
+        assert_eq!(res.spot, Some(exp));
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].field(), Some("call_de"));
+        assert_eq!(res.problems[0].span(), Some(6..14));
+    }
+
+    #[test]
+    fn recover_overlong_calls() {
+        let spot = "DX de PA/DL1ABC/P-#:14025.0 VP2V/DL1ABC/MM/QRP 599 tnx qso 1812 JO62";
+        let res = Parser::new().parse_recover(spot);
+        let exp = Spot::DX(DX {
+            call_de: "PA/DL1ABC/P-#".into(),
+            call_dx: "VP2V/DL1ABC/MM/QRP".into(),
+            freq: 14025000,
+            utc: 0,
+            loc: Some("JO62".into()),
+            comment: Some("599 tnx qso".into()),
+            truncated: false,
+        });
+        assert_eq!(res.spot, Some(exp));
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].field(), Some("utc"));
+        assert_eq!(res.problems[0].text(), Some("1812"));
+    }
+
+    #[test]
+    fn recover_strict_time() {
+        let spot = "DX de KE8GX:     14025.0  3B9FR        599 into N. MI                 2512Z";
+        let parser = Parser::builder().strict(true).build().unwrap();
+        let res = parser.parse_recover(spot);
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].field(), Some("utc"));
+        assert_eq!(res.problems[0].spot_type(), Some(SpotType::DX));
+    }
+
+    #[test]
+    fn recover_strict_rbn() {
+        let spot = "DX de W3LPL-#:   14025.0  DL1ABC       CW    loud   22 WPM  CQ      1234Z";
+        let parser = Parser::builder()
+            .strict(true)
+            .flavor(ClusterFlavor::RBN)
+            .build()
+            .unwrap();
+        let res = parser.parse_recover(spot);
+        assert!(!res.is_complete());
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].field(), Some("db"));
+        assert_eq!(res.problems[0].spot_type(), Some(SpotType::DX));
+        assert_eq!(parser.parse(spot).unwrap_err(), res.problems[0]);
+
+        let res = Parser::builder()
+            .flavor(ClusterFlavor::RBN)
+            .build()
+            .unwrap()
+            .parse_recover(spot);
+        assert!(res.is_complete());
+    }
+
+    #[test]
+    fn recover_only_type() {
+        let res = Parser::new().parse_recover("DX de DF2MX");
+        let fields: Vec<_> = res.problems.iter().filter_map(|e| e.field()).collect();
+        assert_eq!(fields, vec!["freq", "utc", "call_dx"]);
+    }
+
+    #[test]
+    fn recover_nothing() {
+        let res = Parser::new().parse_recover("DX de");
+        assert_eq!(res.spot, None);
+        let fields: Vec<_> = res.problems.iter().filter_map(|e| e.field()).collect();
+        assert_eq!(fields, vec!["call_de", "freq", "utc", "call_dx"]);
+    }
+
+    #[test]
+    fn recover_disabled_type() {
+        let spot =
+            "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62";
+        let parser = Parser::builder()
+            .spot_types(&[SpotType::WWV])
+            .build()
+            .unwrap();
+        let res = parser.parse_recover(spot);
+        assert!(!res.is_complete());
+        assert_eq!(res.spot, None);
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].kind(), ParseErrorKind::UnknownType);
+    }
+
+    #[test]
+    fn recover_other_type() {
+        let res = Parser::new().parse_recover("WWV de VE7CC");
+        assert_eq!(res.spot, None);
+        assert_eq!(res.problems.len(), 1);
+        assert_eq!(res.problems[0].kind(), ParseErrorKind::InvalidContent);
+    }
+}
@@ -0,0 +1,458 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::command::Filter;
+use crate::error::{CommandError, ParseError};
+use crate::history::{Query, SpotHistory, DEFAULT_HISTORY_LEN};
+use crate::sanitize::sanitize_bytes;
+use crate::server::Server;
+use crate::types::{ClusterFlavor, Date, Spot};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default time to wait for a downstream client to take a line, slower clients are disconnected
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builder for a [`Relay`]
+pub struct RelayBuilder {
+    node: String,
+    greeting: Option<String>,
+    write_timeout: Duration,
+    history: usize,
+}
+
+impl RelayBuilder {
+    /// Create a builder of a relay with the callsign `NOCALL`.
+    pub fn new() -> Self {
+        RelayBuilder {
+            node: String::from("NOCALL"),
+            greeting: None,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            history: DEFAULT_HISTORY_LEN,
+        }
+    }
+
+    /// Set the callsign of the relay, shown in the greeting and the prompt.
+    pub fn node(mut self, node: &str) -> Self {
+        self.node = String::from(node);
+        self
+    }
+
+    /// Set the text sent to a connecting client in front of the login prompt, by default `Welcome to <node>`.
+    pub fn greeting(mut self, greeting: &str) -> Self {
+        self.greeting = Some(String::from(greeting));
+        self
+    }
+
+    /// Set the time to wait for a client to take a line, by default [`DEFAULT_WRITE_TIMEOUT`].
+    /// A client not taking a line in time is disconnected, so it does not hold up the others.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Set the number of spots of each kind kept to answer the show commands, by default [`DEFAULT_HISTORY_LEN`].
+    pub fn history(mut self, len: usize) -> Self {
+        self.history = len;
+        self
+    }
+
+    /// Start listening for downstream clients.
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address to listen on, e.g. `127.0.0.1:7300`
+    ///
+    /// ## Result
+    ///
+    /// In case the relay listens, the running relay shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn bind<A: ToSocketAddrs>(self, addr: A) -> io::Result<Relay> {
+        let shared = Arc::new(Shared {
+            history: Mutex::new(SpotHistory::new(self.history)),
+            config: self,
+            clients: Mutex::new(Vec::new()),
+        });
+
+        let server = {
+            let shared = shared.clone();
+            Server::bind(addr, move |stream| {
+                let _ = session(stream, &shared);
+            })?
+        };
+
+        Ok(Relay { server, shared })
+    }
+}
+
+impl Default for RelayBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Telnet server re-broadcasting the spots of a single upstream cluster to any number of downstream clients,
+/// e.g. loggers and bandmaps of a station sharing one connection to a cluster.
+///
+/// Clients log in like at DXSpider and receive the spots in the line format of DXSpider, see [`Spot::to_line`].
+/// Each client may set its own filter with the filter commands of DXSpider or AR-Cluster, see [`Filter`].
+/// Every filter command replaces the previous filter of the client.
+/// The show commands `SH/DX`, `SH/WWV` and `SH/WCY` are answered from the broadcast spots, see [`SpotHistory`].
+/// The relay stops listening and disconnects all clients as soon as it is dropped.
+///
+/// ```no_run
+/// use dxclparser::{ClusterClient, Relay};
+///
+/// let relay = Relay::builder().node("DL1ABC-2").bind("127.0.0.1:7300").unwrap();
+/// let mut upstream = ClusterClient::connect("dxc.example.org:7300", "DL1ABC").unwrap();
+/// relay.forward(upstream.by_ref());
+/// ```
+pub struct Relay {
+    server: Server,
+    shared: Arc<Shared>,
+}
+
+impl Relay {
+    /// Create a builder for a relay.
+    pub fn builder() -> RelayBuilder {
+        RelayBuilder::new()
+    }
+
+    /// Get the address the relay listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.server.addr()
+    }
+
+    /// Get the callsigns of the logged in clients, in the order they logged in.
+    pub fn clients(&self) -> Vec<String> {
+        let clients = self.shared.clients.lock().unwrap();
+        clients.iter().map(|c| c.call.clone()).collect()
+    }
+
+    /// Send a spot to all logged in clients whose filter it passes and keep it for the show commands.
+    /// Clients failing to take the spot are disconnected.
+    ///
+    /// ## Arguments
+    ///
+    /// * `spot`: Spot to send, custom spots are not sent since their layout is not known
+    ///
+    /// ## Result
+    ///
+    /// The number of clients the spot was sent to shall be returned.
+    pub fn broadcast(&self, spot: &Spot) -> usize {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.shared
+            .history
+            .lock()
+            .unwrap()
+            .push(spot, Date::from_timestamp(now));
+
+        let Some(line) = spot.to_line(ClusterFlavor::DXSpider) else {
+            return 0;
+        };
+        let clients: Vec<_> = self.shared.clients.lock().unwrap().clone();
+
+        let mut sent = 0;
+        for client in clients {
+            if !client.filter.lock().unwrap().passes(spot) {
+                continue;
+            }
+            match client.send(&format!("{}\r\n", line)) {
+                Ok(()) => sent += 1,
+                Err(_) => client.disconnect(),
+            }
+        }
+        sent
+    }
+
+    /// Send the spots of an upstream cluster to the clients until the upstream ends, see [`Relay::broadcast`].
+    /// Lines of the upstream that are no spots are skipped.
+    ///
+    /// ## Arguments
+    ///
+    /// * `upstream`: Spots of the upstream cluster, e.g. a [`crate::ClusterClient`] passed by `by_ref()` to take its error afterwards
+    ///
+    /// ## Result
+    ///
+    /// The number of forwarded spots shall be returned.
+    pub fn forward<I>(&self, upstream: I) -> usize
+    where
+        I: Iterator<Item = Result<Spot, ParseError>>,
+    {
+        let mut count = 0;
+        for spot in upstream.flatten() {
+            self.broadcast(&spot);
+            count += 1;
+        }
+        count
+    }
+}
+
+/// State shared by the sessions of the clients and the broadcast
+struct Shared {
+    config: RelayBuilder,
+    clients: Mutex<Vec<Arc<Downstream>>>,
+    history: Mutex<SpotHistory>,
+}
+
+/// Logged in downstream client
+struct Downstream {
+    call: String,
+    filter: Mutex<Filter>,
+    writer: Mutex<TcpStream>,
+}
+
+impl Downstream {
+    fn send(&self, text: &str) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(text.as_bytes())?;
+        writer.flush()
+    }
+
+    fn disconnect(&self) {
+        let _ = self.writer.lock().unwrap().shutdown(Shutdown::Both);
+    }
+}
+
+/// Serve a single client: login and answers to its commands, the spots are sent by the broadcast.
+fn session(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let config = &shared.config;
+    stream.set_write_timeout(Some(config.write_timeout))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let greeting = config
+        .greeting
+        .clone()
+        .unwrap_or_else(|| format!("Welcome to {}", config.node));
+    write!(writer, "{}\r\n\r\nlogin: ", greeting)?;
+    let Some(call) = read_line(&mut reader)? else {
+        return Ok(());
+    };
+    let call = call.to_ascii_uppercase();
+    if call.len() < 3
+        || !call
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"/-".contains(&b))
+    {
+        write!(writer, "Sorry, {} is not a valid callsign\r\n", call)?;
+        return writer.shutdown(Shutdown::Both);
+    }
+
+    let prompt = format!("{} de {} >", call, config.node);
+    let client = Arc::new(Downstream {
+        call,
+        filter: Mutex::new(Filter::Clear),
+        writer: Mutex::new(writer),
+    });
+    client.send(&prompt)?;
+    shared.clients.lock().unwrap().push(client.clone());
+
+    let res = commands(&client, shared, &prompt, &mut reader);
+
+    shared
+        .clients
+        .lock()
+        .unwrap()
+        .retain(|c| !Arc::ptr_eq(c, &client));
+    client.disconnect();
+    res
+}
+
+/// Answer the commands of a logged in client until it logs out.
+fn commands(
+    client: &Downstream,
+    shared: &Shared,
+    prompt: &str,
+    reader: &mut impl BufRead,
+) -> io::Result<()> {
+    while let Some(line) = read_line(reader)? {
+        if line.eq_ignore_ascii_case("bye") {
+            break;
+        }
+
+        let reply = match (line.parse::<Filter>(), line.parse::<Query>()) {
+            _ if line.is_empty() => Vec::new(),
+            (Ok(filter), _) => {
+                *client.filter.lock().unwrap() = filter;
+                Vec::new()
+            }
+            (_, Ok(query)) => shared.history.lock().unwrap().answer(&query),
+            (Err(CommandError::UnknownCommand(_)), Err(e)) | (Err(e), _) => vec![e.to_string()],
+        };
+        let reply: String = reply.iter().map(|l| format!("{}\r\n", l)).collect();
+        client.send(&format!("{}{}", reply, prompt))?;
+    }
+    Ok(())
+}
+
+/// Read the next line of a client, cleaned from telnet commands and control characters.
+/// At the end of the stream `None` is returned.
+fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    let res = sanitize_bytes(&line);
+    Ok(Some(String::from_utf8_lossy(&res.line).trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::client::ClusterClient;
+    use crate::fixtures::{SPOT, SPOT2};
+    use crate::mock::{MockCluster, Recording};
+    use std::io::Read;
+
+    const WWV: &str = "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms";
+
+    /// Downstream client speaking plain telnet, like a logger
+    struct Logger {
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
+        prompt: String,
+    }
+
+    impl Logger {
+        fn login(relay: &Relay, call: &str) -> Self {
+            let stream = TcpStream::connect(relay.addr()).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let reader = BufReader::new(stream.try_clone().unwrap());
+            let mut logger = Logger {
+                stream,
+                reader,
+                prompt: format!("{} de DL1ABC-2 >", call.to_ascii_uppercase()),
+            };
+            assert!(logger
+                .read_until("login: ")
+                .starts_with("Welcome to DL1ABC-2"));
+            logger.command(call);
+            logger
+        }
+
+        /// Send a command and get the reply in front of the prompt.
+        fn command(&mut self, command: &str) -> String {
+            write!(self.stream, "{}\r\n", command).unwrap();
+            let prompt = self.prompt.clone();
+            let reply = self.read_until(&prompt);
+            String::from(reply.strip_suffix(&prompt).unwrap())
+        }
+
+        fn read_until(&mut self, end: &str) -> String {
+            let mut buf = Vec::new();
+            while !buf.ends_with(end.as_bytes()) {
+                let mut byte = [0];
+                self.reader.read_exact(&mut byte).unwrap();
+                buf.push(byte[0]);
+            }
+            String::from_utf8(buf).unwrap()
+        }
+
+        fn lines(&mut self, n: usize) -> Vec<String> {
+            (0..n)
+                .map(|_| {
+                    let mut line = String::new();
+                    self.reader.read_line(&mut line).unwrap();
+                    String::from(line.trim_end())
+                })
+                .collect()
+        }
+    }
+
+    fn relay() -> Relay {
+        Relay::builder()
+            .node("DL1ABC-2")
+            .bind("127.0.0.1:0")
+            .unwrap()
+    }
+
+    #[test]
+    fn relay_filters() {
+        let relay = relay();
+        let mut all = Logger::login(&relay, "DL1ABC-3");
+        let mut hf20 = Logger::login(&relay, "dl1abc-4");
+        assert_eq!(hf20.command("acc/spots on 20m"), "");
+        let mut no20 = Logger::login(&relay, "DL1ABC-5");
+        assert_eq!(no20.command("SET/FILTER DXBAND/REJECT 20m"), "");
+        assert_eq!(relay.clients(), vec!["DL1ABC-3", "DL1ABC-4", "DL1ABC-5"]);
+
+        let upstream = MockCluster::builder()
+            .replay(
+                Recording::new()
+                    .line(Duration::ZERO, SPOT)
+                    .line(Duration::ZERO, SPOT2)
+                    .line(Duration::ZERO, WWV),
+            )
+            .bind("127.0.0.1:0")
+            .unwrap();
+        let mut client = ClusterClient::builder("DL1ABC")
+            .read_timeout(Duration::from_millis(500))
+            .connect(upstream.addr())
+            .unwrap();
+        assert_eq!(relay.forward(client.by_ref()), 3);
+
+        assert_eq!(all.lines(3), vec![SPOT, SPOT2, WWV]);
+        assert_eq!(hf20.lines(2), vec![SPOT2, WWV]);
+        assert_eq!(no20.lines(2), vec![SPOT, WWV]);
+    }
+
+    #[test]
+    fn relay_commands() {
+        let relay = relay();
+        let mut logger = Logger::login(&relay, "DL1ABC-3");
+        assert_eq!(
+            logger.command("accept/spots on 11m"),
+            "Invalid band: 11m\r\n"
+        );
+        assert_eq!(logger.command("sh/users"), "Unknown command: sh/users\r\n");
+        assert_eq!(logger.command("sh/dx on 11m"), "Invalid band: 11m\r\n");
+        assert_eq!(logger.command(""), "");
+
+        write!(logger.stream, "bye\r\n").unwrap();
+        assert_eq!(logger.reader.read(&mut [0]).unwrap(), 0);
+        assert!(relay.clients().is_empty());
+        assert_eq!(relay.broadcast(&crate::parse(SPOT).unwrap()), 0);
+    }
+
+    #[test]
+    fn relay_invalid_call() {
+        let relay = relay();
+        let mut stream = TcpStream::connect(relay.addr()).unwrap();
+        write!(stream, "\r\n").unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert!(reply.ends_with("login: Sorry,  is not a valid callsign\r\n"));
+    }
+
+    #[test]
+    fn relay_show() {
+        let relay = relay();
+        for line in [SPOT, SPOT2, WWV] {
+            relay.broadcast(&crate::parse(line).unwrap());
+        }
+
+        let mut logger = Logger::login(&relay, "DL1ABC-3");
+        let reply = logger.command("sh/dx");
+        let lines: Vec<_> = reply.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(" 14036.1  W0BH "));
+        assert!(lines[1].starts_with("  3780.0  OH5Z "));
+        assert!(lines[1].ends_with("<DJ1TO>"));
+
+        assert_eq!(logger.command("SH/DX OH5Z").lines().count(), 1);
+        assert_eq!(logger.command("SH/DX on 20m").lines().count(), 1);
+        assert_eq!(logger.command("SH/DX 3B9FR"), "");
+        let reply = logger.command("SH/WWV");
+        assert_eq!(reply.lines().count(), 2);
+        assert!(reply.contains("No Storms -> No Storms"));
+    }
+}
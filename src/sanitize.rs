@@ -0,0 +1,354 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+const BEL: u8 = 0x07;
+const CR: u8 = 0x0D;
+const ESC: u8 = 0x1B;
+const DEL: u8 = 0x7F;
+
+// Telnet commands, see RFC 854
+pub(crate) const SE: u8 = 0xF0;
+pub(crate) const SB: u8 = 0xFA;
+pub(crate) const WILL: u8 = 0xFB;
+pub(crate) const DONT: u8 = 0xFE;
+pub(crate) const IAC: u8 = 0xFF;
+
+/// Prefixes of the built-in types of spots, a line starting with one of them is not searched for an embedded DX spot
+const PREFIXES: &[&[u8]] = &[
+    b"DX de",
+    b"WWV de",
+    b"WCY de",
+    b"WX de",
+    b"To ALL de",
+    b"To LOCAL de",
+    b"To Local de",
+];
+
+/// Noise removed from a line by the sanitizer
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Removed {
+    /// Number of ANSI/VT100 escape sequences, e.g. colour codes
+    pub ansi: usize,
+
+    /// Number of bell characters
+    pub bell: usize,
+
+    /// Number of telnet commands, e.g. option negotiations
+    pub telnet: usize,
+
+    /// Number of carriage returns
+    pub cr: usize,
+
+    /// Number of other control characters
+    pub control: usize,
+
+    /// Text in front of an embedded DX spot, e.g. a prompt of the cluster
+    pub prefix: Option<String>,
+}
+
+impl Removed {
+    /// Check whether nothing but surrounding whitespace was removed.
+    pub fn is_empty(&self) -> bool {
+        *self == Removed::default()
+    }
+}
+
+/// Line cleaned by the sanitizer
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Sanitized<'a> {
+    /// Cleaned line, borrowed from the raw line in case only its start or end was removed
+    pub line: Cow<'a, str>,
+
+    /// Removed noise
+    pub removed: Removed,
+}
+
+/// Line of raw bytes cleaned by the sanitizer
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SanitizedBytes<'a> {
+    /// Cleaned line, borrowed from the raw line in case only its start or end was removed
+    pub line: Cow<'a, [u8]>,
+
+    /// Removed noise
+    pub removed: Removed,
+}
+
+/// Clean a line received from a DX Cluster before parsing it.
+///
+/// Removes ANSI/VT100 escape sequences, bell characters, carriage returns and other control characters except tabs
+/// as well as surrounding whitespace.
+/// In case the line does not start with a known type of spot but holds an embedded DX spot, the text in front of it is removed.
+///
+/// ## Arguments
+///
+/// * `raw`: A raw line
+///
+/// ## Result
+///
+/// The cleaned line together with the removed noise shall be returned.
+pub fn sanitize(raw: &str) -> Sanitized<'_> {
+    let res = sanitize_bytes(raw.as_bytes());
+
+    // Only ASCII characters are removed, the line therefore stays valid UTF-8
+    let line = match res.line {
+        Cow::Borrowed(line) => Cow::Borrowed(std::str::from_utf8(line).unwrap()),
+        Cow::Owned(line) => Cow::Owned(String::from_utf8(line).unwrap()),
+    };
+
+    Sanitized {
+        line,
+        removed: res.removed,
+    }
+}
+
+/// Clean a line of raw bytes received from a DX Cluster before parsing it.
+///
+/// In addition to [`sanitize`], telnet commands are removed.
+///
+/// ## Arguments
+///
+/// * `raw`: A raw line
+///
+/// ## Result
+///
+/// The cleaned line together with the removed noise shall be returned.
+pub fn sanitize_bytes(raw: &[u8]) -> SanitizedBytes<'_> {
+    let mut removed = Removed::default();
+    let kept = strip(raw, &mut removed);
+
+    let mut line = match kept.as_slice() {
+        [] => Cow::Borrowed(&raw[..0]),
+        [range] => Cow::Borrowed(&raw[range.clone()]),
+        _ => Cow::Owned(kept.into_iter().flat_map(|r| &raw[r]).copied().collect()),
+    };
+
+    let start = line
+        .iter()
+        .position(|b| !is_blank(*b))
+        .unwrap_or(line.len());
+    let end = line
+        .iter()
+        .rposition(|b| !is_blank(*b))
+        .map_or(start, |p| p + 1);
+    line = narrow(line, start..end);
+
+    if !is_spot(&line) {
+        if let Some(pos) = line.windows(6).position(|w| w == b"DX de ") {
+            let prefix = String::from_utf8_lossy(&line[..pos]);
+            removed.prefix = Some(String::from(prefix.trim_end()));
+            let len = line.len();
+            line = narrow(line, pos..len);
+        }
+    }
+
+    SanitizedBytes { line, removed }
+}
+
+/// Check whether a line starts with a known type of spot.
+pub(crate) fn is_spot(line: &[u8]) -> bool {
+    PREFIXES.iter().any(|p| line.starts_with(p))
+}
+
+/// Get the ranges of a line to keep, counting everything removed in between.
+fn strip(raw: &[u8], removed: &mut Removed) -> Vec<Range<usize>> {
+    let mut kept = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < raw.len() {
+        let len = match raw[i] {
+            ESC => {
+                removed.ansi += 1;
+                escape_len(&raw[i..])
+            }
+            BEL => {
+                removed.bell += 1;
+                1
+            }
+            CR => {
+                removed.cr += 1;
+                1
+            }
+            IAC if raw.get(i + 1) == Some(&IAC) => {
+                // Escaped data byte, only the first one is removed
+                kept.push(start..i);
+                start = i + 1;
+                i += 2;
+                continue;
+            }
+            IAC => {
+                removed.telnet += 1;
+                telnet_len(&raw[i..])
+            }
+            b if (b < 0x20 && b != b'\t') || b == DEL => {
+                removed.control += 1;
+                1
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        kept.push(start..i);
+        i += len;
+        start = i;
+    }
+    kept.push(start..raw.len());
+
+    kept.retain(|r| !r.is_empty());
+    kept
+}
+
+/// Get the length of an escape sequence starting with ESC.
+/// A sequence is cut short at the first unexpected byte, which is then kept.
+fn escape_len(seq: &[u8]) -> usize {
+    let len = |from: usize, class: fn(u8) -> bool| {
+        from + seq[from.min(seq.len())..]
+            .iter()
+            .take_while(|&&b| class(b))
+            .count()
+    };
+
+    match seq.get(1) {
+        // Control sequence, e.g. colours: parameters, intermediates and a final byte
+        Some(b'[') => {
+            let n = len(2, |b| (0x30..=0x3F).contains(&b));
+            let n = len(n, |b| (0x20..=0x2F).contains(&b));
+            n + usize::from(seq.get(n).is_some_and(|b| (0x40..=0x7E).contains(b)))
+        }
+        // Operating system command, e.g. window title: terminated by BEL or ESC \
+        Some(b']') => {
+            let n = len(2, |b| (0x20..=0x7E).contains(&b));
+            match seq.get(n) {
+                Some(&BEL) => n + 1,
+                Some(&ESC) if seq.get(n + 1) == Some(&b'\\') => n + 2,
+                _ => n,
+            }
+        }
+        // Character set designations etc.: intermediates and a final byte
+        Some(0x20..=0x2F) => {
+            let n = len(1, |b| (0x20..=0x2F).contains(&b));
+            n + usize::from(seq.get(n).is_some_and(|b| (0x30..=0x7E).contains(b)))
+        }
+        Some(0x30..=0x7E) => 2,
+        _ => 1,
+    }
+}
+
+/// Get the length of a telnet command starting with IAC.
+fn telnet_len(cmd: &[u8]) -> usize {
+    match cmd.get(1) {
+        // Option negotiation: WILL, WONT, DO or DONT followed by the option
+        Some(WILL..=DONT) => cmd.len().min(3),
+        // Subnegotiation up to and including IAC SE
+        Some(&SB) => cmd
+            .windows(2)
+            .position(|w| w == [IAC, SE])
+            .map_or(cmd.len(), |p| p + 2),
+        Some(_) => 2,
+        None => 1,
+    }
+}
+
+fn is_blank(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+fn narrow(line: Cow<'_, [u8]>, range: Range<usize>) -> Cow<'_, [u8]> {
+    match line {
+        Cow::Borrowed(line) => Cow::Borrowed(&line[range]),
+        Cow::Owned(mut line) => {
+            line.truncate(range.end);
+            line.drain(..range.start);
+            Cow::Owned(line)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::fixtures::SPOT;
+
+    #[test]
+    fn sanitize_clean() {
+        let res = sanitize(SPOT);
+        assert_eq!(res.line, Cow::Borrowed(SPOT));
+        assert!(res.removed.is_empty());
+    }
+
+    #[test]
+    fn sanitize_bell_cr() {
+        let raw = format!("{}\u{0007}\u{0007}\r\r\n", SPOT);
+        let res = sanitize(&raw);
+        assert!(matches!(res.line, Cow::Borrowed(SPOT)));
+        assert_eq!(res.removed.bell, 2);
+        assert_eq!(res.removed.cr, 2);
+        assert_eq!(res.removed.control, 1);
+    }
+
+    #[test]
+    fn sanitize_ansi() {
+        let raw = "\u{1b}[0;32mDX de\u{1b}[0m DJ1TO:      3780.0  \u{1b}[1;33mOH5Z\u{1b}[0m         LSB                            2200Z JO62\u{1b}(B";
+        let res = sanitize(raw);
+        assert_eq!(res.line, SPOT);
+        assert_eq!(res.removed.ansi, 5);
+        assert_eq!(crate::parse(&res.line), crate::parse(SPOT));
+    }
+
+    #[test]
+    fn sanitize_prompt() {
+        let raw = format!(
+            "DL1ABC de DB0SUE-7 10-Sep-2022 2200Z dxspider >\u{0007}{}",
+            SPOT
+        );
+        let res = sanitize(&raw);
+        assert_eq!(res.line, SPOT);
+        assert_eq!(
+            res.removed.prefix.as_deref(),
+            Some("DL1ABC de DB0SUE-7 10-Sep-2022 2200Z dxspider >")
+        );
+        assert_eq!(res.removed.bell, 1);
+    }
+
+    #[test]
+    fn sanitize_no_embedded_in_announcement() {
+        let raw = "To ALL de CT2IDL <1044Z> : DX de is the prefix of a spot";
+        let res = sanitize(raw);
+        assert_eq!(res.line, raw);
+        assert_eq!(res.removed.prefix, None);
+    }
+
+    #[test]
+    fn sanitize_telnet() {
+        let mut raw = b"\xff\xfb\x01\xff\xfd\x03\xff\xfa\x18\x01\xff\xf0".to_vec();
+        raw.extend_from_slice(SPOT.as_bytes());
+        raw.extend_from_slice(b"\xff\xf1\r\n");
+        let res = sanitize_bytes(&raw);
+        assert_eq!(res.line, SPOT.as_bytes());
+        assert_eq!(res.removed.telnet, 4);
+        assert_eq!(res.removed.cr, 1);
+    }
+
+    #[test]
+    fn sanitize_telnet_escaped_data() {
+        let res = sanitize_bytes(b"WX de DL1ABC: \xff\xff");
+        assert_eq!(res.line, &b"WX de DL1ABC: \xff"[..]);
+    }
+
+    #[test]
+    fn sanitize_unterminated() {
+        let res = sanitize("WX de DL1ABC: sunny\u{1b}[1;3");
+        assert_eq!(res.line, "WX de DL1ABC: sunny");
+        let res = sanitize_bytes(b"WX de DL1ABC: sunny\xff\xfa\x18");
+        assert_eq!(res.line, &b"WX de DL1ABC: sunny"[..]);
+        let res = sanitize("\u{1b}[0;32m\u{1b}");
+        assert_eq!(res.line, "");
+    }
+}
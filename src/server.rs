@@ -0,0 +1,158 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// TCP server serving each client on its own thread, used by the [`crate::MockCluster`] and the [`crate::Relay`]
+///
+/// The socket of a client is closed as soon as its session ends.
+/// The server stops listening, disconnects all clients and waits for their sessions as soon as it is dropped.
+pub(crate) struct Server {
+    addr: SocketAddr,
+    conns: Arc<Connections>,
+    accept: Option<JoinHandle<()>>,
+}
+
+/// Connections of the clients, shared by the listener and the sessions
+#[derive(Default)]
+struct Connections {
+    stop: AtomicBool,
+    streams: Mutex<HashMap<u64, TcpStream>>,
+    sessions: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Server {
+    /// Start listening for clients.
+    ///
+    /// ## Arguments
+    ///
+    /// * `addr`: Address to listen on, e.g. `127.0.0.1:7300`
+    /// * `serve`: Session of a single client, called on a thread of its own
+    ///
+    /// ## Result
+    ///
+    /// In case the server listens, the running server shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub(crate) fn bind<A, F>(addr: A, serve: F) -> io::Result<Server>
+    where
+        A: ToSocketAddrs,
+        F: Fn(TcpStream) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let conns = Arc::new(Connections::default());
+
+        let accept = {
+            let conns = conns.clone();
+            thread::spawn(move || accept(listener, conns, Arc::new(serve)))
+        };
+
+        Ok(Server {
+            addr,
+            conns,
+            accept: Some(accept),
+        })
+    }
+
+    /// Get the address the server listens on.
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.conns.stop.store(true, Ordering::SeqCst);
+
+        // Wake up the listener waiting for the next client
+        let _ = TcpStream::connect(self.addr);
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+
+        for (_, stream) in self.conns.streams.lock().unwrap().drain() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        let sessions: Vec<_> = self.conns.sessions.lock().unwrap().drain(..).collect();
+        for session in sessions {
+            let _ = session.join();
+        }
+    }
+}
+
+fn accept<F>(listener: TcpListener, conns: Arc<Connections>, serve: Arc<F>)
+where
+    F: Fn(TcpStream) + Send + Sync + 'static,
+{
+    for (id, stream) in (0..).zip(listener.incoming()) {
+        if conns.stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else { continue };
+        let Ok(clone) = stream.try_clone() else {
+            continue;
+        };
+        conns.streams.lock().unwrap().insert(id, clone);
+
+        // Sessions of clients that already left are done, only the others are joined on drop
+        let mut sessions = conns.sessions.lock().unwrap();
+        sessions.retain(|s| !s.is_finished());
+        sessions.push({
+            let conns = conns.clone();
+            let serve = serve.clone();
+            thread::spawn(move || {
+                serve(stream);
+                conns.streams.lock().unwrap().remove(&id);
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Read;
+    use std::time::{Duration, Instant};
+
+    fn wait_until(cond: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !cond() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn server_closes_left_clients() {
+        let server = Server::bind("127.0.0.1:0", |mut stream| {
+            let _ = stream.read_to_end(&mut Vec::new());
+        })
+        .unwrap();
+        let streams = || server.conns.streams.lock().unwrap().len();
+
+        let clients: Vec<_> = (0..3)
+            .map(|_| TcpStream::connect(server.addr()).unwrap())
+            .collect();
+        wait_until(|| streams() == 3);
+        drop(clients);
+        wait_until(|| streams() == 0);
+
+        // Finished sessions are dropped with the next client
+        let sessions = || server.conns.sessions.lock().unwrap();
+        wait_until(|| sessions().iter().all(|s| s.is_finished()));
+        let client = TcpStream::connect(server.addr()).unwrap();
+        wait_until(|| streams() == 1);
+        assert_eq!(sessions().len(), 1);
+
+        // Remaining clients are disconnected on drop
+        drop(server);
+        assert_eq!((&client).read(&mut [0; 16]).unwrap(), 0);
+    }
+}
@@ -0,0 +1,811 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::band::Band;
+use crate::callinfo::CallInfo;
+use crate::command::abbreviates;
+use crate::error::ExprError;
+use crate::expr::{negate, Descent, Expr, Number, Side, Test};
+use crate::mode::Mode;
+use crate::types::{Spot, SpotType};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// Number of filter slots of each type of spots
+pub const FILTER_SLOTS: u8 = 10;
+
+/// Slot used in case a filter command names none
+pub const DEFAULT_FILTER_SLOT: u8 = 1;
+
+/// Type of spots a filter of DXSpider applies to
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum FilterTarget {
+    /// DX spots, `accept/spots`
+    Spots,
+
+    /// Announcements including WX, `accept/announce`
+    Announce,
+
+    /// WWV, `accept/wwv`
+    Wwv,
+
+    /// WCY, `accept/wcy`
+    Wcy,
+}
+
+impl FilterTarget {
+    /// Get the target filtering the given spot, `None` is returned for custom spots.
+    pub fn of(spot: &Spot) -> Option<FilterTarget> {
+        match spot.spot_type()? {
+            SpotType::DX => Some(FilterTarget::Spots),
+            SpotType::WWV => Some(FilterTarget::Wwv),
+            SpotType::WCY => Some(FilterTarget::Wcy),
+            SpotType::WX | SpotType::ToAll | SpotType::ToLocal => Some(FilterTarget::Announce),
+        }
+    }
+
+    /// Get the name of the target as used in the commands, e.g. `spots`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterTarget::Spots => "spots",
+            FilterTarget::Announce => "announce",
+            FilterTarget::Wwv => "wwv",
+            FilterTarget::Wcy => "wcy",
+        }
+    }
+
+    /// Parse the name of a target, which may be abbreviated like `ann`.
+    fn parse(word: &str) -> Option<FilterTarget> {
+        match word {
+            w if abbreviates(w, "spots", 2) => Some(FilterTarget::Spots),
+            w if abbreviates(w, "announce", 3) => Some(FilterTarget::Announce),
+            "wwv" => Some(FilterTarget::Wwv),
+            "wcy" => Some(FilterTarget::Wcy),
+            _ => None,
+        }
+    }
+
+    /// Get the names of the fields supported for the target.
+    fn fields(&self) -> &'static str {
+        match self {
+            FilterTarget::Spots => {
+                "on, call, call_dxcc, call_itu, call_zone, by, by_dxcc, by_itu, by_zone, info"
+            }
+            FilterTarget::Announce => "by, by_dxcc, by_itu, by_zone, info, wx",
+            FilterTarget::Wwv | FilterTarget::Wcy => "by, by_dxcc, by_itu, by_zone",
+        }
+    }
+}
+
+impl fmt::Display for FilterTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Whether a filter of DXSpider passes or drops the matching spots
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum FilterAction {
+    /// Pass the matching spots, `accept/...`
+    Accept,
+
+    /// Drop the matching spots, `reject/...`
+    Reject,
+}
+
+/// Single filter of DXSpider as set by `accept/<target>` or `reject/<target>`, e.g. `accept/spots 2 on hf/cw and not by_zone 14`
+///
+/// The pattern consists of terms like `on hf/cw` or `by G,M`, combined with `and`, `or`, `not` and parentheses.
+/// The values of a term are separated by commas and match if any of them matches.
+/// Parentheses may be nested up to [`crate::MAX_NESTING`] levels.
+///
+/// | Term | Value |
+/// |------|-------|
+/// | `on` | Band like `20m` or `hf`, `vhf`, `uhf`, optionally with the mode like `hf/cw`, `20m/ssb` or `hf/data`, or a frequency range in kHz like `14000/14070` |
+/// | `call`, `by` | Prefix of the DX call or the spotter, e.g. `G` matches `G4ABC` |
+/// | `call_dxcc`, `by_dxcc` | DXCC entity number of the DX station or the spotter |
+/// | `call_itu`, `by_itu` | ITU zone, e.g. `28` or `27-29` |
+/// | `call_zone`, `by_zone` | CQ zone, e.g. `14` or `14-16` |
+/// | `info` | Text within the comment of a DX spot or an announcement |
+/// | `wx` | `1` for weather announcements, `0` for others |
+///
+/// The mode is taken from the comment of a spot or the band plan, see [`Mode::of`].
+/// Entities and zones are looked up by a [`CallInfo`], see [`SpiderFilter::passes_with`].
+#[derive(Debug, Clone)]
+pub struct SpiderRule {
+    /// Whether the rule passes or drops the matching spots
+    pub action: FilterAction,
+
+    /// Type of spots the rule applies to
+    pub target: FilterTarget,
+
+    /// Slot of the rule, from 0 to 9
+    pub slot: u8,
+
+    pattern: String,
+    expr: Expr,
+}
+
+impl SpiderRule {
+    /// Get the pattern of the rule, e.g. `on hf/cw`.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Check whether a spot of the target of the rule matches the pattern, looking up callsigns by the given information.
+    fn matches(&self, spot: &Spot, info: Option<&dyn CallInfo>) -> bool {
+        self.expr.eval(spot, info)
+    }
+}
+
+impl FromStr for SpiderRule {
+    type Err = ExprError;
+
+    /// Parse a filter command like `accept/spots on hf/cw` or `rej/ann 2 by G,M`, the case is ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_command(s)? {
+            SpiderCommand::Rule(rule) => Ok(rule),
+            SpiderCommand::Clear(..) => Err(ExprError::Unexpected {
+                pos: s.len() - s.trim_start().len(),
+                found: String::from(s.split_whitespace().next().unwrap_or_default()),
+                expected: "accept/ or reject/",
+            }),
+        }
+    }
+}
+
+impl fmt::Display for SpiderRule {
+    /// Write the rule as command, e.g. `accept/spots 1 on hf/cw`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let action = match self.action {
+            FilterAction::Accept => "accept",
+            FilterAction::Reject => "reject",
+        };
+        write!(
+            f,
+            "{}/{} {} {}",
+            action, self.target, self.slot, self.pattern
+        )
+    }
+}
+
+/// Filters of DXSpider for all types of spots, set by the filter commands known from the node
+///
+/// Each type of spots has 10 slots, each holding up to one accept and one reject filter.
+/// The slots are checked in ascending order, within a slot the reject filter is checked before the accept filter.
+/// The first matching filter decides, i.e. a spot is dropped by a matching reject filter and passed by a matching accept filter.
+/// A spot matching no filter is dropped in case an accept filter for its type exists, otherwise it is passed.
+///
+/// ```
+/// use dxclparser::SpiderFilter;
+///
+/// let mut filter = SpiderFilter::new();
+/// filter.apply("accept/spots on hf/cw").unwrap();
+/// filter.apply("reject/spots 0 call K,W").unwrap();
+///
+/// let spot = |line| dxclparser::parse(line).unwrap();
+/// assert!(filter.passes(&spot("DX de DL1ABC:     14025.0  3B9FR        599                            1812Z")));
+/// assert!(!filter.passes(&spot("DX de DL1ABC:     14025.0  W0BH         599                            1812Z")));
+/// assert!(!filter.passes(&spot("DX de DL1ABC:     14205.0  3B9FR        599                            1812Z")));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SpiderFilter {
+    rules: Vec<SpiderRule>,
+}
+
+impl SpiderFilter {
+    /// Create a filter passing all spots.
+    pub fn new() -> Self {
+        SpiderFilter { rules: Vec::new() }
+    }
+
+    /// Apply a filter command, i.e. set a filter by `accept/<target>` or `reject/<target>` or remove filters by `clear/<target>`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `command`: Filter command like `accept/spots 2 on hf/cw`, `clear/spots 2` or `clear/announce all`
+    ///
+    /// ## Result
+    ///
+    /// In case the command is valid, `Ok` shall be returned.
+    /// In case the command is invalid, the error shall be returned and the filters are left unchanged.
+    pub fn apply(&mut self, command: &str) -> Result<(), ExprError> {
+        match parse_command(command)? {
+            SpiderCommand::Rule(rule) => self.insert(rule),
+            SpiderCommand::Clear(target, slot) => self.clear(target, slot),
+        }
+        Ok(())
+    }
+
+    /// Set a filter, replacing the filter of the same action in the same slot.
+    pub fn insert(&mut self, rule: SpiderRule) {
+        self.rules
+            .retain(|r| (r.action, r.target, r.slot) != (rule.action, rule.target, rule.slot));
+        self.rules.push(rule);
+    }
+
+    /// Remove the filters of a type of spots, either of a single slot or of all slots.
+    pub fn clear(&mut self, target: FilterTarget, slot: Option<u8>) {
+        self.rules
+            .retain(|r| r.target != target || slot.is_some_and(|s| s != r.slot));
+    }
+
+    /// Get the filters, in the order they were set.
+    pub fn rules(&self) -> &[SpiderRule] {
+        &self.rules
+    }
+
+    /// Check whether a spot passes the filters, terms needing a lookup of the callsign never match.
+    pub fn passes(&self, spot: &Spot) -> bool {
+        self.check(spot, None)
+    }
+
+    /// Check whether a spot passes the filters, looking up callsigns by the given information.
+    pub fn passes_with(&self, spot: &Spot, info: &dyn CallInfo) -> bool {
+        self.check(spot, Some(info))
+    }
+
+    fn check(&self, spot: &Spot, info: Option<&dyn CallInfo>) -> bool {
+        let Some(target) = FilterTarget::of(spot) else {
+            return true;
+        };
+        let rule = |slot, action| {
+            self.rules
+                .iter()
+                .find(|r| (r.target, r.slot, r.action) == (target, slot, action))
+        };
+
+        for slot in 0..FILTER_SLOTS {
+            if rule(slot, FilterAction::Reject).is_some_and(|r| r.matches(spot, info)) {
+                return false;
+            }
+            if rule(slot, FilterAction::Accept).is_some_and(|r| r.matches(spot, info)) {
+                return true;
+            }
+        }
+        !self
+            .rules
+            .iter()
+            .any(|r| r.target == target && r.action == FilterAction::Accept)
+    }
+}
+
+/// Parsed filter command
+enum SpiderCommand {
+    Rule(SpiderRule),
+    Clear(FilterTarget, Option<u8>),
+}
+
+/// Word of a filter command
+struct Word<'a> {
+    pos: usize,
+    text: &'a str,
+}
+
+/// Parse a filter command, see [`SpiderFilter::apply`].
+fn parse_command(command: &str) -> Result<SpiderCommand, ExprError> {
+    let mut words = split(command).into_iter().peekable();
+    let unknown = |word: &Word| ExprError::Unexpected {
+        pos: word.pos,
+        found: String::from(word.text),
+        expected: "accept/, reject/ or clear/ followed by spots, announce, wwv or wcy",
+    };
+
+    let Some(head) = words.next() else {
+        return Err(ExprError::Empty);
+    };
+    let lower = head.text.to_ascii_lowercase();
+    let Some((verb, target)) = lower.split_once('/') else {
+        return Err(unknown(&head));
+    };
+    let target = FilterTarget::parse(target).ok_or_else(|| unknown(&head))?;
+    let action = match verb {
+        v if abbreviates(v, "accept", 3) => FilterAction::Accept,
+        v if abbreviates(v, "reject", 3) => FilterAction::Reject,
+        v if abbreviates(v, "clear", 3) => {
+            let slot = match words.next() {
+                None => None,
+                Some(w) if w.text.eq_ignore_ascii_case("all") => None,
+                Some(w) => Some(slot(&w)?),
+            };
+            if let Some(word) = words.next() {
+                return Err(ExprError::Unexpected {
+                    pos: word.pos,
+                    found: String::from(word.text),
+                    expected: "the end of the command",
+                });
+            }
+            return Ok(SpiderCommand::Clear(target, slot));
+        }
+        _ => return Err(unknown(&head)),
+    };
+
+    let slot = match words.peek() {
+        Some(w) if w.text.bytes().all(|b| b.is_ascii_digit()) => slot(&words.next().unwrap())?,
+        _ => DEFAULT_FILTER_SLOT,
+    };
+    let words: Vec<_> = words.collect();
+    let Some(first) = words.first() else {
+        return Err(ExprError::UnexpectedEnd("a filter like on hf/cw"));
+    };
+
+    let mut compiler = Compiler {
+        words: &words,
+        next: 0,
+        target,
+    };
+    let expr = compiler.or(0)?;
+    if let Some(word) = compiler.peek() {
+        return Err(ExprError::Unexpected {
+            pos: word.pos,
+            found: String::from(word.text),
+            expected: "and, or or the end of the filter",
+        });
+    }
+
+    Ok(SpiderCommand::Rule(SpiderRule {
+        action,
+        target,
+        slot,
+        pattern: String::from(command[first.pos..].trim_end()),
+        expr,
+    }))
+}
+
+/// Parse the number of a slot.
+fn slot(word: &Word) -> Result<u8, ExprError> {
+    word.text
+        .parse()
+        .ok()
+        .filter(|s| *s < FILTER_SLOTS)
+        .ok_or_else(|| ExprError::InvalidValue {
+            pos: word.pos,
+            value: String::from(word.text),
+            field: "slot",
+            expected: "a slot from 0 to 9",
+        })
+}
+
+/// Split a command into words at whitespace and parentheses.
+/// Lists of values separated by commas are joined into one word, even if there are spaces after the commas.
+fn split(command: &str) -> Vec<Word<'_>> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut start = None;
+
+    for (pos, c) in command.char_indices().chain([(command.len(), ' ')]) {
+        let boundary = c.is_whitespace() || c == '(' || c == ')';
+        if boundary {
+            if let Some(s) = start.take() {
+                match words.last_mut() {
+                    Some(last) if last.text.ends_with(',') => {
+                        last.text = &command[last.pos..pos];
+                    }
+                    _ => words.push(Word {
+                        pos: s,
+                        text: &command[s..pos],
+                    }),
+                }
+            }
+            if c == '(' || c == ')' {
+                words.push(Word {
+                    pos,
+                    text: &command[pos..pos + 1],
+                });
+            }
+        } else if start.is_none() {
+            start = Some(pos);
+        }
+    }
+    words
+}
+
+/// Recursive descent parser of the pattern of a filter
+struct Compiler<'a, 'b> {
+    words: &'b [Word<'a>],
+    next: usize,
+    target: FilterTarget,
+}
+
+impl Compiler<'_, '_> {
+    fn peek(&self) -> Option<&Word<'_>> {
+        self.words.get(self.next)
+    }
+}
+
+impl Descent for Compiler<'_, '_> {
+    const OR: &'static [&'static str] = &["or"];
+    const AND: &'static [&'static str] = &["and"];
+    const NOT: &'static [&'static str] = &["not"];
+
+    fn accept(&mut self, keywords: &[&str]) -> bool {
+        let found = self
+            .peek()
+            .is_some_and(|w| keywords.iter().any(|k| w.text.eq_ignore_ascii_case(k)));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn position(&self) -> Option<usize> {
+        self.peek().map(|w| w.pos)
+    }
+
+    fn unexpected(&self, expected: &'static str) -> ExprError {
+        match self.peek() {
+            Some(word) => ExprError::Unexpected {
+                pos: word.pos,
+                found: String::from(word.text),
+                expected,
+            },
+            None => ExprError::UnexpectedEnd(expected),
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        let Some(field) = self.peek().filter(|w| w.text != "(" && w.text != ")") else {
+            return Err(self.unexpected("a filter like on hf/cw"));
+        };
+        let (pos, name) = (field.pos, field.text.to_ascii_lowercase());
+        let unknown = || ExprError::UnknownField {
+            pos,
+            field: name.clone(),
+            expected: self.target.fields(),
+        };
+        let spots = self.target == FilterTarget::Spots;
+        let announce = self.target == FilterTarget::Announce;
+        let field: &'static str = match name.as_str() {
+            "on" | "freq" if spots => "on",
+            "call" if spots => "call",
+            "call_dxcc" if spots => "call_dxcc",
+            "call_itu" if spots => "call_itu",
+            "call_zone" if spots => "call_zone",
+            "info" if spots || announce => "info",
+            "wx" if announce => "wx",
+            "by" => "by",
+            "by_dxcc" => "by_dxcc",
+            "by_itu" => "by_itu",
+            "by_zone" => "by_zone",
+            _ => return Err(unknown()),
+        };
+        self.next += 1;
+
+        let value = match self.peek() {
+            Some(w) if w.text != "(" && w.text != ")" => self.words[self.next].pos,
+            _ => return Err(self.unexpected("a value")),
+        };
+        let text = self.words[self.next].text;
+        self.next += 1;
+
+        // Values separated by commas, with their positions
+        let values: Vec<(usize, &str)> = text
+            .split(',')
+            .scan(value, |pos, v| {
+                let start = *pos;
+                *pos += v.len() + 1;
+                Some((start, v))
+            })
+            .map(|(pos, v)| (pos + v.len() - v.trim_start().len(), v.trim()))
+            .filter(|(_, v)| !v.is_empty())
+            .collect();
+        let invalid = |(pos, value): (usize, &str), expected| ExprError::InvalidValue {
+            pos,
+            value: String::from(value),
+            field,
+            expected,
+        };
+
+        let test = |test| Expr::Test(test);
+        let exprs = match field {
+            "on" => values
+                .iter()
+                .map(|v| {
+                    frequencies(v.1).ok_or_else(|| {
+                        invalid(*v, "a band like 20m or hf, optionally with a mode like hf/cw, or a range in kHz like 14000/14070")
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            "call" | "by" => {
+                let side = if field == "call" { Side::Dx } else { Side::De };
+                let prefixes = values
+                    .iter()
+                    .map(|(_, v)| match v.contains('*') {
+                        true => v.to_ascii_uppercase(),
+                        false => format!("{}*", v.to_ascii_uppercase()),
+                    })
+                    .collect();
+                vec![test(Test::Call(side, prefixes))]
+            }
+            "info" => values
+                .iter()
+                .map(|(_, v)| test(Test::Contains(v.to_uppercase())))
+                .collect(),
+            "wx" => match values.as_slice() {
+                [(_, "1")] => vec![test(Test::Type(vec![SpotType::WX]))],
+                [(_, "0")] => vec![negate(test(Test::Type(vec![SpotType::WX])), true)],
+                _ => return Err(invalid((value, text), "1 or 0")),
+            },
+            _ => {
+                let side = if field.starts_with("call") { Side::Dx } else { Side::De };
+                let number = match &field[field.len() - 4..] {
+                    "dxcc" => Number::Dxcc(side),
+                    "_itu" => Number::Itu(side),
+                    _ => Number::Zone(side),
+                };
+                let ranges = values
+                    .iter()
+                    .map(|v| numbers(v.1).ok_or_else(|| invalid(*v, "a number like 14 or a range like 14-16")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                vec![test(Test::Number(number, ranges))]
+            }
+        };
+
+        Ok(match exprs.len() {
+            1 => exprs.into_iter().next().unwrap(),
+            _ => Expr::Or(exprs),
+        })
+    }
+}
+
+/// Compile a value of the term `on`, e.g. `hf/cw`, `20m` or `14000/14070`.
+fn frequencies(value: &str) -> Option<Expr> {
+    let value = value.to_ascii_lowercase();
+    let (band, mode) = value.split_once('/').unwrap_or((&value, ""));
+
+    if let (Ok(low), Ok(high)) = (band.parse::<f64>(), mode.parse::<f64>()) {
+        let hz = |khz: f64| (khz.is_finite() && khz >= 0.0).then(|| (khz * 1000.0).round() as u64);
+        return Some(Expr::Test(Test::Number(
+            Number::Freq,
+            vec![hz(low)?..=hz(high)?],
+        )));
+    }
+
+    let bands = match band {
+        "hf" => Band::ALL[..Band::ALL.iter().position(|b| *b == Band::M6)?].to_vec(),
+        "vhf" => vec![Band::M6, Band::M4, Band::M2],
+        "uhf" => vec![Band::Cm70],
+        band if band.ends_with('m') => vec![band.parse().ok()?],
+        _ => return None,
+    };
+    let bands = Expr::Test(Test::Band(bands));
+    let modes = match mode {
+        "" => return Some(bands),
+        "data" | "digi" => Mode::ALL.into_iter().filter(Mode::is_digital).collect(),
+        mode => vec![mode.parse().ok()?],
+    };
+    Some(Expr::And(vec![bands, Expr::Test(Test::Mode(modes))]))
+}
+
+/// Parse a number or a range of numbers like `14-16`.
+fn numbers(value: &str) -> Option<RangeInclusive<u64>> {
+    match value.split_once('-') {
+        Some((low, high)) => Some(low.parse().ok()?..=high.parse().ok()?),
+        None => value.parse().ok().map(|v| v..=v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::callinfo::{CallData, Continent, PrefixTable};
+    use crate::expr::MAX_NESTING;
+    use crate::fixtures::{SPOT, SPOT2, SPOT3};
+
+    const SPOT4: &str =
+        "DX de G4ABC:      50313.0  JA1XYZ       FT8 -12dB                      1200Z";
+    const WWV: &str = "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms";
+    const ANNOUNCE: &str = "To ALL de M0ABC: contest this weekend";
+    const WX: &str = "WX de DL1ABC: sunny";
+
+    fn passing(filter: &SpiderFilter) -> Vec<&'static str> {
+        [SPOT, SPOT2, SPOT3, SPOT4, WWV, ANNOUNCE, WX]
+            .into_iter()
+            .filter(|line| filter.passes(&crate::parse(line).unwrap()))
+            .collect()
+    }
+
+    fn filter(commands: &[&str]) -> SpiderFilter {
+        let mut filter = SpiderFilter::new();
+        for command in commands {
+            filter.apply(command).unwrap();
+        }
+        filter
+    }
+
+    #[test]
+    fn spider_terms() {
+        let dx = |command| {
+            let passing = passing(&filter(&[command]));
+            passing
+                .into_iter()
+                .filter(|l| l.starts_with("DX"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(dx("accept/spots on hf"), vec![SPOT, SPOT2, SPOT3]);
+        assert_eq!(dx("accept/spots on vhf"), vec![SPOT4]);
+        assert_eq!(dx("accept/spots on hf/cw"), vec![SPOT2]);
+        assert_eq!(dx("accept/spots on hf/ssb"), vec![SPOT, SPOT3]);
+        assert_eq!(dx("accept/spots on 6m/data"), vec![SPOT4]);
+        assert_eq!(dx("accept/spots on 80m,20m/ssb"), vec![SPOT, SPOT3]);
+        assert_eq!(dx("accept/spots on 14000/14100"), vec![SPOT2]);
+        assert_eq!(dx("accept/spots call OH,3B"), vec![SPOT, SPOT3]);
+        assert_eq!(dx("accept/spots by D"), vec![SPOT, SPOT3]);
+        assert_eq!(dx("accept/spots by *C*"), vec![SPOT2, SPOT3, SPOT4]);
+        assert_eq!(dx("accept/spots info party"), vec![SPOT2]);
+        assert_eq!(dx("reject/spots on hf and not call W"), vec![SPOT2, SPOT4]);
+        assert_eq!(
+            dx("acc/spot (on 20m or on 6m) and not info ft8"),
+            vec![SPOT2, SPOT3]
+        );
+    }
+
+    #[test]
+    fn spider_slots() {
+        // Reject before accept within a slot, lower slots first
+        let f = filter(&["accept/spots 1 on hf", "reject/spots 1 call W"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT3, WWV, ANNOUNCE, WX]);
+        let f = filter(&["accept/spots 0 call W", "reject/spots 1 on hf"]);
+        assert_eq!(passing(&f), vec![SPOT2, WWV, ANNOUNCE, WX]);
+
+        // Without any accept filter unmatched spots pass
+        let f = filter(&["reject/spots on 80m"]);
+        assert_eq!(passing(&f), vec![SPOT2, SPOT3, SPOT4, WWV, ANNOUNCE, WX]);
+
+        // A filter replaces the one of the same slot
+        let mut f = filter(&["accept/spots on 80m", "accept/spots on 6m"]);
+        assert_eq!(f.rules().len(), 1);
+        assert_eq!(f.rules()[0].to_string(), "accept/spots 1 on 6m");
+        f.apply("accept/spots 2 on 80m").unwrap();
+        assert_eq!(passing(&f), vec![SPOT, SPOT4, WWV, ANNOUNCE, WX]);
+
+        f.apply("clear/spots 1").unwrap();
+        assert_eq!(passing(&f), vec![SPOT, WWV, ANNOUNCE, WX]);
+        f.apply("clear/spots all").unwrap();
+        assert!(f.rules().is_empty());
+    }
+
+    #[test]
+    fn spider_targets() {
+        let f = filter(&["reject/announce by G,M"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT2, SPOT3, SPOT4, WWV, WX]);
+        let f = filter(&["rej/ann wx 1"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT2, SPOT3, SPOT4, WWV, ANNOUNCE]);
+        let f = filter(&["accept/announce info CONTEST"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT2, SPOT3, SPOT4, WWV, ANNOUNCE]);
+        let f = filter(&["accept/wwv by W", "reject/wcy by VE"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT2, SPOT3, SPOT4, ANNOUNCE, WX]);
+
+        let mut f = filter(&["accept/wwv by W", "reject/announce by M"]);
+        f.apply("clear/wwv").unwrap();
+        assert_eq!(f.rules().len(), 1);
+        assert_eq!(f.rules()[0].target, FilterTarget::Announce);
+    }
+
+    #[test]
+    fn spider_call_info() {
+        let mut table = PrefixTable::new();
+        let data = |zone| CallData {
+            continent: Some(Continent::EU),
+            cq_zone: Some(zone),
+            itu_zone: Some(zone + 14),
+            dxcc: Some(u16::from(zone) * 10),
+        };
+        table.insert("DJ", data(14));
+        table.insert("DL", data(14));
+        table.insert("OH", data(15));
+        table.insert("3B9", data(39));
+
+        let f = filter(&["accept/spots on hf/ssb and not by_zone 14"]);
+        let passing = |f: &SpiderFilter| {
+            [SPOT, SPOT2, SPOT3]
+                .into_iter()
+                .filter(|line| f.passes_with(&crate::parse(line).unwrap(), &table))
+                .collect::<Vec<_>>()
+        };
+        assert!(passing(&f).is_empty());
+        let f = filter(&["accept/spots call_zone 15-39"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT3]);
+        let f = filter(&["accept/spots call_itu 53 or by_dxcc 140"]);
+        assert_eq!(passing(&f), vec![SPOT, SPOT3]);
+    }
+
+    #[test]
+    fn spider_errors() {
+        let err = |command: &str| SpiderFilter::new().apply(command).unwrap_err().to_string();
+        assert_eq!(err(""), "Empty filter expression");
+        assert_eq!(
+            err("sh/dx"),
+            "Unexpected \"sh/dx\" at position 0, expected accept/, reject/ or clear/ followed by spots, announce, wwv or wcy"
+        );
+        assert_eq!(
+            err("accept/spots"),
+            "Unexpected end of the expression, expected a filter like on hf/cw"
+        );
+        assert_eq!(
+            err("accept/spots 12 on hf"),
+            "Invalid value \"12\" at position 13 for the field slot, expected a slot from 0 to 9"
+        );
+        assert_eq!(
+            err("accept/spots on 11m"),
+            "Invalid value \"11m\" at position 16 for the field on, expected a band like 20m or hf, optionally with a mode like hf/cw, or a range in kHz like 14000/14070"
+        );
+        assert_eq!(
+            err("accept/spots on hf/morse"),
+            "Invalid value \"hf/morse\" at position 16 for the field on, expected a band like 20m or hf, optionally with a mode like hf/cw, or a range in kHz like 14000/14070"
+        );
+        assert_eq!(
+            err("accept/spots by_zone 14,x"),
+            "Invalid value \"x\" at position 24 for the field by_zone, expected a number like 14 or a range like 14-16"
+        );
+        assert_eq!(
+            err("accept/wwv on hf"),
+            "Unknown field \"on\" at position 11, expected one of by, by_dxcc, by_itu, by_zone"
+        );
+        assert_eq!(
+            err("accept/spots on hf and"),
+            "Unexpected end of the expression, expected a filter like on hf/cw"
+        );
+        assert_eq!(
+            err("accept/spots (on hf"),
+            "Unexpected end of the expression, expected )"
+        );
+        assert_eq!(
+            err("accept/spots on hf call W"),
+            "Unexpected \"call\" at position 19, expected and, or or the end of the filter"
+        );
+        assert_eq!(
+            err("accept/spots on"),
+            "Unexpected end of the expression, expected a value"
+        );
+        assert_eq!(
+            err("clear/spots 1 2"),
+            "Unexpected \"2\" at position 14, expected the end of the command"
+        );
+
+        let mut f = filter(&["accept/spots on 20m"]);
+        assert!(f.apply("accept/spots on 11m").is_err());
+        assert_eq!(f.rules()[0].pattern(), "on 20m");
+    }
+
+    #[test]
+    fn spider_nesting() {
+        let mut spider = SpiderFilter::new();
+        let negations = "not ".repeat(100_000);
+        spider
+            .apply(&format!("accept/spots {}on hf", negations))
+            .unwrap();
+        assert_eq!(passing(&spider), passing(&filter(&["accept/spots on hf"])));
+
+        let nested = |depth| {
+            format!(
+                "reject/spots {}on hf{}",
+                "(".repeat(depth),
+                ")".repeat(depth)
+            )
+        };
+        assert!(spider.apply(&nested(MAX_NESTING)).is_ok());
+        assert_eq!(
+            spider.apply(&nested(MAX_NESTING + 1)).unwrap_err(),
+            ExprError::TooDeep {
+                pos: 13 + MAX_NESTING,
+                max: MAX_NESTING
+            }
+        );
+        assert!(spider
+            .apply(&format!("accept/spots {}on hf", "(not ".repeat(100_000)))
+            .is_err());
+    }
+
+    #[test]
+    fn spider_rule() {
+        let rule: SpiderRule = "REJECT/SPOTS 3 on hf/cw and by G, M".parse().unwrap();
+        assert_eq!(rule.action, FilterAction::Reject);
+        assert_eq!(rule.target, FilterTarget::Spots);
+        assert_eq!(rule.slot, 3);
+        assert_eq!(rule.pattern(), "on hf/cw and by G, M");
+        assert!("clear/spots".parse::<SpiderRule>().is_err());
+    }
+}
@@ -0,0 +1,180 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::command::Command;
+use crate::error::SubmitError;
+use crate::framer::{Boundary, FramedLine};
+use crate::parser::Parser;
+use crate::types::{Spot, DX};
+
+/// Maximum difference (in Hz) between the submitted frequency and the frequency of the echoed spot
+const FREQ_TOLERANCE: u64 = 500;
+
+/// Words of a reply of the cluster rejecting a spot as a duplicate
+const DUPLICATE_REPLIES: &[&str] = &["dup"];
+
+/// Words of a reply of the cluster rejecting a spot since too many spots were sent
+const RATE_REPLIES: &[&str] = &["too many", "too fast", "slow down", "rate limit", "flood"];
+
+/// Words of a reply of the cluster rejecting the callsign of a spot
+const INVALID_CALL_REPLIES: &[&str] = &["invalid", "not a valid", "bad call", "unknown call"];
+
+/// Tracking of a DX spot submitted to a cluster, independent of the transport.
+///
+/// Each line received after the spot was sent is passed to [`Submission::check`].
+/// The spot is confirmed as soon as the cluster echoes it as `DX de <mycall>`,
+/// it is rejected in case the cluster replies with an error.
+///
+/// ```
+/// use dxclparser::{Command, Parser, Submission};
+///
+/// let cmd = Command::dx(14025.0, "3B9FR", Some("599")).unwrap();
+/// let submission = Submission::new("DL1ABC", &cmd).unwrap();
+/// let echo = "DX de DL1ABC:     14025.0  3B9FR        599                            1812Z";
+/// assert!(matches!(submission.check_text(echo, &Parser::new()), Some(Ok(_))));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Submission {
+    call_de: String,
+    call_dx: String,
+    freq: u64,
+}
+
+impl Submission {
+    /// Start tracking a spot.
+    ///
+    /// ## Arguments
+    ///
+    /// * `mycall`: Callsign the client is logged in with
+    /// * `command`: Spot sent to the cluster, must be a [`Command::Dx`]
+    ///
+    /// ## Result
+    ///
+    /// In case the command is a spot, the submission shall be returned.
+    /// Otherwise [`SubmitError::NotSpot`] shall be returned.
+    pub fn new(mycall: &str, command: &Command) -> Result<Self, SubmitError> {
+        match command {
+            Command::Dx { freq, call, .. } => Ok(Submission {
+                call_de: base_call(mycall),
+                call_dx: String::from(call.as_str()),
+                freq: freq.hz(),
+            }),
+            _ => Err(SubmitError::NotSpot),
+        }
+    }
+
+    /// Check a line received from the cluster, see [`Submission::check_text`].
+    /// Prompts never decide on the submission.
+    pub fn check(&self, line: &FramedLine, parser: &Parser) -> Option<Result<DX, SubmitError>> {
+        if line.boundary == Boundary::Prompt {
+            return None;
+        }
+        self.check_text(&String::from_utf8_lossy(&line.bytes), parser)
+    }
+
+    /// Check a line received from the cluster.
+    ///
+    /// ## Arguments
+    ///
+    /// * `line`: Sanitized line received from the cluster
+    /// * `parser`: Parser for the echoed spot
+    ///
+    /// ## Result
+    ///
+    /// In case the line is the echo of the spot, the echoed spot shall be returned.
+    /// In case the line is a reply rejecting the spot, the typed rejection shall be returned.
+    /// In case the line is not related to the spot, `None` shall be returned.
+    pub fn check_text(&self, line: &str, parser: &Parser) -> Option<Result<DX, SubmitError>> {
+        match parser.parse(line) {
+            Ok(Spot::DX(dx)) => {
+                let echo = base_call(&dx.call_de) == self.call_de
+                    && dx.call_dx == self.call_dx
+                    && dx.freq.abs_diff(self.freq) <= FREQ_TOLERANCE;
+                echo.then_some(Ok(dx))
+            }
+            Ok(_) => None,
+            Err(_) => self.reply(line).map(Err),
+        }
+    }
+
+    fn reply(&self, line: &str) -> Option<SubmitError> {
+        let lower = line.to_ascii_lowercase();
+        let contains = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+        let reply = String::from(line);
+
+        if contains(DUPLICATE_REPLIES) {
+            Some(SubmitError::Duplicate(reply))
+        } else if contains(RATE_REPLIES) {
+            Some(SubmitError::RateLimited(reply))
+        } else if contains(INVALID_CALL_REPLIES) && lower.contains("call") {
+            Some(SubmitError::InvalidCall(reply))
+        } else {
+            None
+        }
+    }
+}
+
+/// Get a callsign without the SSID, e.g. `DL1ABC` for `DL1ABC-2`.
+fn base_call(call: &str) -> String {
+    let call = call.to_ascii_uppercase();
+    match call.rsplit_once('-') {
+        Some((base, ssid)) if ssid.bytes().all(|b| b.is_ascii_digit()) => String::from(base),
+        _ => call,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn submission() -> Submission {
+        let cmd = Command::dx(14025.0, "3B9FR", Some("599")).unwrap();
+        Submission::new("dl1abc-2", &cmd).unwrap()
+    }
+
+    #[test]
+    fn submit_echo() {
+        let parser = Parser::new();
+        let echo = "DX de DL1ABC:     14025.1  3B9FR        599                            1812Z";
+        let res = submission().check_text(echo, &parser);
+        assert!(matches!(res, Some(Ok(dx)) if dx.freq == 14025100));
+
+        // Spots of others or of other stations are not related
+        let other = "DX de DK1XY:      14025.0  3B9FR        599                            1812Z";
+        assert!(submission().check_text(other, &parser).is_none());
+        let other = "DX de DL1ABC:     14025.0  3B8CW        599                            1812Z";
+        assert!(submission().check_text(other, &parser).is_none());
+        let other = "DX de DL1ABC:     14027.0  3B9FR        599                            1812Z";
+        assert!(submission().check_text(other, &parser).is_none());
+    }
+
+    #[test]
+    fn submit_replies() {
+        let parser = Parser::new();
+        let check = |line| submission().check_text(line, &parser);
+        assert!(matches!(
+            check("Duplicate spot, not sent"),
+            Some(Err(SubmitError::Duplicate(_)))
+        ));
+        assert!(matches!(
+            check("Sorry, you have sent too many spots, please wait"),
+            Some(Err(SubmitError::RateLimited(_)))
+        ));
+        assert!(matches!(
+            check("Sorry, 3B9FR is not a valid callsign"),
+            Some(Err(SubmitError::InvalidCall(_)))
+        ));
+        assert!(check("Hello DL1ABC").is_none());
+        assert!(check("WX de A1BC: sunny").is_none());
+    }
+
+    #[test]
+    fn submit_no_spot() {
+        assert!(matches!(
+            Submission::new("DL1ABC", &Command::Bye),
+            Err(SubmitError::NotSpot)
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::error::ClientError;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Configuration of a TLS connection to a cluster, e.g. a telnet port wrapped by stunnel.
+///
+/// By default the certificate of the cluster is verified against the Mozilla root certificates.
+/// Clusters using a certificate of a custom CA or a self-signed certificate are trusted by adding that certificate,
+/// see [`TlsConfig::ca_pem`].
+///
+/// ```no_run
+/// let tls = dxclparser::TlsConfig::new()
+///     .ca_file("cluster.pem")
+///     .unwrap();
+/// let client = dxclparser::ClusterClient::builder("DL1ABC")
+///     .connect_tls("dxc.example.org:7373", &tls)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    roots: RootCertStore,
+    accept_invalid_certs: bool,
+    server_name: Option<String>,
+}
+
+impl TlsConfig {
+    /// Create a configuration trusting the Mozilla root certificates.
+    pub fn new() -> Self {
+        TlsConfig {
+            roots: RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            },
+            accept_invalid_certs: false,
+            server_name: None,
+        }
+    }
+
+    /// Create a configuration trusting no certificate at all, certificates need to be added by [`TlsConfig::ca_pem`].
+    pub fn empty() -> Self {
+        TlsConfig {
+            roots: RootCertStore::empty(),
+            ..Self::new()
+        }
+    }
+
+    /// Trust the PEM encoded certificates, either of a custom CA or the self-signed certificate of the cluster.
+    ///
+    /// ## Arguments
+    ///
+    /// * `pem`: One or more PEM encoded certificates
+    ///
+    /// ## Result
+    ///
+    /// In case all certificates were added, the configuration shall be returned.
+    /// In case of an error the occurred error shall be returned.
+    pub fn ca_pem(mut self, pem: &[u8]) -> Result<Self, ClientError> {
+        let mut count = 0;
+        for cert in CertificateDer::pem_slice_iter(pem) {
+            self.roots.add(cert?)?;
+            count += 1;
+        }
+        match count {
+            0 => Err(rustls::pki_types::pem::Error::NoItemsFound.into()),
+            _ => Ok(self),
+        }
+    }
+
+    /// Trust the PEM encoded certificates read from a file, see [`TlsConfig::ca_pem`].
+    pub fn ca_file<P: AsRef<Path>>(self, path: P) -> Result<Self, ClientError> {
+        let pem = std::fs::read(path)?;
+        self.ca_pem(&pem)
+    }
+
+    /// Accept any certificate of the cluster without verifying it.
+    ///
+    /// The connection is still encrypted but open to a man in the middle.
+    /// Prefer trusting the self-signed certificate of the cluster by [`TlsConfig::ca_pem`].
+    pub fn accept_invalid_certs(mut self) -> Self {
+        self.accept_invalid_certs = true;
+        self
+    }
+
+    /// Set the name the certificate of the cluster is verified against, by default the host of the address connected to.
+    pub fn server_name(mut self, name: &str) -> Self {
+        self.server_name = Some(String::from(name));
+        self
+    }
+
+    /// Get the configuration of rustls.
+    pub(crate) fn client_config(&self) -> Result<Arc<ClientConfig>, ClientError> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+
+        let config = if self.accept_invalid_certs {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+                .with_no_client_auth()
+        } else {
+            builder
+                .with_root_certificates(self.roots.clone())
+                .with_no_client_auth()
+        };
+        Ok(Arc::new(config))
+    }
+
+    /// Get the name to verify the certificate against, taken from an address like `host:port` if not configured.
+    pub(crate) fn server_name_of(&self, addr: &str) -> Result<ServerName<'static>, ClientError> {
+        let name = match &self.server_name {
+            Some(name) => name.as_str(),
+            None => {
+                let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+                host.trim_start_matches('[').trim_end_matches(']')
+            }
+        };
+        ServerName::try_from(String::from(name))
+            .map_err(|_| ClientError::ServerName(String::from(name)))
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Verifier accepting any certificate, only the signatures of the handshake are checked
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::client::ClusterClient;
+    use crate::fixtures::SPOT;
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ServerConfig, ServerConnection, StreamOwned};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Generate a self-signed certificate for `localhost`, returned as PEM together with the config of a server using it.
+    fn certificate() -> (String, Arc<ServerConfig>) {
+        let key = rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+        let der = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.signing_key.serialize_der()));
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![key.cert.der().clone()], der)
+            .unwrap();
+        (key.cert.pem(), Arc::new(config))
+    }
+
+    /// TLS echo server, prompting for the login once and then echoing each line received.
+    fn echo_server(config: Arc<ServerConfig>) -> (String, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let conn = ServerConnection::new(config).unwrap();
+            let mut tls = StreamOwned::new(conn, stream);
+            if tls.write_all(b"login: ").is_err() {
+                return;
+            }
+            let mut lines = BufReader::new(tls);
+            let mut line = String::new();
+            while lines.read_line(&mut line).unwrap_or(0) > 0 {
+                let echo = match line.trim_end() {
+                    "DL1ABC" => String::from("DL1ABC de DB0SUE-7 >"),
+                    "bye" => return,
+                    text => format!("{}\r\n", text),
+                };
+                let tls = lines.get_mut();
+                tls.write_all(echo.as_bytes()).unwrap();
+                tls.flush().unwrap();
+                line.clear();
+            }
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn tls_server_name() {
+        let tls = TlsConfig::new();
+        assert_eq!(
+            tls.server_name_of("dxc.example.org:7373").unwrap(),
+            ServerName::try_from("dxc.example.org").unwrap()
+        );
+        assert_eq!(
+            tls.server_name_of("[::1]:7373").unwrap(),
+            ServerName::try_from("::1").unwrap()
+        );
+        assert!(matches!(
+            tls.server_name_of("dxc example:7373"),
+            Err(ClientError::ServerName(_))
+        ));
+
+        let tls = tls.server_name("cluster.example.org");
+        assert_eq!(
+            tls.server_name_of("127.0.0.1:7373").unwrap(),
+            ServerName::try_from("cluster.example.org").unwrap()
+        );
+    }
+
+    #[test]
+    fn tls_ca_pem_invalid() {
+        assert!(matches!(
+            TlsConfig::empty().ca_pem(b"no certificate"),
+            Err(ClientError::Certificate(_))
+        ));
+    }
+
+    #[test]
+    fn tls_client_custom_ca() {
+        let (pem, config) = certificate();
+        let (addr, handle) = echo_server(config);
+
+        let tls = TlsConfig::empty()
+            .ca_pem(pem.as_bytes())
+            .unwrap()
+            .server_name("localhost");
+        let mut client = ClusterClient::builder("DL1ABC")
+            .connect_tls(&addr, &tls)
+            .unwrap();
+        client.send(SPOT).unwrap();
+        assert_eq!(client.next(), Some(crate::parse(SPOT)));
+        client.disconnect().unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn tls_client_untrusted() {
+        let (_, config) = certificate();
+        let (addr, handle) = echo_server(config);
+
+        let tls = TlsConfig::new().server_name("localhost");
+        let res = ClusterClient::builder("DL1ABC").connect_tls(&addr, &tls);
+        assert!(matches!(res, Err(ClientError::Io(_))));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn tls_client_accept_invalid_certs() {
+        let (_, config) = certificate();
+        let (addr, handle) = echo_server(config);
+
+        // The certificate is issued for localhost, not for the address connected to
+        let tls = TlsConfig::empty().accept_invalid_certs();
+        let mut client = ClusterClient::builder("DL1ABC")
+            .connect_tls(&addr, &tls)
+            .unwrap();
+        client.send(SPOT).unwrap();
+        assert_eq!(client.next(), Some(crate::parse(SPOT)));
+        client.disconnect().unwrap();
+        handle.join().unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tls_stream() {
+        use crate::async_client::ClusterStream;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let (pem, config) = certificate();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = tokio_rustls::TlsAcceptor::from(config);
+            let mut tls = acceptor.accept(stream).await.unwrap();
+            tls.write_all(b"login: ").await.unwrap();
+            let mut lines = tokio::io::BufReader::new(tls).lines();
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), "DL1ABC");
+            let tls = lines.get_mut().get_mut();
+            tls.write_all(format!("DL1ABC de DB0SUE-7 >{}\r\n", SPOT).as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), "bye");
+        });
+
+        let tls = TlsConfig::empty()
+            .ca_pem(pem.as_bytes())
+            .unwrap()
+            .server_name("localhost");
+        let mut stream = ClusterStream::builder("DL1ABC")
+            .connect_tls(&addr, &tls)
+            .unwrap();
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            crate::parse(SPOT).unwrap()
+        );
+        stream.shutdown().await;
+        server.await.unwrap();
+    }
+}
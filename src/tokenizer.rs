@@ -0,0 +1,519 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Single-pass tokenizer for the built-in types of spots.
+//!
+//! The tokenizer walks a line once from left to right and makes the same choices as the greedy regular expressions in [`crate::parser`].
+//! It only handles well-formed lines: as soon as a line takes a shape where the regular expression would have to backtrack,
+//! the tokenizer gives up and returns `None`. The caller then falls back to the regular expressions,
+//! which also take care of the detailed errors and the fallback to the columns of a DX spot.
+
+use crate::borrowed::*;
+use std::borrow::Cow;
+
+/// Engine used by a parser to parse the built-in types of spots
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Engine {
+    /// Single-pass tokenizer, falling back to the regular expressions for lines it can not handle
+    #[default]
+    Tokenizer,
+
+    /// Regular expressions only
+    Regex,
+}
+
+/// Tokenize a spot of a built-in type.
+///
+/// ## Arguments
+///
+/// * `raw`: A raw spot that is already cleaned from newline or bell characters etc.
+///
+/// ## Result
+///
+/// In case the spot is well-formed, the spot shall be returned.
+/// Otherwise `None` shall be returned and the spot has to be parsed by the regular expressions.
+pub(crate) fn scan(raw: &str) -> Option<SpotRef<'_>> {
+    match raw.as_bytes().first()? {
+        b'D' => scan_dx(raw).map(SpotRef::DX),
+        b'W' if raw.starts_with("WWV de") => scan_wwv(raw).map(SpotRef::WWV),
+        b'W' if raw.starts_with("WCY de") => scan_wcy(raw).map(SpotRef::WCY),
+        b'W' => {
+            let (call_de, utc, msg) = scan_message(raw, &["WX de"], false)?;
+            Some(SpotRef::WX(WXRef { call_de, utc, msg }))
+        }
+        b'T' if raw.starts_with("To ALL de") => {
+            let (call_de, utc, msg) = scan_message(raw, &["To ALL de"], false)?;
+            Some(SpotRef::ToAll(ToAllRef { call_de, utc, msg }))
+        }
+        b'T' => {
+            let (call_de, utc, msg) = scan_message(raw, &["To LOCAL de", "To Local de"], true)?;
+            Some(SpotRef::ToLocal(ToLocalRef { call_de, utc, msg }))
+        }
+        _ => None,
+    }
+}
+
+/// Tokenize the comment section of a RBN spot.
+///
+/// ## Arguments
+///
+/// * `raw`: A raw comment section of a parsed DX spot.
+///
+/// ## Result
+///
+/// In case the comment is well-formed, the parsed comment shall be returned.
+/// Otherwise `None` shall be returned and the comment has to be parsed by the regular expressions.
+pub(crate) fn scan_rbn(raw: &str) -> Option<RBNRef<'_>> {
+    let mut c = Cursor::new(raw);
+    let mut rbn = RBNRef::new();
+
+    rbn.mode = Cow::Borrowed(c.take(2, usize::MAX, |b| b.is_ascii_alphanumeric())?);
+    c.spaces(1)?;
+    rbn.db = c
+        .take(1, 4, |b| b.is_ascii_digit() || b == b'-')?
+        .parse()
+        .ok()?;
+    c.spaces(1)?;
+    c.literal("dB")?;
+    let spaces = c.spaces(1)?;
+
+    // Only the variant with speed information starts with a digit after the signal strength
+    if c.peek()?.is_ascii_digit() {
+        rbn.speed = Some(c.take(1, 3, |b| b.is_ascii_digit())?.parse().ok()?);
+        c.spaces(1)?;
+        let unit = c.literal("WPM").or_else(|| c.literal("BPS"))?;
+        rbn.speed_unit = Some(Cow::Borrowed(unit));
+        c.spaces(1)?;
+    } else {
+        let loc = c.rest().as_bytes().get(..4);
+        if loc.is_some_and(is_upper_loc) {
+            rbn.loc = Some(Cow::Borrowed(&c.rest()[..4]));
+            c.pos += 4;
+            c.spaces(1)?;
+        } else if spaces < 2 {
+            return None;
+        }
+    }
+
+    let info = c.rest();
+    if info.is_empty() || !info.bytes().all(|b| b.is_ascii_alphabetic() || b == b' ') {
+        return None;
+    }
+    rbn.info = Cow::Borrowed(info);
+
+    Some(rbn)
+}
+
+fn scan_dx(raw: &str) -> Option<DXRef<'_>> {
+    let mut c = Cursor::new(raw);
+    let mut dx = DXRef::new();
+
+    c.literal("DX de")?;
+    c.spaces(1)?;
+    dx.call_de = Cow::Borrowed(c.take(3, usize::MAX, is_call_char)?);
+    match c.peek()? {
+        b':' => c.pos += 1,
+        b' ' => {}
+        _ => return None,
+    }
+    c.spaces(0);
+
+    let freq_start = c.pos;
+    c.take(1, usize::MAX, |b| b.is_ascii_digit())?;
+    c.literal(".")?;
+    c.take(1, 2, |b| b.is_ascii_digit())?;
+    let freq = raw[freq_start..c.pos].parse::<f64>().ok()?;
+    dx.freq = (freq * 1000.0).round() as u64;
+    if dx.freq == 0 {
+        return None;
+    }
+    c.spaces(0);
+    dx.call_dx = Cow::Borrowed(c.take(3, usize::MAX, is_call_char)?);
+
+    // Time and locator are taken from the end of the line, the comment is everything in between
+    let rest = c.rest().as_bytes();
+    let mut end = rest.len();
+    if rest.last() != Some(&b'Z') {
+        let loc = rest.get(end.checked_sub(4)?..)?;
+        if !(is_word(loc[0]) && is_word(loc[1]) && loc[2..].iter().all(u8::is_ascii_digit)) {
+            return None;
+        }
+        dx.loc = Some(Cow::Borrowed(&c.rest()[end - 4..]));
+        end -= 4;
+        while end > 0 && rest[end - 1] == b' ' {
+            end -= 1;
+        }
+    }
+    let time = end.checked_sub(5)?;
+    if rest[end - 1] != b'Z'
+        || !rest[time..end - 1].iter().all(u8::is_ascii_digit)
+        || rest.get(time.checked_sub(1)?) != Some(&b' ')
+    {
+        return None;
+    }
+    dx.utc = c.rest()[time..end - 1].parse().ok()?;
+
+    let middle = &c.rest()[..time];
+    if !middle.starts_with(' ') || !is_text(middle) {
+        return None;
+    }
+    let comment = middle.trim_matches(' ');
+    if comment.is_empty() {
+        if middle.len() < 2 {
+            return None;
+        }
+    } else {
+        dx.comment = Some(Cow::Borrowed(comment));
+    }
+
+    Some(dx)
+}
+
+fn scan_wwv(raw: &str) -> Option<WWVRef<'_>> {
+    let mut c = Cursor::new(raw);
+    let mut wwv = WWVRef::new();
+
+    c.literal("WWV de")?;
+    c.spaces(1)?;
+    wwv.call_de = Cow::Borrowed(c.take(1, usize::MAX, is_call_char)?);
+    c.spaces(1)?;
+    c.literal("<")?;
+    wwv.utc = c.take(2, 2, |b| b.is_ascii_digit())?.parse().ok()?;
+    c.literal("Z");
+    c.literal(">")?;
+    c.spaces(0);
+    c.literal(":")?;
+    c.spaces(0);
+    c.literal("SFI=")?;
+    wwv.sfi = c.number()?;
+    c.literal(", A=")?;
+    wwv.a = c.number()?;
+    c.literal(", K=")?;
+    wwv.k = c.number()?;
+    c.literal(", ")?;
+
+    let rest = c.rest();
+    let arrow = rest.rfind("->")?;
+    let info1 = rest[..arrow].trim_end_matches(' ');
+    let info2 = rest[arrow + 2..].trim_matches(' ');
+    for info in [info1, info2] {
+        if !info.bytes().last().is_some_and(is_word) || !is_text(info) {
+            return None;
+        }
+    }
+    wwv.info1 = Cow::Borrowed(info1);
+    wwv.info2 = Cow::Borrowed(info2);
+
+    Some(wwv)
+}
+
+fn scan_wcy(raw: &str) -> Option<WCYRef<'_>> {
+    let mut c = Cursor::new(raw);
+    let mut wcy = WCYRef::new();
+
+    c.literal("WCY de")?;
+    c.spaces(1)?;
+    wcy.call_de = Cow::Borrowed(c.take(1, usize::MAX, is_call_char)?);
+    c.spaces(1)?;
+    c.literal("<")?;
+    wcy.utc = c.take(2, 2, |b| b.is_ascii_digit())?.parse().ok()?;
+    c.literal(">")?;
+    c.spaces(0);
+    c.literal(":")?;
+    c.spaces(1)?;
+    c.literal("K=")?;
+    wcy.k = c.number()?;
+    c.literal(" expK=")?;
+    wcy.expk = c.number()?;
+    c.literal(" A=")?;
+    wcy.a = c.number()?;
+    c.literal(" R=")?;
+    wcy.r = c.number()?;
+    c.literal(" SFI=")?;
+    wcy.sfi = c.number()?;
+    c.literal(" SA=")?;
+    wcy.sa = Cow::Borrowed(c.take(1, 3, |b| b.is_ascii_alphabetic())?);
+    c.literal(" GMF=")?;
+    wcy.gmf = Cow::Borrowed(c.take(1, 3, |b| b.is_ascii_alphabetic())?);
+    c.literal(" Au=")?;
+    wcy.au = Cow::Borrowed(c.take(2, 6, |b| b.is_ascii_alphabetic())?);
+    c.spaces(0);
+    if !c.rest().is_empty() {
+        return None;
+    }
+
+    Some(wcy)
+}
+
+type Message<'a> = (Cow<'a, str>, Option<u16>, Option<Cow<'a, str>>);
+
+/// Tokenize a spot of the types WX, ToAll or ToLocal, which only differ in their prefix and the spacing in front of the time.
+fn scan_message<'a>(
+    raw: &'a str,
+    prefixes: &[&'static str],
+    spaced_time: bool,
+) -> Option<Message<'a>> {
+    let mut c = Cursor::new(raw);
+
+    prefixes.iter().find_map(|p| c.literal(p))?;
+    c.spaces(1)?;
+    let call_de = Cow::Borrowed(c.take(0, usize::MAX, is_call_char)?);
+
+    // The time is optional, in case it is not followed by a separator the regular expression would have to backtrack
+    let mut utc = None;
+    let start = c.pos;
+    let spaces = if spaced_time {
+        c.spaces(1)
+    } else {
+        c.literal(" ").map(|_| 1)
+    };
+    if spaces.is_some() || !spaced_time {
+        if let Some(time) = c.time() {
+            utc = Some(time);
+            c.separator()?;
+        }
+    }
+    if utc.is_none() {
+        c.pos = start;
+        c.separator()?;
+    }
+
+    let msg = c.rest();
+    if !is_text(msg) {
+        return None;
+    }
+
+    Some((call_de, utc, Some(Cow::Borrowed(msg))))
+}
+
+/// Position within a line, all methods only advance in case the requested token was found.
+struct Cursor<'a> {
+    raw: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(raw: &'a str) -> Self {
+        Cursor { raw, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.raw.as_bytes().get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.raw[self.pos..]
+    }
+
+    /// Consume a literal.
+    fn literal(&mut self, lit: &'static str) -> Option<&'a str> {
+        if self.rest().starts_with(lit) {
+            self.pos += lit.len();
+            Some(&self.raw[self.pos - lit.len()..self.pos])
+        } else {
+            None
+        }
+    }
+
+    /// Consume as many spaces as possible, at least `min`.
+    fn spaces(&mut self, min: usize) -> Option<usize> {
+        self.take(min, usize::MAX, |b| b == b' ').map(str::len)
+    }
+
+    /// Consume as many bytes of a class as possible, up to `max`.
+    /// In case there are less than `min` bytes, or more than `max` bytes, nothing is consumed.
+    fn take(&mut self, min: usize, max: usize, class: impl Fn(u8) -> bool) -> Option<&'a str> {
+        let rest = self.rest().as_bytes();
+        let len = rest.iter().take_while(|&&b| class(b)).count();
+        if len < min || len > max {
+            return None;
+        }
+
+        self.pos += len;
+        Some(&self.raw[self.pos - len..self.pos])
+    }
+
+    /// Consume a number of one to three digits.
+    fn number(&mut self) -> Option<u16> {
+        self.take(1, 3, |b| b.is_ascii_digit())?.parse().ok()
+    }
+
+    /// Consume a time in angle brackets, e.g. `<1044Z>`.
+    fn time(&mut self) -> Option<u16> {
+        let start = self.pos;
+        let time = self
+            .literal("<")
+            .and_then(|_| self.take(4, 4, |b| b.is_ascii_digit()));
+        match time {
+            Some(time) if self.literal("Z>").is_some() => time.parse().ok(),
+            _ => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    /// Consume the separator in front of a message, made of spaces and colons.
+    fn separator(&mut self) -> Option<()> {
+        self.take(1, usize::MAX, |b| b == b' ' || b == b':')
+            .map(|_| ())
+    }
+}
+
+fn is_call_char(b: u8) -> bool {
+    b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'/' || b == b'-' || b == b'#'
+}
+
+fn is_word(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn is_upper_loc(b: &[u8]) -> bool {
+    b[0].is_ascii_uppercase() && b[1].is_ascii_uppercase() && b[2..].iter().all(u8::is_ascii_digit)
+}
+
+/// Check that free text holds neither control characters nor whitespace other than spaces.
+fn is_text(val: &str) -> bool {
+    val.chars()
+        .all(|c| c == ' ' || !(c.is_whitespace() || c.is_control()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::Parser;
+    use crate::types::*;
+
+    const LINES: &[&str] = &[
+        "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62",
+        "DX de N2CQ:      14036.1  W0BH         OK QSO Party: Major            1624Z",
+        "DX de ZS6WN:     21075.4  CX2DAJ       FT8                            1625Z",
+        "DX de OZ1FJB:     3527.6  DL2ASG                                      1815Z JO55",
+        "DX de KE8GX:     14025,0  3B9FR        599 into N. MI                 1812Z",
+        "DX de KE8GX:     14025.0  3B9FR        599 into N. MI                 2512Z",
+        "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO6",
+        "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z ZZ62",
+        "DX de DJ1TO:      3780.0  OH5Z         LSB 1200Z qrv                  2200Z",
+        "DX de DJ1TO:      3780.0  OH5Z         tnx für QSO                    2200Z",
+        "DX de DJ1TO:      3780.0  OH5Z         LSB\tup                        2200Z",
+        "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200ZJO62",
+        "DX de DJ1TO:      3780.0  OH5Z         LSB                            2200Z JO62 ",
+        "DX de DJ1TO:      3780.0  OH5Z 2200Z",
+        "DX de DJ1TO:      3780.0  OH5Z  2200Z",
+        "DX de DJ1TO 3780.00 OH5Z  2200Z",
+        "DX de DJ1TO:3780.000 OH5Z  2200Z",
+        "DX de PA/DL1ABC/P14025.0  3B9FR        599                            1812Z JO62",
+        "DX de DL1ABC:     14025.0  VP2V/DL1ABC/MMtnx fer qso                 1812Z",
+        "DX de DF2MX",
+        "WWV de VE7CC <15Z> :   SFI=68, A=9, K=2, No Storms -> Minor w/G1",
+        "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms -> No Storms",
+        "WWV de VE7CC <21>:   SFI=70, A=12, K=3, No Storms. -> No Storms",
+        "WWV de VE7CC <21>:   SFI=70, A=12, K=3, a -> b -> c  ",
+        "WWV de VE7CC <21>:   SFI=70, A=12, K=3, a -> b.",
+        "WCY de DK0WCY-1 <17> : K=2 expK=3 A=15 R=0 SFI=68 SA=qui GMF=min Au=no",
+        "WCY de DK0WCY-1 <22> : K=9 expK=0 A=139 R=156 SFI=223 SA=maj GMF=sev Au=aurora",
+        "WCY de DK0WCY-1 <17> : K=2 expK=3 A=15 R=x SFI=68 SA=qui GMF=min Au=no",
+        "WCY de DK0WCY-1 <17> : K=2 expK=3 A=15 R=0 SFI=68 SA=quiet GMF=min Au=no",
+        "WX de LA3WAA <1001Z> :  The command WX will send a local weather announcement.  (WX Sunny and Warm)",
+        "WX de VA3SAE: va3sub",
+        "WX de VA3SAE  <1001Z> : sunny",
+        "WX de VA3SAE <1001Z>sunny",
+        "WX de VA3SAE <10Z> sunny",
+        "WX de VA3SAE :",
+        "WX de OZ4AEC",
+        "To ALL de CT2IDL <1044Z> : TNX qso..",
+        "To ALL de CT2IDL <1075Z> : TNX qso..",
+        "To ALL de EA8CEN-9: carnaval de tenerife ea8urt",
+        "To ALL de SV5FRI-1",
+        "To LOCAL de IW5CLM: off",
+        "To Local de N5UXT <1405Z> : rebooting",
+        "To Local de N5UXT   <1405Z>: rebooting",
+        "To Local de N5UXT<1405Z> : rebooting",
+        "To Local de N5UXT",
+        "Hello world",
+        "",
+    ];
+
+    const COMMENTS: &[&str] = &[
+        "FT8  -12 dB  FK68    CQ",
+        "FT8  -15 dB   6 BPS  CQ",
+        "RTTY  10 dB  45 BPS  CQ",
+        "CW     9 dB  21 WPM  NCDXF B",
+        "CW     9 dB  21 KPH  NCDXF B",
+        "CW     9 dB  21 WPM  ",
+        "FT8  -12 dB  CQ",
+        "FT8  -12 dB CQ",
+        "FT8  -12 dB  FK68CQ",
+        "FT8  -12 dB  fk68  CQ",
+        "FT8  1-2 dB  FK68    CQ",
+        "FT8",
+    ];
+
+    fn engines() -> (Parser, Parser) {
+        let tokenizer = Parser::new();
+        let regex = Parser::builder().engine(Engine::Regex).build().unwrap();
+        (tokenizer, regex)
+    }
+
+    #[test]
+    fn tokenizer_same_as_regex() {
+        let (tokenizer, regex) = engines();
+        for line in LINES {
+            assert_eq!(tokenizer.parse(line), regex.parse(line), "{}", line);
+        }
+        for comment in COMMENTS {
+            assert_eq!(
+                tokenizer.parse_rbn(comment),
+                regex.parse_rbn(comment),
+                "{}",
+                comment
+            );
+        }
+    }
+
+    #[test]
+    fn tokenizer_same_as_regex_corpus() {
+        let (tokenizer, regex) = engines();
+        let corpus = concat!(
+            include_str!("../benches/data/dxspider.txt"),
+            include_str!("../benches/data/rbn.txt")
+        );
+        for line in corpus.lines() {
+            let res = tokenizer.parse(line);
+            assert_eq!(res, regex.parse(line), "{}", line);
+            if let Ok(Spot::DX(DX {
+                comment: Some(comment),
+                ..
+            })) = res
+            {
+                assert_eq!(tokenizer.parse_rbn(&comment), regex.parse_rbn(&comment));
+            }
+        }
+    }
+
+    #[test]
+    fn tokenizer_handles_well_formed() {
+        for line in &LINES[..4] {
+            assert!(scan(line).is_some(), "{}", line);
+        }
+        assert!(scan("WWV de VE7CC <15Z> :   SFI=68, A=9, K=2, No Storms -> Minor w/G1").is_some());
+        assert!(scan("To Local de N5UXT <1405Z> : rebooting").is_some());
+        assert!(scan_rbn("FT8  -12 dB  FK68    CQ").is_some());
+        assert!(scan_rbn("CW     9 dB  21 WPM  NCDXF B").is_some());
+    }
+
+    #[test]
+    fn tokenizer_disabled_type() {
+        let parser = Parser::builder()
+            .spot_types(&[SpotType::WWV])
+            .build()
+            .unwrap();
+        let res = parser.parse(LINES[0]);
+        assert_eq!(
+            res.map_err(|e| e.kind()),
+            Err(crate::ParseErrorKind::UnknownType)
+        );
+    }
+}
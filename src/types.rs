@@ -2,10 +2,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 /// Structured representation of a parsed spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum Spot {
     /// Spot of the type DX
     DX(DX),
@@ -24,6 +25,9 @@ pub enum Spot {
 
     /// Spot of the type ToLocal
     ToLocal(ToLocal),
+
+    /// Spot of a user-defined type
+    Custom(Custom),
 }
 
 impl Spot {
@@ -31,10 +35,145 @@ impl Spot {
     pub fn to_json(&self) -> String {
         serde_json::to_string(&self).unwrap()
     }
+
+    /// Get the built-in type of the spot, `None` for a custom spot
+    pub fn spot_type(&self) -> Option<SpotType> {
+        match self {
+            Spot::DX(_) => Some(SpotType::DX),
+            Spot::WWV(_) => Some(SpotType::WWV),
+            Spot::WCY(_) => Some(SpotType::WCY),
+            Spot::WX(_) => Some(SpotType::WX),
+            Spot::ToAll(_) => Some(SpotType::ToAll),
+            Spot::ToLocal(_) => Some(SpotType::ToLocal),
+            Spot::Custom(_) => None,
+        }
+    }
+}
+
+/// Built-in types of spots
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SpotType {
+    /// Spot of the type DX
+    DX,
+
+    /// Spot of the type WWV
+    WWV,
+
+    /// Spot of the type WCY
+    WCY,
+
+    /// Spot of the type WX
+    WX,
+
+    /// Spot of the type ToAll
+    ToAll,
+
+    /// Spot of the type ToLocal
+    ToLocal,
+}
+
+impl SpotType {
+    /// All built-in types of spots
+    pub const ALL: [SpotType; 6] = [
+        SpotType::DX,
+        SpotType::WWV,
+        SpotType::WCY,
+        SpotType::WX,
+        SpotType::ToAll,
+        SpotType::ToLocal,
+    ];
+}
+
+/// Cluster software a spot originates from
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum ClusterFlavor {
+    /// Cluster software is not known
+    #[default]
+    Unknown,
+
+    /// DXSpider
+    DXSpider,
+
+    /// AR-Cluster
+    ARCluster,
+
+    /// CC Cluster
+    CCCluster,
+
+    /// Reverse Beacon Network
+    RBN,
+}
+
+/// Calendar date used to put the time of a spot into context
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Date {
+    /// Year
+    pub year: u16,
+
+    /// Month (1 to 12)
+    pub month: u8,
+
+    /// Day of month (1 to 31)
+    pub day: u8,
+}
+
+impl Date {
+    /// Create a date from its year, month (1 to 12) and day of month (1 to 31).
+    pub fn new(year: u16, month: u8, day: u8) -> Self {
+        Date { year, month, day }
+    }
+
+    /// Get the seconds since the unix epoch for a time (`HHMM` in UTC) on this date.
+    pub fn timestamp(&self, utc: u16) -> u64 {
+        // Days since epoch, see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let y = i64::from(self.year) - i64::from(self.month <= 2);
+        let m = i64::from(self.month);
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+
+        let secs = u64::from(utc / 100) * 3600 + u64::from(utc % 100) * 60;
+        (days.max(0) as u64) * 86400 + secs
+    }
+
+    /// Get the date of a time given in seconds since the unix epoch, the inverse of [`Date::timestamp`].
+    pub fn from_timestamp(secs: u64) -> Self {
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = (secs / 86400) as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Date::new(year as u16, month as u8, day as u8)
+    }
+}
+
+/// Spot of a user-defined type
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Custom {
+    /// Name of the custom type
+    pub kind: String,
+
+    /// Payload produced by the parser of the custom type
+    pub payload: serde_json::Value,
+}
+
+impl Custom {
+    /// Convert the payload into the type produced by the parser of the custom type.
+    pub fn payload_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.payload)
+    }
 }
 
 /// DX Spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct DX {
     /// Call of spotting station
     pub call_de: String,
@@ -53,6 +192,10 @@ pub struct DX {
 
     /// Comment
     pub comment: Option<String>,
+
+    /// The comment filled its whole column and was probably truncated by the cluster
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
 }
 
 impl DX {
@@ -64,6 +207,7 @@ impl DX {
             utc: 0,
             loc: None,
             comment: None,
+            truncated: false,
         }
     }
 }
@@ -75,7 +219,7 @@ impl Default for DX {
 }
 
 /// RBN spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct RBN {
     /// Mode: CW, RTTY or FT8
     pub mode: String,
@@ -116,7 +260,7 @@ impl Default for RBN {
 }
 
 /// WWV spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct WWV {
     /// Call of spotting station
     pub call_de: String,
@@ -161,7 +305,7 @@ impl Default for WWV {
 }
 
 /// WCY spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct WCY {
     /// Call of spotting station
     pub call_de: String,
@@ -218,7 +362,7 @@ impl Default for WCY {
 }
 
 /// WX spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct WX {
     /// Call of spotting station
     pub call_de: String,
@@ -247,7 +391,7 @@ impl Default for WX {
 }
 
 /// To all spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct ToAll {
     /// Call of spotting station
     pub call_de: String,
@@ -276,7 +420,7 @@ impl Default for ToAll {
 }
 
 /// To local spot
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct ToLocal {
     /// Call of spotting station
     pub call_de: String,